@@ -0,0 +1 @@
+ALTER TYPE public.provider ADD VALUE 'webpush';
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS public.delivery_queue
+(
+    id              varchar(255) primary key default gen_random_uuid(),
+    tenant_id       varchar(255) not null,
+    client_id       varchar(255) not null,
+    notification_id varchar(255) not null,
+
+    payload         jsonb        not null,
+
+    attempts        integer      not null default 0,
+    last_error      text         null,
+
+    available_at    timestamptz  not null default now(),
+    locked_until    timestamptz  null,
+    failed_at       timestamptz  null,
+    created_at      timestamptz  not null default now(),
+
+    CONSTRAINT fk_delivery_queue_client_id FOREIGN KEY (client_id)
+        REFERENCES public.clients (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS delivery_queue_available_at_idx
+    ON public.delivery_queue (available_at)
+    WHERE failed_at IS NULL;
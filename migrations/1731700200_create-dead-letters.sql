@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS public.dead_letters
+(
+    id              varchar(255) primary key default gen_random_uuid(),
+    tenant_id       varchar(255) not null,
+    client_id       varchar(255) not null,
+    notification_id varchar(255) not null,
+
+    -- Message contents are stripped, the original payload is kept in `notifications`
+    payload         jsonb        not null,
+
+    error           text         not null,
+    attempts        integer      not null default 1,
+
+    created_at      timestamptz  not null default now(),
+    updated_at      timestamptz  not null default now(),
+
+    CONSTRAINT fk_dead_letters_client_id FOREIGN KEY (client_id)
+        REFERENCES public.clients (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS dead_letters_tenant_id_created_at_idx
+    ON public.dead_letters (tenant_id, created_at DESC);
@@ -0,0 +1,7 @@
+CREATE TYPE public.notification_status AS ENUM ('accepted', 'sent', 'failed');
+
+ALTER TABLE public.notifications
+    ADD COLUMN status              public.notification_status not null default 'accepted',
+    ADD COLUMN failure_reason      text                       null,
+    ADD COLUMN provider_message_id text                       null,
+    ADD COLUMN status_updated_at   timestamptz                not null default now();
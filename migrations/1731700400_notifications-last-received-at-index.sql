@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS notifications_last_received_at_idx
+    ON public.notifications (last_received_at);
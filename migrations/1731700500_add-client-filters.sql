@@ -0,0 +1,4 @@
+ALTER TABLE public.clients
+    ADD COLUMN allowed_tags bigint[] null,
+    ADD COLUMN denied_tags  bigint[] not null default '{}',
+    ADD COLUMN muted_topics text[]   not null default '{}';
@@ -0,0 +1,7 @@
+CREATE TYPE public.quiet_hours_mode AS ENUM ('silent', 'defer');
+
+ALTER TABLE public.clients
+    ADD COLUMN timezone          text                    null,
+    ADD COLUMN quiet_hours_start time                    null,
+    ADD COLUMN quiet_hours_end   time                    null,
+    ADD COLUMN quiet_hours_mode  public.quiet_hours_mode not null default 'silent';
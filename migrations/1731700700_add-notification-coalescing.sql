@@ -0,0 +1,6 @@
+ALTER TABLE public.notifications
+    ADD COLUMN topic        text null,
+    ADD COLUMN collapse_key text null;
+
+CREATE INDEX IF NOT EXISTS notifications_coalescing_idx
+    ON public.notifications (tenant_id, client_id, topic, last_received_at DESC);
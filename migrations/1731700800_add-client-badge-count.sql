@@ -0,0 +1,2 @@
+ALTER TABLE public.clients
+    ADD COLUMN badge_count integer not null default 0;
@@ -0,0 +1,2 @@
+ALTER TABLE public.clients
+    ADD COLUMN locale text null;
@@ -0,0 +1,2 @@
+ALTER TABLE public.clients
+    ADD COLUMN apns_topic text null;
@@ -0,0 +1,10 @@
+CREATE TYPE public.client_status AS ENUM ('active', 'suspended');
+
+ALTER TABLE public.clients
+    ADD COLUMN status       public.client_status not null default 'active',
+    ADD COLUMN strikes      integer not null default 0,
+    ADD COLUMN suspended_at timestamptz null;
+
+CREATE INDEX IF NOT EXISTS clients_suspended_idx
+    ON public.clients (suspended_at)
+    WHERE status = 'suspended';
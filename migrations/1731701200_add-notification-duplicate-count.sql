@@ -0,0 +1,7 @@
+ALTER TABLE public.notifications
+    ADD COLUMN duplicate_count integer not null default 0;
+
+-- A notification has at most one open delivery
+CREATE UNIQUE INDEX IF NOT EXISTS delivery_queue_notification_idx
+    ON public.delivery_queue (client_id, notification_id)
+    WHERE failed_at IS NULL;
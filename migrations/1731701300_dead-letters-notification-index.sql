@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS dead_letters_notification_idx
+    ON public.dead_letters (client_id, notification_id);
@@ -2,11 +2,19 @@ use {
     crate::error::{Error, Result},
     base64::Engine as _,
     serde::{Deserialize, Serialize},
+    serde_json::{Map, Value},
 };
 
 pub type Flag = u32;
 pub const ENCRYPTED_FLAG: Flag = 1 << 0;
 
+/// Message data key holding the URL of the image to show with a plain
+/// notification
+pub const IMAGE_DATA_KEY: &str = "image";
+/// Message data key holding the deep link to open when a plain notification is
+/// tapped
+pub const URL_DATA_KEY: &str = "url";
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct DecryptedPayloadBlob {
     pub title: String,
@@ -22,4 +30,13 @@ impl DecryptedPayloadBlob {
             .map_err(Error::DecryptedNotificationDecode)?;
         serde_json::from_slice(&blob_decoded).map_err(Error::DecryptedNotificationParse)
     }
+
+    /// The rich content which is sent to clients as message data, keys are
+    /// only present when the blob has them
+    pub fn rich_data(&self) -> Map<String, Value> {
+        [(IMAGE_DATA_KEY, &self.image), (URL_DATA_KEY, &self.url)]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), Value::from(value.clone()?))))
+            .collect()
+    }
 }
@@ -5,13 +5,13 @@ use {
             Error,
             Error::{InvalidConfiguration, NoApnsConfigured},
         },
-        stores::tenant::ApnsType,
+        stores::tenant::{ApnsType, ClientAuthMode},
     },
     serde::Deserialize,
 };
 
 #[cfg(not(feature = "multitenant"))]
-use crate::providers::ProviderKind;
+use crate::{providers::ProviderKind, stores::tenant::MAX_COALESCE_WINDOW_SECS};
 
 #[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Config {
@@ -24,9 +24,28 @@ pub struct Config {
     pub log_level_otel: String,
     #[serde(default = "default_disable_header")]
     pub disable_header: bool,
+    /// Comma separated hex encoded keys, may be empty when the keys are
+    /// loaded from `relay_public_keys_source`
+    #[serde(default)]
     pub relay_public_key: String,
+    /// File path or HTTP endpoint serving the relay's key set, reloaded so
+    /// the relay can rotate its keys
+    pub relay_public_keys_source: Option<String>,
+    #[serde(default = "default_relay_public_keys_refresh_secs")]
+    pub relay_public_keys_refresh_secs: u64,
     #[serde(default = "default_validate_signatures")]
     pub validate_signatures: bool,
+    /// How far the signature timestamp may be from the current time, a
+    /// signature is only accepted once within this window
+    #[serde(default = "default_signature_max_clock_skew_secs")]
+    pub signature_max_clock_skew_secs: u64,
+    /// Largest signed request body that is read
+    #[serde(default = "default_max_request_body_bytes")]
+    pub max_request_body_bytes: usize,
+    /// Whether client registrations and deletions require an `Authorization`
+    /// header, tenants can override this
+    #[serde(default)]
+    pub client_auth_mode: ClientAuthMode,
     pub database_url: String,
     #[serde(default = "default_is_test", skip)]
     /// This is an internal flag to disable logging, cannot be defined by user
@@ -36,6 +55,59 @@ pub struct Config {
     #[serde(default = "default_cors_allowed_origins")]
     pub cors_allowed_origins: Vec<String>,
 
+    // Provider retries
+    #[serde(default = "default_retry_max_attempts")]
+    pub retry_max_attempts: u32,
+    #[serde(default = "default_retry_initial_backoff_ms")]
+    pub retry_initial_backoff_ms: u64,
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub retry_max_backoff_ms: u64,
+    #[serde(default = "default_retry_max_elapsed_ms")]
+    pub retry_max_elapsed_ms: u64,
+
+    // Delivery queue
+    /// When enabled push requests are persisted and delivered by background
+    /// workers instead of inline
+    #[serde(default)]
+    pub queue_enabled: bool,
+    #[serde(default = "default_queue_workers")]
+    pub queue_workers: usize,
+    #[serde(default = "default_queue_poll_interval_ms")]
+    pub queue_poll_interval_ms: u64,
+    #[serde(default = "default_queue_lease_secs")]
+    pub queue_lease_secs: u64,
+    #[serde(default = "default_queue_max_attempts")]
+    pub queue_max_attempts: u32,
+
+    // Deduplication
+    /// How long an accepted but not yet sent notification is considered in
+    /// flight, after which a redelivery from the relay is attempted again
+    #[serde(default = "default_notification_in_flight_timeout_secs")]
+    pub notification_in_flight_timeout_secs: u64,
+    /// How long notifications are kept, this is also the deduplication window
+    /// as a redelivery of a pruned notification is sent again. Notifications
+    /// with a dead letter are kept until it's replayed or deleted.
+    #[serde(default = "default_notification_retention_secs")]
+    pub notification_retention_secs: u64,
+    #[serde(default = "default_notification_pruning_interval_secs")]
+    pub notification_pruning_interval_secs: u64,
+    #[serde(default = "default_notification_pruning_batch_size")]
+    pub notification_pruning_batch_size: i64,
+
+    // Client suspension
+    /// Consecutive bad device token responses after which a client is
+    /// suspended instead of being sent to
+    #[serde(default = "default_client_strike_limit")]
+    pub client_strike_limit: u32,
+    /// How long a suspended client is kept for re-registration before it is
+    /// pruned
+    #[serde(default = "default_client_suspension_grace_secs")]
+    pub client_suspension_grace_secs: u64,
+    #[serde(default = "default_client_pruning_interval_secs")]
+    pub client_pruning_interval_secs: u64,
+    #[serde(default = "default_client_pruning_batch_size")]
+    pub client_pruning_batch_size: i64,
+
     // TELEMETRY
     pub otel_exporter_otlp_endpoint: Option<String>,
     pub telemetry_prometheus_port: Option<u16>,
@@ -45,6 +117,10 @@ pub struct Config {
     pub apns_type: Option<ApnsType>,
     #[cfg(not(feature = "multitenant"))]
     pub apns_topic: Option<String>,
+    /// Further bundle ids sharing the token credentials
+    #[cfg(not(feature = "multitenant"))]
+    #[serde(default)]
+    pub apns_topics: Vec<String>,
 
     #[cfg(not(feature = "multitenant"))]
     pub apns_certificate: Option<String>,
@@ -64,10 +140,43 @@ pub struct Config {
     #[cfg(not(feature = "multitenant"))]
     pub fcm_v1_credentials: Option<String>,
 
+    // Web Push
+    #[cfg(not(feature = "multitenant"))]
+    pub webpush_vapid_private_key: Option<String>,
+    #[cfg(not(feature = "multitenant"))]
+    pub webpush_vapid_subject: Option<String>,
+
+    // Coalescing
+    #[cfg(not(feature = "multitenant"))]
+    pub coalesce_window_secs: Option<u32>,
+
+    // Retry tokens rejected by one APNs environment against the other one
+    #[cfg(not(feature = "multitenant"))]
+    #[serde(default)]
+    pub apns_environment_fallback: bool,
+
     // Multi-tenancy
     pub tenant_database_url: String,
+    /// Secret for HS256 tenant management tokens, may be empty when the keys
+    /// are loaded from `jwt_jwks_source`
     #[cfg(feature = "multitenant")]
+    #[serde(default)]
     pub jwt_secret: String,
+    /// File path or HTTP endpoint serving the JWKS document for RS256, ES256
+    /// and EdDSA tenant management tokens
+    #[cfg(feature = "multitenant")]
+    pub jwt_jwks_source: Option<String>,
+    #[cfg(feature = "multitenant")]
+    #[serde(default = "default_jwt_jwks_refresh_secs")]
+    pub jwt_jwks_refresh_secs: u64,
+    #[cfg(feature = "multitenant")]
+    pub jwt_audience: Option<String>,
+    #[cfg(feature = "multitenant")]
+    pub jwt_issuer: Option<String>,
+    /// Reject tenant management tokens without a `scope` claim
+    #[cfg(feature = "multitenant")]
+    #[serde(default)]
+    pub jwt_require_scopes: bool,
 
     // Analytics
     #[cfg(any(feature = "analytics", feature = "geoblock"))]
@@ -96,6 +205,18 @@ impl Config {
                         .to_string(),
                 ));
             }
+
+            if self.jwt_secret.is_empty() && self.jwt_jwks_source.is_none() {
+                return Err(InvalidConfiguration(
+                    "`JWT_SECRET` cannot be empty without `JWT_JWKS_SOURCE`".to_string(),
+                ));
+            }
+
+            if self.jwt_jwks_refresh_secs == 0 {
+                return Err(InvalidConfiguration(
+                    "`JWT_JWKS_REFRESH_SECS` must be at least 1".to_string(),
+                ));
+            }
         }
 
         // Check that APNS config is valid when it has been configured
@@ -105,10 +226,57 @@ impl Config {
             Err(e) => Err(e),
         }?;
 
-        // Empty Relay public key is not allowed
-        if self.relay_public_key.is_empty() {
+        #[cfg(not(feature = "multitenant"))]
+        if self
+            .coalesce_window_secs
+            .is_some_and(|secs| secs > MAX_COALESCE_WINDOW_SECS)
+        {
+            return Err(InvalidConfiguration(format!(
+                "`COALESCE_WINDOW_SECS` must be at most {MAX_COALESCE_WINDOW_SECS}"
+            )));
+        }
+
+        // Pushes would be accepted and never delivered
+        if self.queue_enabled && self.queue_workers == 0 {
+            return Err(InvalidConfiguration(
+                "`QUEUE_WORKERS` must be at least 1 when the queue is enabled".to_string(),
+            ));
+        }
+
+        if self.notification_pruning_interval_secs == 0 {
+            return Err(InvalidConfiguration(
+                "`NOTIFICATION_PRUNING_INTERVAL_SECS` must be at least 1".to_string(),
+            ));
+        }
+
+        if self.notification_pruning_batch_size < 1 {
+            return Err(InvalidConfiguration(
+                "`NOTIFICATION_PRUNING_BATCH_SIZE` must be at least 1".to_string(),
+            ));
+        }
+
+        if self.client_pruning_interval_secs == 0 {
+            return Err(InvalidConfiguration(
+                "`CLIENT_PRUNING_INTERVAL_SECS` must be at least 1".to_string(),
+            ));
+        }
+
+        if self.client_pruning_batch_size < 1 {
+            return Err(InvalidConfiguration(
+                "`CLIENT_PRUNING_BATCH_SIZE` must be at least 1".to_string(),
+            ));
+        }
+
+        if self.relay_public_keys_refresh_secs == 0 {
             return Err(InvalidConfiguration(
-                "`RELAY_PUBLIC_KEY` cannot be empty".to_string(),
+                "`RELAY_PUBLIC_KEYS_REFRESH_SECS` must be at least 1".to_string(),
+            ));
+        }
+
+        // At least one source of Relay public keys is required
+        if self.relay_public_key.is_empty() && self.relay_public_keys_source.is_none() {
+            return Err(InvalidConfiguration(
+                "`RELAY_PUBLIC_KEY` cannot be empty without `RELAY_PUBLIC_KEYS_SOURCE`".to_string(),
             ));
         }
 
@@ -128,6 +296,10 @@ impl Config {
             supported.push(ProviderKind::Fcm);
         }
 
+        if self.webpush_vapid_private_key.is_some() && self.webpush_vapid_subject.is_some() {
+            supported.push(ProviderKind::WebPush);
+        }
+
         // Only available in debug/testing
         #[cfg(any(debug_assertions, test))]
         supported.push(ProviderKind::Noop);
@@ -192,6 +364,24 @@ fn default_validate_signatures() -> bool {
     true
 }
 
+fn default_relay_public_keys_refresh_secs() -> u64 {
+    300
+}
+
+#[cfg(feature = "multitenant")]
+fn default_jwt_jwks_refresh_secs() -> u64 {
+    300
+}
+
+fn default_signature_max_clock_skew_secs() -> u64 {
+    300
+}
+
+fn default_max_request_body_bytes() -> usize {
+    // 4 MiB, enough for a full batch of encrypted messages
+    4 * 1024 * 1024
+}
+
 fn default_is_test() -> bool {
     false
 }
@@ -204,3 +394,69 @@ pub fn get_config() -> error::Result<Config> {
     let config = envy::from_env::<Config>()?;
     Ok(config)
 }
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_backoff_ms() -> u64 {
+    100
+}
+
+fn default_retry_max_backoff_ms() -> u64 {
+    2_000
+}
+
+fn default_retry_max_elapsed_ms() -> u64 {
+    5_000
+}
+
+fn default_queue_workers() -> usize {
+    4
+}
+
+fn default_queue_poll_interval_ms() -> u64 {
+    500
+}
+
+fn default_queue_lease_secs() -> u64 {
+    60
+}
+
+fn default_queue_max_attempts() -> u32 {
+    10
+}
+
+fn default_notification_in_flight_timeout_secs() -> u64 {
+    600
+}
+
+fn default_notification_retention_secs() -> u64 {
+    // 7 days
+    604_800
+}
+
+fn default_notification_pruning_interval_secs() -> u64 {
+    3_600
+}
+
+fn default_notification_pruning_batch_size() -> i64 {
+    10_000
+}
+
+fn default_client_strike_limit() -> u32 {
+    3
+}
+
+fn default_client_suspension_grace_secs() -> u64 {
+    // 7 days
+    604_800
+}
+
+fn default_client_pruning_interval_secs() -> u64 {
+    3_600
+}
+
+fn default_client_pruning_batch_size() -> i64 {
+    1_000
+}
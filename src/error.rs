@@ -7,6 +7,7 @@ use {
     },
     axum::response::{IntoResponse, Response},
     hyper::StatusCode,
+    std::time::Duration,
 };
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -37,6 +38,10 @@ pub enum Error {
     #[error("FCM v1 Responded with an error")]
     FcmV1Response(fcm_v1::ErrorReason),
 
+    /// Status code and the `Retry-After` delay if the push service sent one
+    #[error("Web Push service responded with status {0}")]
+    WebPushResponse(u16, Option<Duration>),
+
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
@@ -106,6 +111,15 @@ pub enum Error {
     #[error("neither signature or timestamp header cannot not found")]
     MissingAllSignatureHeader,
 
+    #[error("signature timestamp is outside the allowed clock skew")]
+    StaleSignatureTimestamp,
+
+    #[error("signature has already been used")]
+    ReplayedSignature,
+
+    #[error("request body is larger than {0} bytes")]
+    RequestBodyTooLarge(usize),
+
     #[error("single-tenant request made while echo server in multi-tenant mode")]
     MissingTenantId,
 
@@ -115,6 +129,15 @@ pub enum Error {
     #[error("invalid configuration: {0}")]
     InvalidConfiguration(String),
 
+    #[error("invalid relay key set: {0}")]
+    InvalidRelayKeySet(String),
+
+    #[error("invalid JWKS document: {0}")]
+    InvalidJwks(String),
+
+    #[error("the token is missing the {0} scope")]
+    InsufficientScope(&'static str),
+
     #[error("invalid tenant id: {0}")]
     InvalidTenantId(String),
 
@@ -154,6 +177,9 @@ pub enum Error {
     #[error("the provided authentication does not authenticate the request")]
     InvalidAuthentication,
 
+    #[error("the request is missing the client's authentication")]
+    MissingAuthentication,
+
     #[error("GeoIpReader Error: {0}")]
     GeoIpReader(String),
 
@@ -181,6 +207,15 @@ pub enum Error {
     #[error("Invalid APNs creds")]
     BadApnsCredentials,
 
+    #[error("Invalid Web Push VAPID credentials")]
+    BadWebPushCredentials,
+
+    #[error("Invalid Web Push subscription: {0}")]
+    InvalidWebPushSubscription(String),
+
+    #[error("Failed to encrypt Web Push payload")]
+    WebPushEncryption,
+
     #[error("Expired APNs certificate")]
     ApnsCertificateExpired,
 
@@ -193,16 +228,131 @@ pub enum Error {
     #[error("client deleted due to invalid device token")]
     ClientDeleted,
 
+    #[error("client suspended due to invalid device token")]
+    ClientSuspended,
+
     #[error("tenant suspended due to invalid configuration")]
     TenantSuspended,
 
+    #[error("{0} provider suspended due to invalid credentials")]
+    ProviderSuspended(String),
+
     #[error("Payload is too large")]
     PayloadTooLarge,
+
+    #[error("Invalid batch: {0}")]
+    InvalidBatch(String),
+
+    #[error("Invalid client settings: {0}")]
+    InvalidClientSettings(String),
+
+    #[error("Invalid tenant settings: {0}")]
+    InvalidTenantSettings(String),
+
+    #[error("Invalid templates: {0}")]
+    InvalidTemplates(String),
+
+    #[error("APNs topic {0} is not configured for the tenant")]
+    ApnsTopicNotConfigured(String),
+
+    #[error("The device token does not match the APNs topic")]
+    ApnsTopicMismatch,
+
+    #[error("Invalid APNs topics: {0}")]
+    InvalidApnsTopics(String),
 }
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
-        let response = match &self {
+fn http_request_is_retryable(e: &reqwest::Error) -> bool {
+    e.is_connect()
+        || e.is_timeout()
+        || e.status()
+            .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+}
+
+impl Error {
+    /// Whether the error is transient and sending the notification again may
+    /// succeed, permanent errors (e.g. bad tokens or credentials) are never
+    /// retried
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Apns(a2::Error::ConnectionError(_)) => true,
+            Error::Apns(a2::Error::ResponseError(response)) => {
+                response.code == 429 || response.code >= 500
+            }
+            Error::ApnsResponse(reason) => matches!(
+                reason,
+                a2::ErrorReason::TooManyRequests
+                    | a2::ErrorReason::InternalServerError
+                    | a2::ErrorReason::ServiceUnavailable
+                    | a2::ErrorReason::Shutdown
+            ),
+            Error::Fcm(fcm::FcmError::ServerError(_)) => true,
+            Error::FcmResponse(reason) => matches!(
+                reason,
+                fcm::ErrorReason::Unavailable | fcm::ErrorReason::InternalServerError
+            ),
+            // Unregistered tokens and bad credentials are mapped to their own
+            // errors, the remaining send errors are transient when the
+            // underlying request failed or was answered with a 429 or 5xx
+            Error::FcmV1(e) => {
+                std::iter::successors(Some(e as &(dyn std::error::Error + 'static)), |e| {
+                    e.source()
+                })
+                .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+                .any(http_request_is_retryable)
+            }
+            Error::WebPushResponse(status, _) => *status == 429 || *status >= 500,
+            Error::HttpRequest(e) => http_request_is_retryable(e),
+            _ => false,
+        }
+    }
+
+    /// Delay requested by the provider before the next attempt
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Fcm(fcm::FcmError::ServerError(Some(retry_after))) => match retry_after {
+                fcm::RetryAfter::Delay(delay) => delay.to_std().ok(),
+                fcm::RetryAfter::DateTime(date) => (*date - chrono::Utc::now()).to_std().ok(),
+            },
+            Error::WebPushResponse(_, retry_after) => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether the notification should be kept as a dead letter for a later
+    /// replay, this excludes bad device tokens as the client is deleted
+    pub fn is_dead_letter(&self) -> bool {
+        matches!(
+            self,
+            Error::Apns(_)
+                | Error::ApnsResponse(_)
+                | Error::Fcm(_)
+                | Error::FcmResponse(_)
+                | Error::FcmV1(_)
+                | Error::FcmV1Response(_)
+                | Error::WebPushResponse(_, _)
+                | Error::HttpRequest(_)
+                | Error::ProviderNotAvailable(_)
+                | Error::BadFcmApiKey
+                | Error::BadFcmV1Credentials
+                | Error::BadApnsCredentials
+                | Error::BadWebPushCredentials
+                | Error::ApnsCertificateExpired
+                | Error::ApnsCertificateUnknownCA
+                | Error::ApnsInvalidProviderToken
+                | Error::ApnsTopicNotConfigured(_)
+                | Error::ApnsTopicMismatch
+                | Error::TenantSuspended
+                | Error::ProviderSuspended(_)
+        )
+    }
+}
+
+impl Error {
+    /// Status code and body describing the error, also used to report the
+    /// outcome of each entry of a batch
+    pub fn as_response(&self) -> crate::handlers::Response {
+        match self {
             Error::BadDeviceToken(e) => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
                 ResponseError {
                     name: "invalid_token".to_string(),
@@ -281,6 +431,42 @@ impl IntoResponse for Error {
                     location: ErrorLocation::Body,
                 }
             ]),
+            Error::WebPushResponse(status, _) => crate::handlers::Response::new_failure(StatusCode::INTERNAL_SERVER_ERROR, vec![
+                ResponseError {
+                    name: "webpush_response".to_string(),
+                    message: format!("Web Push service responded with status {status}"),
+                }
+            ], vec![]),
+            Error::WebPushEncryption => crate::handlers::Response::new_failure(StatusCode::INTERNAL_SERVER_ERROR, vec![
+                ResponseError {
+                    name: "webpush".to_string(),
+                    message: "Failed to encrypt the Web Push payload".to_string(),
+                }
+            ], vec![]),
+            Error::BadWebPushCredentials => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
+                ResponseError {
+                    name: "bad_webpush_credentials".to_string(),
+                    message: "The provided VAPID credentials were not valid".to_string(),
+                }
+            ], vec![
+                ErrorField {
+                    field: "vapid_private_key".to_string(),
+                    description: "VAPID private key and subject".to_string(),
+                    location: ErrorLocation::Body,
+                }
+            ]),
+            Error::InvalidWebPushSubscription(e) => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
+                ResponseError {
+                    name: "invalid_token".to_string(),
+                    message: e.to_string(),
+                }
+            ], vec![
+                ErrorField {
+                    field: "token".to_string(),
+                    description: "Invalid Web Push subscription".to_string(),
+                    location: ErrorLocation::Body,
+                }
+            ]),
             Error::Database(e) => crate::handlers::Response::new_failure(StatusCode::INTERNAL_SERVER_ERROR, vec![
                 ResponseError {
                     name: "sqlx".to_string(),
@@ -395,6 +581,36 @@ impl IntoResponse for Error {
                     location: ErrorLocation::Header,
                 }
             ]),
+            Error::StaleSignatureTimestamp => crate::handlers::Response::new_failure(StatusCode::UNAUTHORIZED, vec![
+                ResponseError {
+                    name: "stale_timestamp".to_string(),
+                    message: "Failed to validate webhook, the timestamp is too far from the current time.".to_string(),
+                }
+            ], vec![
+                ErrorField {
+                    field: TIMESTAMP_HEADER_NAME.to_string(),
+                    description: "Outside the allowed clock skew".to_string(),
+                    location: ErrorLocation::Header,
+                }
+            ]),
+            Error::ReplayedSignature => crate::handlers::Response::new_failure(StatusCode::UNAUTHORIZED, vec![
+                ResponseError {
+                    name: "replayed_signature".to_string(),
+                    message: "Failed to validate webhook, the request has already been received.".to_string(),
+                }
+            ], vec![
+                ErrorField {
+                    field: SIGNATURE_HEADER_NAME.to_string(),
+                    description: "Already used".to_string(),
+                    location: ErrorLocation::Header,
+                }
+            ]),
+            Error::RequestBodyTooLarge(limit) => crate::handlers::Response::new_failure(StatusCode::PAYLOAD_TOO_LARGE, vec![
+                ResponseError {
+                    name: "body".to_string(),
+                    message: format!("Request body is larger than {limit} bytes"),
+                },
+            ], vec![]),
             Error::InvalidTenantId(id) => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
                 ResponseError {
                     name: "tenant".to_string(),
@@ -447,6 +663,20 @@ impl IntoResponse for Error {
                     }
                 ],
             ),
+            Error::MissingAuthentication => crate::handlers::Response::new_failure(
+                StatusCode::UNAUTHORIZED,
+                vec![ResponseError {
+                    name: "authentication".to_string(),
+                    message: "the tenant requires requests to be authenticated as the client".to_string(),
+                }],
+                vec![
+                    ErrorField {
+                        field: axum::http::header::AUTHORIZATION.to_string(),
+                        description: "missing authorization token".to_string(),
+                        location: ErrorLocation::Header,
+                    }
+                ],
+            ),
             Error::InvalidProjectId(id) => crate::handlers::Response::new_failure(
                 StatusCode::BAD_REQUEST,
                 vec![ResponseError {
@@ -510,8 +740,18 @@ impl IntoResponse for Error {
                     message: "failed to decode body as bytes, please ensure your JSON body is encoded properly".to_string(),
                 },
             ], vec![]),
-            // Figure out
-            // TODO: Error::InvalidOptionsProvided(_) => {}
+            Error::InvalidOptionsProvided(f) => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
+                ResponseError {
+                    name: "invalid_options".to_string(),
+                    message: format!("invalid delivery options provided for {f}"),
+                },
+            ], vec![
+                ErrorField {
+                    field: format!("options.{f}"),
+                    description: "invalid delivery option".to_string(),
+                    location: ErrorLocation::Body,
+                }
+            ]),
             Error::FromUtf8Error(_) => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
                 ResponseError {
                     name: "decode".to_string(),
@@ -564,6 +804,12 @@ impl IntoResponse for Error {
                     message: "JWT Authentication Failed".to_string(),
                 },
             ], vec![]),
+            Error::InsufficientScope(scope) => crate::handlers::Response::new_failure(StatusCode::FORBIDDEN, vec![
+                ResponseError {
+                    name: "insufficient_scope".to_string(),
+                    message: format!("The token doesn't grant the `{scope}` scope"),
+                },
+            ], vec![]),
             Error::MissmatchedTenantId => crate::handlers::Response::new_failure(StatusCode::BAD_REQUEST, vec![
                 ResponseError {
                     name: "missmatched_identifiers".to_string(),
@@ -587,12 +833,24 @@ impl IntoResponse for Error {
                     message: "Request Accepted, client deleted due to invalid token".to_string(),
                 },
             ], vec![]),
+            Error::ClientSuspended => crate::handlers::Response::new_failure(StatusCode::ACCEPTED, vec![
+                ResponseError {
+                    name: "client_suspended".to_string(),
+                    message: "Request Accepted, client suspended due to invalid token".to_string(),
+                },
+            ], vec![]),
             Error::TenantSuspended => crate::handlers::Response::new_failure(StatusCode::ACCEPTED, vec![
                 ResponseError {
                     name: "tenant_suspended".to_string(),
                     message: "Request Accepted, tenant suspended due to invalid configuration".to_string(),
                 },
             ], vec![]),
+            Error::ProviderSuspended(provider) => crate::handlers::Response::new_failure(StatusCode::ACCEPTED, vec![
+                ResponseError {
+                    name: "provider_suspended".to_string(),
+                    message: format!("Request Accepted, {provider} provider suspended due to invalid credentials"),
+                },
+            ], vec![]),
             Error::PayloadTooLarge => crate::handlers::Response::new_failure(
                 StatusCode::BAD_REQUEST,
                 vec![ResponseError {
@@ -601,6 +859,84 @@ impl IntoResponse for Error {
                 }],
                 vec![],
             ),
+            Error::InvalidBatch(e) => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "invalid_batch".to_string(),
+                    message: e.to_string(),
+                }],
+                vec![ErrorField {
+                    field: "messages".to_string(),
+                    description: e.to_string(),
+                    location: ErrorLocation::Body,
+                }],
+            ),
+            Error::InvalidTenantSettings(e) => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "invalid_settings".to_string(),
+                    message: e.to_string(),
+                }],
+                vec![],
+            ),
+            Error::ApnsTopicNotConfigured(topic) => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "apns_topic_not_configured".to_string(),
+                    message: format!("APNs topic {topic} is not configured for the tenant"),
+                }],
+                vec![ErrorField {
+                    field: "apns_topic".to_string(),
+                    description: format!("APNs topic {topic} is not configured for the tenant"),
+                    location: ErrorLocation::Body,
+                }],
+            ),
+            Error::ApnsTopicMismatch => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "apns_topic_mismatch".to_string(),
+                    message: "The device token does not belong to the client's APNs topic, \
+                              check the bundle id the client registered with"
+                        .to_string(),
+                }],
+                vec![],
+            ),
+            Error::InvalidApnsTopics(e) => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "invalid_apns_topics".to_string(),
+                    message: e.to_string(),
+                }],
+                vec![ErrorField {
+                    field: "apns_topics".to_string(),
+                    description: e.to_string(),
+                    location: ErrorLocation::Body,
+                }],
+            ),
+            Error::InvalidTemplates(e) => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "invalid_templates".to_string(),
+                    message: e.to_string(),
+                }],
+                vec![ErrorField {
+                    field: "templates".to_string(),
+                    description: e.to_string(),
+                    location: ErrorLocation::Body,
+                }],
+            ),
+            Error::InvalidClientSettings(e) => crate::handlers::Response::new_failure(
+                StatusCode::BAD_REQUEST,
+                vec![ResponseError {
+                    name: "invalid_settings".to_string(),
+                    message: e.to_string(),
+                }],
+                vec![ErrorField {
+                    field: "settings".to_string(),
+                    description: e.to_string(),
+                    location: ErrorLocation::Body,
+                }],
+            ),
             e => {
                 warn!("Error does not have response clause, {:?}", e);
 
@@ -615,7 +951,13 @@ impl IntoResponse for Error {
                     },
                 ], vec![])
             }
-        }.into_response();
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let response = self.as_response().into_response();
 
         if response.status().is_client_error() {
             warn!("HTTP client error: {self:?}");
@@ -0,0 +1,222 @@
+use {
+    crate::{
+        error::Error,
+        handlers::{
+            get_tenant::{enabled_providers, provider_health, ProviderHealth},
+            validate_admin_request,
+        },
+        log::prelude::*,
+        providers::ProviderKind,
+        state::AppState,
+        stores::tenant::{SuspensionAction, SuspensionEvent, Tenant, TenantListFilter},
+    },
+    axum::{
+        extract::{Path, Query, State},
+        http::HeaderMap,
+        Json,
+    },
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    std::{collections::HashMap, sync::Arc},
+    tracing::instrument,
+};
+
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+const MAX_PAGE_LIMIT: i64 = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct ListTenantsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Tenants suspended entirely or for one of their providers
+    pub suspended: Option<bool>,
+    /// Tenants with credentials for the provider
+    pub provider: Option<String>,
+    pub updated_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PageQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuspendTenantBody {
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AdminTenantResponse {
+    pub id: String,
+    pub enabled_providers: Vec<String>,
+    pub suspended: bool,
+    pub suspended_reason: Option<String>,
+    pub provider_health: HashMap<String, ProviderHealth>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Tenant> for AdminTenantResponse {
+    fn from(tenant: Tenant) -> Self {
+        Self {
+            enabled_providers: enabled_providers(&tenant),
+            provider_health: provider_health(&tenant),
+            id: tenant.id,
+            suspended: tenant.suspended,
+            suspended_reason: tenant.suspended_reason,
+            created_at: tenant.created_at,
+            updated_at: tenant.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListTenantsResponse {
+    pub tenants: Vec<AdminTenantResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuspensionEventResponse {
+    pub action: SuspensionAction,
+    pub provider: Option<String>,
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<SuspensionEvent> for SuspensionEventResponse {
+    fn from(event: SuspensionEvent) -> Self {
+        Self {
+            action: event.action,
+            provider: event.provider,
+            reason: event.reason,
+            actor: event.actor,
+            created_at: event.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuspensionHistoryResponse {
+    pub events: Vec<SuspensionEventResponse>,
+}
+
+fn verify_admin(state: &AppState, headers: &HeaderMap) -> Result<String, Error> {
+    validate_admin_request(&state.jwt_validation_client, headers).map_err(|e| {
+        error!(err = ?e, "admin JWT verification failed");
+        e
+    })
+}
+
+fn page(limit: Option<i64>, offset: Option<i64>) -> Result<(i64, i64), Error> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(Error::InvalidOptionsProvided("limit".to_string()));
+    }
+    let offset = offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(Error::InvalidOptionsProvided("offset".to_string()));
+    }
+
+    Ok((limit, offset))
+}
+
+#[instrument(skip_all, name = "admin_list_tenants_handler")]
+pub async fn list_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListTenantsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ListTenantsResponse>, Error> {
+    verify_admin(&state, &headers)?;
+
+    let (limit, offset) = page(query.limit, query.offset)?;
+    let filter = TenantListFilter {
+        suspended: query.suspended,
+        provider: query
+            .provider
+            .as_deref()
+            .map(ProviderKind::try_from)
+            .transpose()?,
+        updated_since: query.updated_since,
+    };
+
+    let tenants = state
+        .tenant_store
+        .list_tenants(&filter, limit, offset)
+        .await?;
+
+    Ok(Json(ListTenantsResponse {
+        tenants: tenants.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[instrument(skip_all, name = "admin_suspend_tenant_handler")]
+pub async fn suspend_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<SuspendTenantBody>,
+) -> Result<Json<AdminTenantResponse>, Error> {
+    let actor = verify_admin(&state, &headers)?;
+
+    if body.reason.trim().is_empty() {
+        return Err(Error::EmptyField("reason".to_string()));
+    }
+
+    let tenant = state
+        .tenant_store
+        .suspend_tenant(&id, &body.reason, &actor)
+        .await?;
+
+    info!(
+        tenant_id = %id,
+        %actor,
+        reason = %body.reason,
+        "tenant suspended by operator"
+    );
+
+    Ok(Json(tenant.into()))
+}
+
+#[instrument(skip_all, name = "admin_unsuspend_tenant_handler")]
+pub async fn unsuspend_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<AdminTenantResponse>, Error> {
+    let actor = verify_admin(&state, &headers)?;
+
+    let tenant = state.tenant_store.unsuspend_tenant(&id, &actor).await?;
+
+    info!(
+        tenant_id = %id,
+        %actor,
+        "tenant unsuspended by operator"
+    );
+
+    Ok(Json(tenant.into()))
+}
+
+#[instrument(skip_all, name = "admin_suspension_history_handler")]
+pub async fn history_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<PageQuery>,
+    headers: HeaderMap,
+) -> Result<Json<SuspensionHistoryResponse>, Error> {
+    verify_admin(&state, &headers)?;
+
+    let (limit, offset) = page(query.limit, query.offset)?;
+
+    // Unknown tenants are an error rather than an empty history
+    state.tenant_store.get_tenant(&id).await?;
+    let events = state
+        .tenant_store
+        .get_suspension_history(&id, limit, offset)
+        .await?;
+
+    Ok(Json(SuspensionHistoryResponse {
+        events: events.into_iter().map(Into::into).collect(),
+    }))
+}
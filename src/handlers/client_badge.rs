@@ -0,0 +1,45 @@
+use {
+    crate::{
+        error::Result,
+        handlers::{verify_client, Response},
+        log::prelude::*,
+        state::AppState,
+    },
+    axum::{
+        extract::{Json, Path, State as StateExtractor},
+        http::HeaderMap,
+    },
+    serde::{Deserialize, Serialize},
+    std::sync::Arc,
+    tracing::instrument,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BadgeBody {
+    /// The client's unread count, usually `0` once the app is opened
+    pub count: u32,
+}
+
+#[instrument(skip_all, name = "update_client_badge_handler")]
+pub async fn handler(
+    Path((tenant_id, id)): Path<(String, String)>,
+    StateExtractor(state): StateExtractor<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<BadgeBody>,
+) -> Result<Response> {
+    let id = verify_client(&state, &tenant_id, &id, headers)?;
+
+    state
+        .client_store
+        .set_badge_count(&tenant_id, &id, body.count)
+        .await?;
+
+    debug!(
+        %tenant_id,
+        client_id = %id,
+        count = body.count,
+        "updated client badge count"
+    );
+
+    Ok(Response::default())
+}
@@ -0,0 +1,192 @@
+use {
+    crate::{
+        error::{Error::InvalidClientSettings, Result},
+        handlers::{verify_client, Response},
+        log::prelude::*,
+        state::AppState,
+        stores::{
+            client::{ClientSettings, QuietHoursMode},
+            tenant::{is_valid_locale, normalize_locale},
+        },
+    },
+    axum::{
+        extract::{Json, Path, State as StateExtractor},
+        http::HeaderMap,
+    },
+    chrono::NaiveTime,
+    chrono_tz::Tz,
+    serde::{Deserialize, Serialize},
+    std::sync::Arc,
+    tracing::instrument,
+};
+
+/// Maximum number of entries in each of the filter lists
+pub const MAX_FILTER_ENTRIES: usize = 256;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClientSettingsBody {
+    /// Only messages with one of these tags are sent, all tags are allowed
+    /// when not set
+    #[serde(default)]
+    pub allowed_tags: Option<Vec<u32>>,
+    #[serde(default)]
+    pub denied_tags: Vec<u32>,
+    #[serde(default)]
+    pub muted_topics: Vec<String>,
+    /// IANA timezone name (e.g. `Europe/Berlin`), required for quiet hours
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    /// BCP 47 language tag (e.g. `pt-BR`) choosing the language of the
+    /// tenant's notification templates
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+/// Daily window, in the client's local time, during which alert messages are
+/// silenced or deferred
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct QuietHours {
+    /// e.g. `22:00`
+    pub start: NaiveTime,
+    /// e.g. `07:00`, windows ending before they start wrap past midnight
+    pub end: NaiveTime,
+    #[serde(default)]
+    pub mode: QuietHoursMode,
+}
+
+impl ClientSettingsBody {
+    pub fn validate(&self) -> Result<()> {
+        let lengths = [
+            (
+                "allowed tags",
+                self.allowed_tags.as_ref().map_or(0, Vec::len),
+            ),
+            ("denied tags", self.denied_tags.len()),
+            ("muted topics", self.muted_topics.len()),
+        ];
+        for (name, len) in lengths {
+            if len > MAX_FILTER_ENTRIES {
+                return Err(InvalidClientSettings(format!(
+                    "at most {MAX_FILTER_ENTRIES} {name} are allowed"
+                )));
+            }
+        }
+
+        if self.muted_topics.iter().any(|topic| topic.is_empty()) {
+            return Err(InvalidClientSettings(
+                "muted topics must not be empty".to_string(),
+            ));
+        }
+
+        if let Some(timezone) = &self.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                return Err(InvalidClientSettings(format!(
+                    "unknown timezone {timezone}"
+                )));
+            }
+        }
+
+        if let Some(locale) = &self.locale {
+            if !is_valid_locale(locale) {
+                return Err(InvalidClientSettings(format!("invalid locale {locale}")));
+            }
+        }
+
+        if let Some(quiet_hours) = &self.quiet_hours {
+            if self.timezone.is_none() {
+                return Err(InvalidClientSettings(
+                    "quiet hours require a timezone".to_string(),
+                ));
+            }
+            if quiet_hours.start == quiet_hours.end {
+                return Err(InvalidClientSettings(
+                    "quiet hours must not start and end at the same time".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ClientSettingsBody> for ClientSettings {
+    fn from(body: ClientSettingsBody) -> Self {
+        ClientSettings {
+            allowed_tags: body
+                .allowed_tags
+                .map(|tags| tags.into_iter().map(i64::from).collect()),
+            denied_tags: body.denied_tags.into_iter().map(i64::from).collect(),
+            muted_topics: body.muted_topics,
+            timezone: body.timezone,
+            quiet_hours_start: body.quiet_hours.as_ref().map(|q| q.start),
+            quiet_hours_end: body.quiet_hours.as_ref().map(|q| q.end),
+            quiet_hours_mode: body.quiet_hours.map(|q| q.mode).unwrap_or_default(),
+            locale: body.locale.as_deref().map(normalize_locale),
+        }
+    }
+}
+
+impl From<ClientSettings> for ClientSettingsBody {
+    fn from(settings: ClientSettings) -> Self {
+        // Tags are only ever written from `u32` values
+        let to_tags = |tags: Vec<i64>| {
+            tags.into_iter()
+                .filter_map(|tag| u32::try_from(tag).ok())
+                .collect()
+        };
+        ClientSettingsBody {
+            allowed_tags: settings.allowed_tags.map(to_tags),
+            denied_tags: to_tags(settings.denied_tags),
+            muted_topics: settings.muted_topics,
+            timezone: settings.timezone,
+            quiet_hours: settings
+                .quiet_hours_start
+                .zip(settings.quiet_hours_end)
+                .map(|(start, end)| QuietHours {
+                    start,
+                    end,
+                    mode: settings.quiet_hours_mode,
+                }),
+            locale: settings.locale,
+        }
+    }
+}
+
+#[instrument(skip_all, name = "get_client_settings_handler")]
+pub async fn get_handler(
+    Path((tenant_id, id)): Path<(String, String)>,
+    StateExtractor(state): StateExtractor<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ClientSettingsBody>> {
+    let id = verify_client(&state, &tenant_id, &id, headers)?;
+
+    let client = state.client_store.get_client(&tenant_id, &id).await?;
+
+    Ok(Json(client.settings.into()))
+}
+
+#[instrument(skip_all, name = "update_client_settings_handler")]
+pub async fn update_handler(
+    Path((tenant_id, id)): Path<(String, String)>,
+    StateExtractor(state): StateExtractor<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<ClientSettingsBody>,
+) -> Result<Response> {
+    let id = verify_client(&state, &tenant_id, &id, headers)?;
+
+    body.validate()?;
+    state
+        .client_store
+        .update_client_settings(&tenant_id, &id, &body.into())
+        .await?;
+
+    debug!(
+        %tenant_id,
+        client_id = %id,
+        "updated client settings"
+    );
+
+    Ok(Response::default())
+}
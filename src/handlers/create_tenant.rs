@@ -1,7 +1,8 @@
 use {
     crate::{
-        error::Error, handlers::validate_tenant_request, increment_counter, log::prelude::*,
-        state::AppState, stores::tenant::TenantUpdateParams,
+        error::Error, handlers::validate_tenant_request, increment_counter,
+        jwt_validation::TenantScope, log::prelude::*, state::AppState,
+        stores::tenant::TenantUpdateParams,
     },
     axum::{extract::State, http::HeaderMap, Json},
     serde::{Deserialize, Serialize},
@@ -28,7 +29,13 @@ pub async fn handler(
     Json(body): Json<TenantRegisterBody>,
 ) -> Result<Json<TenantRegisterResponse>, Error> {
     #[cfg(feature = "cloud")]
-    if let Err(e) = validate_tenant_request(&state.jwt_validation_client, &headers, &body.id).await
+    if let Err(e) = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &body.id,
+        TenantScope::Write,
+    )
+    .await
     {
         error!(
             tenant_id = %body.id,
@@ -39,7 +46,9 @@ pub async fn handler(
     }
 
     #[cfg(not(feature = "cloud"))]
-    if let Err(e) = validate_tenant_request(&state.jwt_validation_client, &headers) {
+    if let Err(e) =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write)
+    {
         error!(
             tenant_id = %body.id,
             err = ?e,
@@ -0,0 +1,264 @@
+use {
+    crate::{
+        error::Error,
+        handlers::{
+            push_message::{
+                build_push_message, deliver_notification, record_notification_status,
+                PushMessageBody,
+            },
+            validate_tenant_request,
+        },
+        increment_counter,
+        jwt_validation::TenantScope,
+        providers::retry::RetryPolicy,
+        state::AppState,
+        stores::{
+            client::ClientStatus, dead_letter::DeadLetter, notification::NotificationStatus,
+            StoreError,
+        },
+    },
+    axum::{
+        extract::{Path, Query, State},
+        http::HeaderMap,
+        Json,
+    },
+    chrono::{DateTime, Utc},
+    hyper::StatusCode,
+    serde::{Deserialize, Serialize},
+    std::sync::Arc,
+    tracing::{error, instrument, warn},
+};
+
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+const MAX_PAGE_LIMIT: i64 = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct ListDeadLettersQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeadLetterResponse {
+    pub id: String,
+    pub client_id: String,
+    pub notification_id: String,
+    pub payload: PushMessageBody,
+    pub error: String,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<DeadLetter> for DeadLetterResponse {
+    fn from(dead_letter: DeadLetter) -> Self {
+        Self {
+            id: dead_letter.id,
+            client_id: dead_letter.client_id,
+            notification_id: dead_letter.notification_id,
+            payload: dead_letter.payload.0,
+            error: dead_letter.error,
+            attempts: dead_letter.attempts,
+            created_at: dead_letter.created_at,
+            updated_at: dead_letter.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListDeadLettersResponse {
+    pub dead_letters: Vec<DeadLetterResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PurgeDeadLettersResponse {
+    pub deleted: u64,
+}
+
+async fn verify_tenant(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: &str,
+    scope: TenantScope,
+) -> Result<(), Error> {
+    #[cfg(feature = "cloud")]
+    let verification_res =
+        validate_tenant_request(&state.jwt_validation_client, headers, id, scope).await;
+
+    #[cfg(not(feature = "cloud"))]
+    let verification_res = validate_tenant_request(&state.jwt_validation_client, headers, scope);
+
+    if let Err(e) = verification_res {
+        error!(
+            tenant_id = %id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+#[instrument(skip_all, name = "list_dead_letters_handler")]
+pub async fn list_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<ListDeadLettersQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ListDeadLettersResponse>, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Read).await?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(Error::InvalidOptionsProvided("limit".to_string()));
+    }
+    let offset = query.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(Error::InvalidOptionsProvided("offset".to_string()));
+    }
+
+    let dead_letters = state
+        .dead_letter_store
+        .get_dead_letters(&id, limit, offset)
+        .await?;
+
+    Ok(Json(ListDeadLettersResponse {
+        dead_letters: dead_letters.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[instrument(skip_all, name = "get_dead_letter_handler")]
+pub async fn get_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, dead_letter_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<DeadLetterResponse>, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Read).await?;
+
+    let dead_letter = state
+        .dead_letter_store
+        .get_dead_letter(&id, &dead_letter_id)
+        .await?;
+
+    Ok(Json(dead_letter.into()))
+}
+
+/// Send the original notification again, the dead letter is removed once it
+/// has been delivered
+#[instrument(skip_all, name = "replay_dead_letter_handler")]
+pub async fn replay_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, dead_letter_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Write).await?;
+
+    let dead_letter = state
+        .dead_letter_store
+        .get_dead_letter(&id, &dead_letter_id)
+        .await?;
+
+    // The dead letter only holds a redacted payload, the original is still
+    // stored with the notification
+    let notification = state
+        .notification_store
+        .get_notification(&dead_letter.notification_id, &dead_letter.client_id, &id)
+        .await?;
+    let body: PushMessageBody = serde_json::from_value(notification.last_payload.0)
+        .map_err(Error::InternalSerializationError)?;
+
+    // `ClientNotFound` is reported as a success to the relay, operators need a
+    // not found here
+    let client = state
+        .client_store
+        .get_client(&id, &dead_letter.client_id)
+        .await?;
+    if client.status == ClientStatus::Suspended {
+        return Err(Error::ClientSuspended);
+    }
+
+    let tenant = state.tenant_store.get_tenant(&id).await?;
+    tenant.check_suspended(&client.push_type)?;
+
+    let options = body.options.clone().unwrap_or_default();
+    let push_message = build_push_message(body, client.always_raw)?;
+
+    if let Err(e) = deliver_notification(
+        &state,
+        &tenant,
+        &dead_letter.client_id,
+        &client,
+        &dead_letter.notification_id,
+        &push_message,
+        &options,
+        &RetryPolicy::from(&state.config),
+    )
+    .await
+    {
+        warn!(
+            tenant_id = %id,
+            %dead_letter_id,
+            "dead letter replay failed: {e:?}"
+        );
+        record_notification_status(
+            &state,
+            &id,
+            &dead_letter.client_id,
+            &dead_letter.notification_id,
+            NotificationStatus::Failed,
+            Some(&e.to_string()),
+            None,
+        )
+        .await;
+        match state
+            .dead_letter_store
+            .update_dead_letter_error(&id, &dead_letter_id, &e.to_string())
+            .await
+        {
+            // The client has been deleted, nothing left to replay
+            Err(StoreError::NotFound(_, _)) => {}
+            Err(store_error) => warn!("error updating dead letter: {store_error:?}"),
+            Ok(_) => {}
+        }
+        return Err(e);
+    }
+
+    state
+        .dead_letter_store
+        .delete_dead_letter(&id, &dead_letter_id)
+        .await?;
+
+    increment_counter!(state.metrics, dead_letter_replays);
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[instrument(skip_all, name = "delete_dead_letter_handler")]
+pub async fn delete_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, dead_letter_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Write).await?;
+
+    state
+        .dead_letter_store
+        .delete_dead_letter(&id, &dead_letter_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[instrument(skip_all, name = "purge_dead_letters_handler")]
+pub async fn purge_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<PurgeDeadLettersResponse>, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Write).await?;
+
+    let deleted = state.dead_letter_store.purge_dead_letters(&id).await?;
+
+    Ok(Json(PurgeDeadLettersResponse { deleted }))
+}
@@ -1,7 +1,9 @@
 use {
     crate::{
         error::{Error::InvalidAuthentication, Result},
-        handlers::{authenticate_client, Response, DECENTRALIZED_IDENTIFIER_PREFIX},
+        handlers::{
+            authenticate_client, check_client_auth_mode, Response, DECENTRALIZED_IDENTIFIER_PREFIX,
+        },
         log::prelude::*,
         state::AppState,
     },
@@ -24,6 +26,9 @@ pub async fn handler(
         .trim_start_matches(DECENTRALIZED_IDENTIFIER_PREFIX)
         .to_string();
 
+    let tenant = state.tenant_store.get_tenant(&tenant_id).await?;
+    check_client_auth_mode(&state, &tenant, "delete", &headers)?;
+
     let client_to_be_deleted = ClientId::new(id.clone().into());
     if !authenticate_client(headers, &state.config.public_url, |client_id| {
         if let Some(client_id) = client_id {
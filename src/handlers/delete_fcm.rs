@@ -3,6 +3,8 @@ use {
         error::Error::{self},
         handlers::validate_tenant_request,
         increment_counter,
+        jwt_validation::TenantScope,
+        providers::ProviderKind,
         state::AppState,
     },
     axum::{
@@ -22,11 +24,17 @@ pub async fn handler(
 ) -> Result<StatusCode, Error> {
     // JWT token verification
     #[cfg(feature = "cloud")]
-    let jwt_verification_result =
-        validate_tenant_request(&state.jwt_validation_client, &headers, &id).await;
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
 
     #[cfg(not(feature = "cloud"))]
-    let jwt_verification_result = validate_tenant_request(&state.jwt_validation_client, &headers);
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
 
     if let Err(e) = jwt_verification_result {
         error!(
@@ -41,11 +49,14 @@ pub async fn handler(
     let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
 
     let new_tenant = state.tenant_store.update_tenant_delete_fcm(&id).await?;
+    state.provider_cache.invalidate_tenant(&id);
 
-    if new_tenant.suspended {
-        // If suspended, it can be restored now because valid credentials have been
-        // provided
-        state.tenant_store.unsuspend_tenant(&new_tenant.id).await?;
+    if new_tenant.provider_suspension(&ProviderKind::Fcm).is_some() {
+        // The suspension doesn't apply to the removed credentials
+        state
+            .tenant_store
+            .unsuspend_provider(&new_tenant.id, &ProviderKind::Fcm)
+            .await?;
     }
 
     increment_counter!(state.metrics, tenant_fcm_updates);
@@ -1,5 +1,8 @@
 use {
-    crate::{error::Error, handlers::validate_tenant_request, log::prelude::*, state::AppState},
+    crate::{
+        error::Error, handlers::validate_tenant_request, jwt_validation::TenantScope,
+        log::prelude::*, state::AppState,
+    },
     axum::{
         extract::{Path, State},
         http::HeaderMap,
@@ -22,11 +25,17 @@ pub async fn handler(
     headers: HeaderMap,
 ) -> Result<Json<DeleteTenantResponse>, Error> {
     #[cfg(feature = "cloud")]
-    let verification_res =
-        validate_tenant_request(&state.jwt_validation_client, &headers, &id).await;
+    let verification_res = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
 
     #[cfg(not(feature = "cloud"))]
-    let verification_res = validate_tenant_request(&state.jwt_validation_client, &headers);
+    let verification_res =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
 
     if let Err(e) = verification_res {
         error!(
@@ -38,6 +47,7 @@ pub async fn handler(
     }
 
     state.tenant_store.delete_tenant(&id).await?;
+    state.provider_cache.invalidate_tenant(&id);
 
     debug!(
         tenant_id = %id,
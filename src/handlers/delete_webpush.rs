@@ -0,0 +1,68 @@
+use {
+    crate::{
+        error::Error::{self},
+        handlers::validate_tenant_request,
+        increment_counter,
+        jwt_validation::TenantScope,
+        providers::ProviderKind,
+        state::AppState,
+    },
+    axum::{
+        extract::{Path, State},
+        http::HeaderMap,
+    },
+    hyper::StatusCode,
+    std::sync::Arc,
+    tracing::{error, instrument},
+};
+
+#[instrument(skip_all, name = "delete_webpush_handler")]
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, Error> {
+    // JWT token verification
+    #[cfg(feature = "cloud")]
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
+
+    // -- check if tenant is real
+    let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
+
+    #[cfg(not(feature = "cloud"))]
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
+
+    if let Err(e) = jwt_verification_result {
+        error!(
+            tenant_id = %id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    let new_tenant = state.tenant_store.update_tenant_delete_webpush(&id).await?;
+    state.provider_cache.invalidate_tenant(&id);
+
+    if new_tenant
+        .provider_suspension(&ProviderKind::WebPush)
+        .is_some()
+    {
+        // The suspension doesn't apply to the removed credentials
+        state
+            .tenant_store
+            .unsuspend_provider(&new_tenant.id, &ProviderKind::WebPush)
+            .await?;
+    }
+
+    increment_counter!(state.metrics, tenant_webpush_updates);
+
+    Ok(StatusCode::NO_CONTENT)
+}
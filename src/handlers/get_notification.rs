@@ -0,0 +1,88 @@
+use {
+    crate::{
+        error::Error,
+        handlers::{validate_tenant_request, DECENTRALIZED_IDENTIFIER_PREFIX},
+        jwt_validation::TenantScope,
+        log::prelude::*,
+        state::AppState,
+        stores::notification::NotificationStatus,
+    },
+    axum::{
+        extract::{Path, State},
+        http::HeaderMap,
+        Json,
+    },
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    std::sync::Arc,
+    tracing::instrument,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetNotificationResponse {
+    pub id: String,
+    pub client_id: String,
+    pub status: NotificationStatus,
+    pub failure_reason: Option<String>,
+    pub provider_message_id: Option<String>,
+    pub status_updated_at: DateTime<Utc>,
+    pub last_received_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[instrument(skip_all, name = "get_notification_handler")]
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path((tenant_id, client_id, notification_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<GetNotificationResponse>, Error> {
+    #[cfg(feature = "cloud")]
+    let verification_res = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &tenant_id,
+        TenantScope::Read,
+    )
+    .await;
+
+    #[cfg(not(feature = "cloud"))]
+    let verification_res =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Read);
+
+    if let Err(e) = verification_res {
+        error!(
+            %tenant_id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    let client_id = client_id
+        .trim_start_matches(DECENTRALIZED_IDENTIFIER_PREFIX)
+        .to_string();
+
+    let notification = state
+        .notification_store
+        .get_notification(&notification_id, &client_id, &tenant_id)
+        .await?;
+
+    debug!(
+        %tenant_id,
+        %client_id,
+        %notification_id,
+        status = ?notification.status,
+        "requested notification status"
+    );
+
+    Ok(Json(GetNotificationResponse {
+        id: notification.id,
+        client_id: notification.client_id,
+        status: notification.status,
+        failure_reason: notification.failure_reason,
+        provider_message_id: notification.provider_message_id,
+        status_updated_at: notification.status_updated_at,
+        last_received_at: notification.last_received_at,
+        created_at: notification.created_at,
+    }))
+}
@@ -2,18 +2,20 @@ use {
     crate::{
         error::Error,
         handlers::validate_tenant_request,
+        jwt_validation::TenantScope,
         log::prelude::*,
         providers::{ProviderKind, PROVIDER_FCM_V1},
         state::AppState,
-        stores::tenant::ApnsType,
+        stores::tenant::{ApnsType, ClientAuthMode, Tenant},
     },
     axum::{
         extract::{Path, State},
         http::HeaderMap,
         Json,
     },
+    chrono::{DateTime, Utc},
     serde::{Deserialize, Serialize},
-    std::sync::Arc,
+    std::{collections::HashMap, sync::Arc},
     tracing::instrument,
 };
 
@@ -22,9 +24,64 @@ pub struct GetTenantResponse {
     pub url: String,
     pub enabled_providers: Vec<String>,
     pub apns_topic: Option<String>,
+    #[serde(default)]
+    pub apns_topics: Vec<String>,
     pub apns_type: Option<ApnsType>,
+    pub webpush_vapid_public_key: Option<String>,
     pub suspended: bool,
     pub suspended_reason: Option<String>,
+    pub coalesce_window_secs: Option<i32>,
+    #[serde(default)]
+    pub apns_environment_fallback: bool,
+    /// The tenant's override of the server's client authentication mode
+    #[serde(default)]
+    pub client_auth_mode: Option<ClientAuthMode>,
+    /// Clients not sent to because of bad device tokens, until they register
+    /// again or are pruned
+    #[serde(default)]
+    pub suspended_clients: i64,
+    /// Keyed by provider, `apns` covers both environments and `fcm` both APIs
+    #[serde(default)]
+    pub provider_health: HashMap<String, ProviderHealth>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ProviderHealth {
+    pub suspended: bool,
+    pub suspended_reason: Option<String>,
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+pub fn enabled_providers(tenant: &Tenant) -> Vec<String> {
+    tenant
+        .providers()
+        .iter()
+        .map(Into::into)
+        // Special case on fcm_v1 for credentials because providers() is also used for token management (of which FCM and FCM V1 tokens are the same)
+        .chain(if tenant.fcm_v1_credentials.is_some() {
+            vec![PROVIDER_FCM_V1.to_string()]
+        } else {
+            vec![]
+        })
+        .collect()
+}
+
+/// Suspension of each of the tenant's providers, keyed by provider
+pub fn provider_health(tenant: &Tenant) -> HashMap<String, ProviderHealth> {
+    let providers = tenant.providers();
+    [ProviderKind::Apns, ProviderKind::Fcm, ProviderKind::WebPush]
+        .into_iter()
+        .filter(|provider| providers.contains(provider))
+        .map(|provider| {
+            let suspension = tenant.provider_suspension(&provider);
+            let health = ProviderHealth {
+                suspended: suspension.is_some(),
+                suspended_reason: suspension.as_ref().and_then(|s| s.reason.clone()),
+                suspended_at: suspension.map(|s| s.suspended_at),
+            };
+            (provider.as_str().to_string(), health)
+        })
+        .collect()
 }
 
 #[instrument(skip_all, name = "get_tenant_handler")]
@@ -34,11 +91,17 @@ pub async fn handler(
     headers: HeaderMap,
 ) -> Result<Json<GetTenantResponse>, Error> {
     #[cfg(feature = "cloud")]
-    let verification_res =
-        validate_tenant_request(&state.jwt_validation_client, &headers, &id).await;
+    let verification_res = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Read,
+    )
+    .await;
 
     #[cfg(not(feature = "cloud"))]
-    let verification_res = validate_tenant_request(&state.jwt_validation_client, &headers);
+    let verification_res =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Read);
 
     if let Err(e) = verification_res {
         error!(
@@ -50,33 +113,37 @@ pub async fn handler(
     }
 
     let tenant = state.tenant_store.get_tenant(&id).await?;
+    let suspended_clients = state.client_store.count_suspended_clients(&id).await?;
 
     let providers = tenant.providers();
+    let provider_health = provider_health(&tenant);
 
     let mut res = GetTenantResponse {
         url: format!("{}/{}", state.config.public_url, tenant.id),
-        enabled_providers: tenant
-            .providers()
-            .iter()
-            .map(Into::into)
-            // Special case on fcm_v1 for credentials because providers() is also used for token management (of which FCM and FCM V1 tokens are the same)
-            .chain(if tenant.fcm_v1_credentials.is_some() {
-                vec![PROVIDER_FCM_V1.to_string()]
-            } else {
-                vec![]
-            })
-            .collect(),
+        enabled_providers: enabled_providers(&tenant),
         apns_topic: None,
+        apns_topics: vec![],
         apns_type: None,
+        webpush_vapid_public_key: None,
         suspended: tenant.suspended,
         suspended_reason: tenant.suspended_reason,
+        coalesce_window_secs: tenant.coalesce_window_secs,
+        apns_environment_fallback: tenant.apns_environment_fallback,
+        client_auth_mode: tenant.client_auth_mode,
+        suspended_clients,
+        provider_health,
     };
 
     if providers.contains(&ProviderKind::Apns) {
         res.apns_topic = tenant.apns_topic;
+        res.apns_topics = tenant.apns_topics;
         res.apns_type = tenant.apns_type;
     }
 
+    if providers.contains(&ProviderKind::WebPush) {
+        res.webpush_vapid_public_key = tenant.webpush_vapid_public_key;
+    }
+
     debug!(
         tenant_id = %id,
         "requested tenant"
@@ -1,7 +1,12 @@
 use {
     crate::{
-        error::{Error::InvalidAuthentication, Result},
-        jwt_validation::{Claims, JwtValidationClient},
+        error::{
+            Error::{InsufficientScope, InvalidAuthentication, MissingAuthentication},
+            Result,
+        },
+        jwt_validation::{Claims, JwtValidationClient, TenantScope, SCOPE_ADMIN},
+        state::AppState,
+        stores::tenant::{ClientAuthMode, Tenant},
     },
     axum::{
         http::{header::AUTHORIZATION, HeaderMap},
@@ -16,20 +21,27 @@ use {
     },
     serde_json::{json, Value},
     std::{collections::HashSet, string::ToString},
-    tracing::{debug, instrument},
+    tracing::{debug, instrument, warn},
 };
 
 // Push
+pub mod client_badge;
+pub mod client_settings;
 pub mod delete_client;
 pub mod metrics;
+pub mod push_batch;
 pub mod push_message;
 pub mod register_client;
 #[cfg(not(feature = "multitenant"))]
 pub mod single_tenant_wrappers;
 // Tenant Management
 #[cfg(feature = "multitenant")]
+pub mod admin_tenants;
+#[cfg(feature = "multitenant")]
 pub mod create_tenant;
 #[cfg(feature = "multitenant")]
+pub mod dead_letters;
+#[cfg(feature = "multitenant")]
 pub mod delete_apns;
 #[cfg(feature = "multitenant")]
 pub mod delete_fcm;
@@ -38,15 +50,27 @@ pub mod delete_fcm_v1;
 #[cfg(feature = "multitenant")]
 pub mod delete_tenant;
 #[cfg(feature = "multitenant")]
+pub mod delete_webpush;
+#[cfg(feature = "multitenant")]
+pub mod get_notification;
+#[cfg(feature = "multitenant")]
 pub mod get_tenant;
 pub mod health;
 pub mod rate_limit_test;
 #[cfg(feature = "multitenant")]
+pub mod tenant_templates;
+#[cfg(feature = "multitenant")]
 pub mod update_apns;
 #[cfg(feature = "multitenant")]
+pub mod update_apns_topics;
+#[cfg(feature = "multitenant")]
 pub mod update_fcm;
 #[cfg(feature = "multitenant")]
 pub mod update_fcm_v1;
+#[cfg(feature = "multitenant")]
+pub mod update_tenant_settings;
+#[cfg(feature = "multitenant")]
+pub mod update_webpush;
 
 pub const DECENTRALIZED_IDENTIFIER_PREFIX: &str = "did:key:";
 
@@ -71,13 +95,95 @@ where
         let client_id: ClientId = claims.iss.into();
         Ok(check(Some(client_id)))
     } else {
-        // Note: Authentication is not required right now to ensure that this is a
-        // non-breaking change, eventually it will be required and this should default
-        // to returning `Err(MissingAuthentication)` or `Err(InvalidAuthentication)`
+        // Note: Whether a missing token is acceptable depends on the tenant's
+        // client authentication mode, see `check_client_auth_mode`
         Ok(true)
     };
 }
 
+/// Apply the tenant's client authentication mode (falling back to the
+/// configured default) to a request without an `Authorization` header.
+/// Requests carrying one are verified by `authenticate_client` instead.
+pub fn check_client_auth_mode(
+    state: &AppState,
+    tenant: &Tenant,
+    action: &'static str,
+    headers: &HeaderMap,
+) -> Result<()> {
+    if headers.contains_key(AUTHORIZATION) {
+        return Ok(());
+    }
+
+    let mode = tenant
+        .client_auth_mode
+        .unwrap_or(state.config.client_auth_mode);
+    if let Some(metrics) = &state.metrics {
+        metrics.unauthenticated_client_request(&tenant.id, action, mode.as_str());
+    }
+
+    match mode {
+        ClientAuthMode::Off => Ok(()),
+        ClientAuthMode::Warn => {
+            warn!(tenant_id = %tenant.id, %action, "unauthenticated client request");
+            Ok(())
+        }
+        ClientAuthMode::Enforce => {
+            debug!(tenant_id = %tenant.id, %action, "rejected unauthenticated client request");
+            Err(MissingAuthentication)
+        }
+    }
+}
+
+/// Check the request is authenticated as the client, returning the client id
+/// without the DID prefix. Unlike registration these routes have no legacy
+/// callers, so the token is always required.
+pub fn verify_client(
+    state: &AppState,
+    tenant_id: &str,
+    id: &str,
+    headers: HeaderMap,
+) -> Result<String> {
+    if !headers.contains_key(AUTHORIZATION) {
+        debug!(%tenant_id, requested_client_id = %id, "client verification failed: missing token");
+        return Err(MissingAuthentication);
+    }
+
+    let id = id
+        .trim_start_matches(DECENTRALIZED_IDENTIFIER_PREFIX)
+        .to_string();
+
+    let requested_client_id = ClientId::new(id.clone().into());
+    if !authenticate_client(headers, &state.config.public_url, |client_id| {
+        if let Some(client_id) = client_id {
+            debug!(
+                %tenant_id,
+                %requested_client_id,
+                token_client_id = %client_id,
+                "client_id authentication checking"
+            );
+            client_id == requested_client_id
+        } else {
+            debug!(
+                %tenant_id,
+                %requested_client_id,
+                token_client_id = "unknown",
+                "client_id verification failed: missing client_id"
+            );
+            false
+        }
+    })? {
+        debug!(
+            %tenant_id,
+            %requested_client_id,
+            token_client_id = "unknown",
+            "client_id verification failed: invalid client_id"
+        );
+        return Err(InvalidAuthentication);
+    }
+
+    Ok(id)
+}
+
 #[derive(serde::Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ErrorLocation {
@@ -180,16 +286,44 @@ fn validate_jwt(
     }
 }
 
+fn check_scope(
+    jwt_validation_client: &JwtValidationClient,
+    claims: &Claims,
+    scope: TenantScope,
+) -> Result<()> {
+    if claims.has_scope(scope, jwt_validation_client.require_scopes()) {
+        Ok(())
+    } else {
+        Err(InsufficientScope(scope.as_str()))
+    }
+}
+
+/// Check the request carries a token with the `admin` scope, returning the
+/// token's subject
+#[instrument(skip_all)]
+pub fn validate_admin_request(
+    jwt_validation_client: &JwtValidationClient,
+    headers: &HeaderMap,
+) -> Result<String> {
+    let token_data = validate_jwt(jwt_validation_client, headers)?;
+    if token_data.claims.is_admin() {
+        Ok(token_data.claims.sub)
+    } else {
+        Err(InsufficientScope(SCOPE_ADMIN))
+    }
+}
+
 #[cfg(feature = "cloud")]
-#[instrument(skip_all, fields(project_id = %project_id))]
+#[instrument(skip_all, fields(project_id = %project_id, scope = ?scope))]
 pub async fn validate_tenant_request(
     jwt_validation_client: &JwtValidationClient,
     headers: &HeaderMap,
     project_id: &str,
+    scope: TenantScope,
 ) -> Result<()> {
     let token_data = validate_jwt(jwt_validation_client, headers)?;
     if token_data.claims.sub == project_id {
-        Ok(())
+        check_scope(jwt_validation_client, &token_data.claims, scope)
     } else {
         // TODO specific wrong `sub` error
         Err(InvalidAuthentication)
@@ -197,10 +331,12 @@ pub async fn validate_tenant_request(
 }
 
 #[cfg(not(feature = "cloud"))]
-#[instrument(skip_all)]
+#[instrument(skip_all, fields(scope = ?scope))]
 pub fn validate_tenant_request(
     jwt_validation_client: &JwtValidationClient,
     headers: &HeaderMap,
+    scope: TenantScope,
 ) -> Result<()> {
-    validate_jwt(jwt_validation_client, headers).map(|_| ())
+    let token_data = validate_jwt(jwt_validation_client, headers)?;
+    check_scope(jwt_validation_client, &token_data.claims, scope)
 }
@@ -0,0 +1,530 @@
+#[cfg(feature = "multitenant")]
+use crate::stores::tenant::DEFAULT_TENANT_ID;
+#[cfg(feature = "analytics")]
+use {
+    crate::analytics::message_info::MessageInfo, axum_client_ip::SecureClientIp, std::net::IpAddr,
+};
+use {
+    crate::{
+        error::Error,
+        handlers::{
+            push_message::{
+                apply_coalescing, apply_quiet_hours, build_push_message, deliver_notification,
+                record_dead_letter, record_notification_status, PushMessageBody,
+            },
+            Response, DECENTRALIZED_IDENTIFIER_PREFIX,
+        },
+        increment_counter,
+        log::prelude::*,
+        middleware::validate_signature::RequireValidSignature,
+        providers::{retry::RetryPolicy, DeliveryOptions, ProviderKind, PushMessage},
+        state::AppState,
+        stores::{
+            client::{Client, ClientStatus},
+            notification::{NotificationClaim, NotificationStatus},
+            tenant::Tenant,
+        },
+    },
+    axum::{
+        extract::{Json, Path, State as StateExtractor},
+        http::StatusCode,
+    },
+    chrono::{DateTime, Utc},
+    futures_util::{stream, StreamExt},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+        time::Duration,
+    },
+    tracing::instrument,
+};
+
+/// Maximum number of messages in a single batch request
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// Maximum number of concurrent sends to each provider
+const MAX_CONCURRENT_SENDS_PER_PROVIDER: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BatchPushEntry {
+    pub client_id: String,
+    pub message: PushMessageBody,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BatchPushMessageBody {
+    pub messages: Vec<BatchPushEntry>,
+}
+
+#[derive(Serialize)]
+pub struct BatchPushEntryResult {
+    pub client_id: String,
+    pub status_code: u16,
+    #[serde(flatten)]
+    pub response: Response,
+}
+
+#[derive(Serialize)]
+pub struct BatchPushResponse {
+    pub results: Vec<BatchPushEntryResult>,
+}
+
+/// Entry which passed validation and is sent to the client's provider
+struct PreparedEntry {
+    index: usize,
+    client_id: String,
+    client: Client,
+    body: PushMessageBody,
+    push_message: PushMessage,
+    options: DeliveryOptions,
+    /// Set when the client's quiet hours defer the entry to the queue
+    deferred_until: Option<DateTime<Utc>>,
+}
+
+#[instrument(skip_all, name = "push_batch_handler", fields(tenant_id = tenant_id))]
+pub async fn handler(
+    #[cfg(feature = "analytics")] SecureClientIp(client_ip): SecureClientIp,
+    Path(tenant_id): Path<String>,
+    StateExtractor(state): StateExtractor<Arc<AppState>>,
+    RequireValidSignature(Json(body)): RequireValidSignature<Json<BatchPushMessageBody>>,
+) -> Result<Json<BatchPushResponse>, Error> {
+    if body.messages.is_empty() {
+        return Err(Error::InvalidBatch("no messages provided".to_string()));
+    }
+    if body.messages.len() > MAX_BATCH_SIZE {
+        return Err(Error::InvalidBatch(format!(
+            "at most {MAX_BATCH_SIZE} messages are allowed"
+        )));
+    }
+
+    let entries = body
+        .messages
+        .into_iter()
+        .map(|entry| {
+            let client_id = entry
+                .client_id
+                .trim_start_matches(DECENTRALIZED_IDENTIFIER_PREFIX)
+                .to_string();
+            (client_id, entry.message)
+        })
+        .collect::<Vec<_>>();
+    if let Some(metrics) = &state.metrics {
+        metrics
+            .received_notifications
+            .add(entries.len() as u64, &[]);
+    }
+
+    let client_ids = entries
+        .iter()
+        .map(|(client_id, _)| client_id.clone())
+        .collect::<Vec<_>>();
+    let clients = state
+        .client_store
+        .get_clients(&tenant_id, &client_ids)
+        .await?;
+    #[cfg(feature = "multitenant")]
+    let clients = add_unset_tenant_clients(&state, &tenant_id, &client_ids, clients).await?;
+    let tenant = state.tenant_store.get_tenant(&tenant_id).await?;
+    debug!(
+        %tenant_id,
+        messages = entries.len(),
+        clients = clients.len(),
+        "fetched clients for batch"
+    );
+
+    let mut results: Vec<Option<Result<StatusCode, Error>>> =
+        entries.iter().map(|_| None).collect();
+
+    let mut filtered = HashSet::new();
+    let mut prepared = Vec::with_capacity(entries.len());
+    for (index, (client_id, body)) in entries.iter().enumerate() {
+        let Some(client) = clients.get(client_id).cloned() else {
+            results[index] = Some(Err(Error::ClientNotFound));
+            continue;
+        };
+        if client.status == ClientStatus::Suspended {
+            results[index] = Some(Err(Error::ClientSuspended));
+            continue;
+        }
+
+        let options = body.options.clone().unwrap_or_default();
+        let push_message = options
+            .validate()
+            .and_then(|_| build_push_message(body.clone(), client.always_raw));
+        match push_message {
+            Ok(push_message)
+                if !client
+                    .settings
+                    .allows(push_message.tag(), &push_message.topic()) =>
+            {
+                debug!(
+                    %tenant_id,
+                    %client_id,
+                    notification_id = %push_message.message_id(),
+                    "notification filtered by client settings"
+                );
+                increment_counter!(state.metrics, filtered_notifications);
+                filtered.insert(index);
+                results[index] = Some(Ok(StatusCode::OK));
+            }
+            Ok(push_message) => prepared.push(PreparedEntry {
+                index,
+                client_id: client_id.clone(),
+                client,
+                body: body.clone(),
+                push_message,
+                options,
+                deferred_until: None,
+            }),
+            Err(e) => results[index] = Some(Err(e)),
+        }
+    }
+
+    let claims = state
+        .notification_store
+        .claim_notifications(
+            &tenant_id,
+            &prepared
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.push_message.message_id().to_string(),
+                        entry.client_id.clone(),
+                        entry.body.clone(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            Duration::from_secs(state.config.notification_in_flight_timeout_secs),
+        )
+        .await?;
+
+    let mut claimed = Vec::with_capacity(prepared.len());
+    for (entry, claim) in prepared.into_iter().zip(claims) {
+        match claim {
+            NotificationClaim::Claimed(_) => claimed.push(entry),
+            NotificationClaim::AlreadyDelivered(_) | NotificationClaim::InFlight(_) => {
+                debug!(
+                    %tenant_id,
+                    client_id = %entry.client_id,
+                    notification_id = %entry.push_message.message_id(),
+                    "notification has already been received"
+                );
+                results[entry.index] = Some(Ok(StatusCode::OK));
+            }
+        }
+    }
+
+    // Entries for a suspended tenant or provider are kept as dead letters
+    let mut sendable = Vec::with_capacity(claimed.len());
+    for entry in claimed {
+        let Err(error) = tenant.check_suspended(&entry.client.push_type) else {
+            sendable.push(entry);
+            continue;
+        };
+        warn!(%tenant_id, client_id = %entry.client_id, "{error}");
+        let notification_id = entry.push_message.message_id();
+        record_notification_status(
+            &state,
+            &tenant_id,
+            &entry.client_id,
+            &notification_id,
+            NotificationStatus::Failed,
+            Some(&error.to_string()),
+            None,
+        )
+        .await;
+        record_dead_letter(
+            &state,
+            &tenant_id,
+            &entry.client_id,
+            &notification_id,
+            &entry.body,
+            &error,
+            1,
+        )
+        .await;
+        results[entry.index] = Some(Err(error));
+    }
+    for (index, result) in process_groups(&state, &tenant, sendable).await {
+        results[index] = Some(result);
+    }
+
+    let results = entries
+        .iter()
+        .zip(results)
+        .map(|((client_id, _), result)| {
+            // Every entry has been handled by one of the branches above
+            let result = result.unwrap_or(Err(Error::InternalServerError));
+            let response = match &result {
+                Ok(status) => Response::new_success(*status),
+                Err(e) => {
+                    warn!(
+                        %tenant_id,
+                        %client_id,
+                        "error handling batch push message: {e:?}"
+                    );
+                    e.as_response()
+                }
+            };
+            (client_id.clone(), result, response)
+        })
+        .collect::<Vec<_>>();
+
+    #[cfg(feature = "analytics")]
+    send_analytics(
+        &state, client_ip, &tenant_id, &entries, &clients, &filtered, &results,
+    );
+
+    Ok(Json(BatchPushResponse {
+        results: results
+            .into_iter()
+            .map(|(client_id, _, response)| BatchPushEntryResult {
+                client_id,
+                status_code: response.status_code.as_u16(),
+                response,
+            })
+            .collect(),
+    }))
+}
+
+/// Like single pushes, clients whose tenant id has not been set are allowed
+#[cfg(feature = "multitenant")]
+async fn add_unset_tenant_clients(
+    state: &AppState,
+    tenant_id: &str,
+    client_ids: &[String],
+    mut clients: HashMap<String, Client>,
+) -> Result<HashMap<String, Client>, Error> {
+    if tenant_id == DEFAULT_TENANT_ID {
+        return Ok(clients);
+    }
+    let missing = client_ids
+        .iter()
+        .filter(|client_id| !clients.contains_key(*client_id))
+        .cloned()
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(clients);
+    }
+
+    let unset = state
+        .client_store
+        .get_clients(DEFAULT_TENANT_ID, &missing)
+        .await?;
+    for (client_id, client) in unset {
+        warn!(
+            %tenant_id,
+            %client_id,
+            "client tenant id has not been set, allowing request to continue"
+        );
+        clients.insert(client_id, client);
+    }
+    Ok(clients)
+}
+
+/// Entries for the same client and topic are coalesced and sent one after
+/// the other in batch order, so later ones see the earlier ones. The groups
+/// are split by provider, each provider gets its own bounded set of
+/// concurrent requests so a slow provider doesn't hold up the others
+async fn process_groups(
+    state: &AppState,
+    tenant: &Tenant,
+    entries: Vec<PreparedEntry>,
+) -> Vec<(usize, Result<StatusCode, Error>)> {
+    let mut groups: Vec<Vec<PreparedEntry>> = Vec::new();
+    let mut group_indexes: HashMap<(String, Arc<str>), usize> = HashMap::new();
+    for entry in entries {
+        let key = (entry.client_id.clone(), entry.push_message.topic());
+        match group_indexes.get(&key) {
+            Some(&group) => groups[group].push(entry),
+            None => {
+                group_indexes.insert(key, groups.len());
+                groups.push(vec![entry]);
+            }
+        }
+    }
+
+    let mut by_provider: HashMap<ProviderKind, Vec<Vec<PreparedEntry>>> = HashMap::new();
+    for group in groups {
+        // Every entry of a group belongs to the same client
+        by_provider
+            .entry(group[0].client.push_type)
+            .or_default()
+            .push(group);
+    }
+
+    let sends = by_provider.into_values().map(|groups| {
+        stream::iter(groups)
+            .map(|group| async move {
+                let mut results = Vec::with_capacity(group.len());
+                for entry in group {
+                    results.push(process_entry(state, tenant, entry).await);
+                }
+                results
+            })
+            .buffer_unordered(MAX_CONCURRENT_SENDS_PER_PROVIDER)
+            .collect::<Vec<_>>()
+    });
+
+    futures_util::future::join_all(sends)
+        .await
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect()
+}
+
+async fn process_entry(
+    state: &AppState,
+    tenant: &Tenant,
+    mut entry: PreparedEntry,
+) -> (usize, Result<StatusCode, Error>) {
+    let notification_id = entry.push_message.message_id();
+    apply_coalescing(
+        state,
+        tenant,
+        &entry.client_id,
+        &notification_id,
+        &entry.push_message,
+        &mut entry.body,
+        &mut entry.options,
+    )
+    .await;
+    entry.deferred_until =
+        apply_quiet_hours(state, &entry.client, &mut entry.body, &mut entry.options);
+
+    if state.config.queue_enabled {
+        let res = state
+            .queue_store
+            .enqueue(
+                &tenant.id,
+                &entry.client_id,
+                &notification_id,
+                &entry.body,
+                entry.deferred_until,
+            )
+            .await;
+        return match res {
+            Ok(_) => {
+                increment_counter!(state.metrics, queued_notifications);
+                (entry.index, Ok(StatusCode::ACCEPTED))
+            }
+            Err(e) => (entry.index, Err(Error::Store(e))),
+        };
+    }
+
+    let res = deliver_notification(
+        state,
+        tenant,
+        &entry.client_id,
+        &entry.client,
+        &notification_id,
+        &entry.push_message,
+        &entry.options,
+        &RetryPolicy::from(&state.config),
+    )
+    .await;
+
+    match res {
+        Ok(_) => (entry.index, Ok(StatusCode::ACCEPTED)),
+        Err(e) => {
+            record_notification_status(
+                state,
+                &tenant.id,
+                &entry.client_id,
+                &notification_id,
+                NotificationStatus::Failed,
+                Some(&e.to_string()),
+                None,
+            )
+            .await;
+            if e.is_dead_letter() {
+                record_dead_letter(
+                    state,
+                    &tenant.id,
+                    &entry.client_id,
+                    &notification_id,
+                    &entry.body,
+                    &e,
+                    1,
+                )
+                .await;
+            }
+            (entry.index, Err(e))
+        }
+    }
+}
+
+#[cfg(feature = "analytics")]
+fn send_analytics(
+    state: &Arc<AppState>,
+    client_ip: IpAddr,
+    tenant_id: &str,
+    entries: &[(String, PushMessageBody)],
+    clients: &HashMap<String, Client>,
+    filtered: &HashSet<usize>,
+    results: &[(String, Result<StatusCode, Error>, Response)],
+) {
+    let message_infos = entries
+        .iter()
+        .zip(results)
+        .enumerate()
+        .map(|(index, ((client_id, body), (_, result, response)))| {
+            let client = clients.get(client_id);
+            MessageInfo {
+                msg_id: body
+                    .raw
+                    .as_ref()
+                    .map(|msg| relay_rpc::rpc::msg_id::get_message_id(&msg.message).into())
+                    .or_else(|| body.legacy.as_ref().map(|msg| msg.id.clone()))
+                    .unwrap_or("error: no message id".into()),
+                region: None,
+                country: None,
+                continent: None,
+                project_id: tenant_id.into(),
+                client_id: client_id.as_str().into(),
+                topic: body
+                    .raw
+                    .as_ref()
+                    .map(|m| m.topic.clone())
+                    .or_else(|| body.legacy.as_ref().map(|m| m.payload.topic.clone()))
+                    .unwrap_or("error: no topic".into()),
+                push_provider: client
+                    .map(|c| c.push_type.as_str())
+                    .unwrap_or("unknown")
+                    .into(),
+                always_raw: client.map(|c| c.always_raw),
+                tag: body.raw.as_ref().map(|m| m.tag),
+                encrypted: body.legacy.as_ref().map(|m| m.payload.is_encrypted()),
+                flags: body.legacy.as_ref().map(|m| m.payload.flags),
+                status: response.status_code.as_u16(),
+                response_message: Some(match result {
+                    Ok(_) if filtered.contains(&index) => "Filtered".into(),
+                    Ok(_) => "Batch".into(),
+                    Err(e) => format!("{e:?}").into(),
+                }),
+                received_at: wc::analytics::time::now(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Some(analytics) = &state.analytics {
+            let (country, continent, region) = analytics
+                .lookup_geo_data(client_ip)
+                .map_or((None, None, None), |geo| {
+                    (geo.country, geo.continent, geo.region)
+                });
+            let region: Option<Arc<str>> = region.map(|r| Arc::from(r.join(", ")));
+
+            for mut message_info in message_infos {
+                message_info.country = country.clone();
+                message_info.continent = continent.clone();
+                message_info.region = region.clone();
+                analytics.message(message_info);
+            }
+        }
+    });
+}
@@ -11,17 +11,27 @@ use {
         increment_counter,
         log::prelude::*,
         middleware::validate_signature::RequireValidSignature,
-        providers::{LegacyPushMessage, Provider, PushMessage, PushProvider, RawPushMessage},
+        providers::{
+            retry::{send_with_retry, RetryPolicy},
+            AlertText, DeliveryOptions, DeliveryPushType, LegacyPushMessage, MessagePayload,
+            Provider, ProviderKind, PushMessage, RawPushMessage,
+        },
         state::AppState,
-        stores::StoreError,
+        stores::{
+            client::{Client, ClientStatus, QuietHoursMode},
+            notification::{NotificationClaim, NotificationStatus},
+            tenant::Tenant,
+            StoreError,
+        },
     },
     axum::{
         extract::{Json, Path, State as StateExtractor},
         http::StatusCode,
         response::IntoResponse,
     },
+    chrono::{DateTime, Utc},
     serde::{Deserialize, Serialize},
-    std::sync::Arc,
+    std::{sync::Arc, time::Duration},
     tap::TapFallible,
     tracing::instrument,
 };
@@ -34,6 +44,34 @@ pub struct PushMessageBody {
     // Legacy (deprecating) fields
     #[serde(flatten)]
     pub legacy: Option<LegacyPushMessage>,
+
+    /// Optional delivery hints (TTL, priority, collapse key, push type)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<DeliveryOptions>,
+}
+
+/// Placeholder for message contents which must not be persisted in clear
+pub const REDACTED: &str = "[redacted]";
+
+impl PushMessageBody {
+    /// Copy of the body without the (possibly plain-text) message contents,
+    /// topics, ids and flags are kept so operators can still identify it
+    pub fn redacted(&self) -> Self {
+        Self {
+            raw: self.raw.as_ref().map(|raw| RawPushMessage {
+                message: REDACTED.into(),
+                ..raw.clone()
+            }),
+            legacy: self.legacy.as_ref().map(|legacy| LegacyPushMessage {
+                id: legacy.id.clone(),
+                payload: MessagePayload {
+                    blob: REDACTED.into(),
+                    ..legacy.payload.clone()
+                },
+            }),
+            options: self.options.clone(),
+        }
+    }
 }
 
 #[instrument(skip_all, name = "push_message_handler")]
@@ -168,27 +206,10 @@ pub async fn handler_internal(
         )
     })?;
 
-    let cloned_body = body.clone();
-    let push_message = if client.always_raw {
-        if let Some(body) = body.raw {
-            PushMessage::RawPushMessage(body)
-        } else {
-            return Err((
-                Error::EmptyField("missing topic, tag, or message field".to_string()),
-                None,
-            ));
-        }
-    } else {
-        #[allow(clippy::collapsible_else_if)]
-        if let Some(body) = body.legacy {
-            PushMessage::LegacyPushMessage(body)
-        } else {
-            return Err((
-                Error::EmptyField("missing id or payload field".to_string()),
-                None,
-            ));
-        }
-    };
+    let mut cloned_body = body.clone();
+    let mut delivery_options = body.options.clone().unwrap_or_default();
+    delivery_options.validate().map_err(|e| (e, None))?;
+    let push_message = build_push_message(body, client.always_raw).map_err(|e| (e, None))?;
 
     let message_id = push_message.message_id();
 
@@ -280,23 +301,46 @@ pub async fn handler_internal(
         }
     }
 
-    if let Ok(notification) = state
-        .notification_store
-        .get_notification(&message_id, &client_id, &tenant_id)
-        .await
+    if client.status == ClientStatus::Suspended {
+        debug!(
+            %tenant_id,
+            client_id = %client_id,
+            notification_id = %message_id,
+            "client is suspended, dropping notification"
+        );
+
+        #[cfg(feature = "analytics")]
+        {
+            analytics = Some(MessageInfo {
+                response_message: Some("Client suspended".into()),
+                ..analytics.unwrap()
+            });
+
+            return Err((Error::ClientSuspended, analytics));
+        }
+
+        #[cfg(not(feature = "analytics"))]
+        return Err((Error::ClientSuspended, None));
+    }
+
+    // Messages the client has filtered out are acknowledged without being
+    // stored, so the relay doesn't redeliver them
+    if !client
+        .settings
+        .allows(push_message.tag(), &push_message.topic())
     {
-        warn!(
+        debug!(
             %tenant_id,
             client_id = %client_id,
-            notification_id = %notification.id,
-            last_recieved_at = %notification.last_received_at,
-            "notification has already been received"
+            notification_id = %message_id,
+            "notification filtered by client settings"
         );
+        increment_counter!(state.metrics, filtered_notifications);
 
         #[cfg(feature = "analytics")]
         {
             analytics = Some(MessageInfo {
-                response_message: Some("Notification has already been received".into()),
+                response_message: Some("Filtered".into()),
                 ..analytics.unwrap()
             });
 
@@ -307,13 +351,69 @@ pub async fn handler_internal(
         return Ok(((StatusCode::OK).into_response(), None));
     }
 
-    let notification = state
+    let claim = state
         .notification_store
-        .create_or_update_notification(&message_id, &tenant_id, &client_id, &cloned_body)
+        .claim_notification(
+            &message_id,
+            &tenant_id,
+            &client_id,
+            &cloned_body,
+            Duration::from_secs(state.config.notification_in_flight_timeout_secs),
+        )
         .await
-        .tap_err(|e| warn!("error create_or_update_notification: {e:?}"))
+        .tap_err(|e| warn!("error claim_notification: {e:?}"))
         .map_err(|e| (Error::Store(e), analytics.clone()))?;
 
+    // Only notifications that were sent are discarded, failed or abandoned
+    // sends are attempted again when the relay redelivers them
+    let notification = match claim {
+        NotificationClaim::Claimed(notification) => notification,
+        NotificationClaim::AlreadyDelivered(notification) => {
+            warn!(
+                %tenant_id,
+                client_id = %client_id,
+                notification_id = %notification.id,
+                last_recieved_at = %notification.last_received_at,
+                "notification has already been delivered"
+            );
+
+            #[cfg(feature = "analytics")]
+            {
+                analytics = Some(MessageInfo {
+                    response_message: Some("Notification has already been received".into()),
+                    ..analytics.unwrap()
+                });
+
+                return Ok(((StatusCode::OK).into_response(), analytics));
+            }
+
+            #[cfg(not(feature = "analytics"))]
+            return Ok(((StatusCode::OK).into_response(), None));
+        }
+        NotificationClaim::InFlight(notification) => {
+            warn!(
+                %tenant_id,
+                client_id = %client_id,
+                notification_id = %notification.id,
+                status_updated_at = %notification.status_updated_at,
+                "notification is already being processed"
+            );
+
+            #[cfg(feature = "analytics")]
+            {
+                analytics = Some(MessageInfo {
+                    response_message: Some("Notification has already been processed".into()),
+                    ..analytics.unwrap()
+                });
+
+                return Ok(((StatusCode::OK).into_response(), analytics));
+            }
+
+            #[cfg(not(feature = "analytics"))]
+            return Ok(((StatusCode::OK).into_response(), None));
+        }
+    };
+
     debug!(
         %tenant_id,
         client_id = %client_id,
@@ -321,203 +421,713 @@ pub async fn handler_internal(
         "stored notification",
     );
 
-    // TODO make better by only ignoring if previously executed successfully
-    // If notification received more than once then discard
-    if notification.previous_payloads.len() > 1 {
-        warn!(
+    let tenant = state
+        .tenant_store
+        .get_tenant(&tenant_id)
+        .await
+        .tap_err(|e| warn!("error fetching tenant: {e:?}"))
+        .map_err(|e| (e, analytics.clone()))?;
+    debug!(
+        %tenant_id,
+        client_id = %client_id,
+        notification_id = %notification.id,
+        "fetched tenant"
+    );
+
+    if let Err(error) = tenant.check_suspended(&client.push_type) {
+        warn!("{error}");
+        record_notification_status(
+            &state,
+            &tenant_id,
+            &client_id,
+            &notification.id,
+            NotificationStatus::Failed,
+            Some(&error.to_string()),
+            None,
+        )
+        .await;
+        record_dead_letter(
+            &state,
+            &tenant_id,
+            &client_id,
+            &notification.id,
+            &cloned_body,
+            &error,
+            1,
+        )
+        .await;
+        return Err((error, analytics.clone()));
+    }
+
+    apply_coalescing(
+        &state,
+        &tenant,
+        &client_id,
+        &notification.id,
+        &push_message,
+        &mut cloned_body,
+        &mut delivery_options,
+    )
+    .await;
+
+    let deferred_until =
+        apply_quiet_hours(&state, &client, &mut cloned_body, &mut delivery_options);
+    if let Some(deferred_until) = deferred_until {
+        debug!(
+            %tenant_id,
+            client_id = %client_id,
+            notification_id = %notification.id,
+            %deferred_until,
+            "deferring notification until the client's quiet hours end"
+        );
+    }
+
+    // Persist the message and let the queue workers deliver it
+    if state.config.queue_enabled {
+        state
+            .queue_store
+            .enqueue(
+                &tenant_id,
+                &client_id,
+                &notification.id,
+                &cloned_body,
+                deferred_until,
+            )
+            .await
+            .tap_err(|e| warn!("error enqueueing notification: {e:?}"))
+            .map_err(|e| (Error::Store(e), analytics.clone()))?;
+        increment_counter!(state.metrics, queued_notifications);
+        debug!(
             %tenant_id,
             client_id = %client_id,
             notification_id = %notification.id,
-            last_recieved_at = %notification.last_received_at,
-            "notification has already been processed"
+            "queued notification"
         );
 
         #[cfg(feature = "analytics")]
         {
             analytics = Some(MessageInfo {
-                response_message: Some("Notification has already been processed".into()),
+                response_message: Some(
+                    if deferred_until.is_some() {
+                        "Deferred"
+                    } else {
+                        "Queued"
+                    }
+                    .into(),
+                ),
                 ..analytics.unwrap()
             });
 
-            return Ok(((StatusCode::OK).into_response(), analytics));
+            return Ok(((StatusCode::ACCEPTED).into_response(), analytics));
         }
 
         #[cfg(not(feature = "analytics"))]
-        return Ok(((StatusCode::OK).into_response(), None));
+        return Ok(((StatusCode::ACCEPTED).into_response(), None));
     }
 
-    let tenant = state
+    if let Err(e) = deliver_notification(
+        &state,
+        &tenant,
+        &client_id,
+        &client,
+        &notification.id,
+        &push_message,
+        &delivery_options,
+        &RetryPolicy::from(&state.config),
+    )
+    .await
+    {
+        record_notification_status(
+            &state,
+            &tenant_id,
+            &client_id,
+            &notification.id,
+            NotificationStatus::Failed,
+            Some(&e.to_string()),
+            None,
+        )
+        .await;
+        if e.is_dead_letter() {
+            record_dead_letter(
+                &state,
+                &tenant_id,
+                &client_id,
+                &notification.id,
+                &cloned_body,
+                &e,
+                1,
+            )
+            .await;
+        }
+        return Err((e, analytics.clone()));
+    }
+
+    #[cfg(feature = "analytics")]
+    {
+        analytics = Some(MessageInfo {
+            response_message: Some("Delivered".into()),
+            ..analytics.unwrap()
+        });
+
+        return Ok(((StatusCode::ACCEPTED).into_response(), analytics));
+    }
+
+    #[cfg(not(feature = "analytics"))]
+    Ok(((StatusCode::ACCEPTED).into_response(), None))
+}
+
+/// Send the notification through the tenant's provider, handling permanent
+/// provider errors by suspending the client or the provider, and record a
+/// successful send as the notification status. Shared by the inline push
+/// handler and the delivery queue workers, transient provider errors are
+/// retried according to `retry_policy`. Failures are recorded by the caller,
+/// which may still retry them later.
+#[allow(clippy::too_many_arguments)]
+pub async fn deliver_notification(
+    state: &AppState,
+    tenant: &Tenant,
+    client_id: &str,
+    client: &Client,
+    notification_id: &str,
+    push_message: &PushMessage,
+    options: &DeliveryOptions,
+    retry_policy: &RetryPolicy,
+) -> Result<Option<String>, Error> {
+    // Alert messages count towards the client's unread badge when its
+    // provider shows one, the count is taken back when the message could not
+    // be delivered
+    let mut options = options.clone();
+    if !options.is_background() {
+        if tenant.shows_badge(&client.push_type) {
+            options.badge = add_badge_count(state, tenant, client_id, 1).await;
+        }
+        if push_message.is_encrypted() {
+            options.alert = get_template(state, tenant, client, push_message).await;
+        }
+    }
+
+    let result = send_to_provider(
+        state,
+        tenant,
+        client_id,
+        client,
+        notification_id,
+        push_message,
+        &options,
+        retry_policy,
+    )
+    .await;
+
+    if result.is_err() && options.badge.is_some() {
+        add_badge_count(state, tenant, client_id, -1).await;
+    }
+
+    if let Ok(provider_message_id) = &result {
+        record_notification_status(
+            state,
+            &tenant.id,
+            client_id,
+            notification_id,
+            NotificationStatus::Sent,
+            None,
+            provider_message_id.as_deref(),
+        )
+        .await
+    }
+
+    result
+}
+
+/// Failing to update the count is only logged, the message is then sent
+/// without a badge
+async fn add_badge_count(
+    state: &AppState,
+    tenant: &Tenant,
+    client_id: &str,
+    delta: i32,
+) -> Option<u32> {
+    match state
+        .client_store
+        .add_badge_count(&tenant.id, client_id, delta)
+        .await
+    {
+        Ok(count) => Some(count),
+        Err(e) => {
+            warn!(
+                tenant_id = %tenant.id,
+                %client_id,
+                "error updating badge count: {e:?}"
+            );
+            None
+        }
+    }
+}
+
+/// Failing to look up the template is only logged, the message is then sent
+/// with the generic placeholder
+async fn get_template(
+    state: &AppState,
+    tenant: &Tenant,
+    client: &Client,
+    push_message: &PushMessage,
+) -> Option<AlertText> {
+    match state
         .tenant_store
-        .get_tenant(&tenant_id)
+        .get_template(
+            &tenant.id,
+            push_message.tag(),
+            client.settings.locale.as_deref(),
+        )
         .await
-        .tap_err(|e| warn!("error fetching tenant: {e:?}"))
-        .map_err(|e| (e, analytics.clone()))?;
-    debug!(
-        %tenant_id,
-        client_id = %client_id,
-        notification_id = %notification.id,
-        "fetched tenant"
-    );
+    {
+        Ok(template) => template.map(|template| AlertText {
+            title: template.title,
+            body: template.body,
+        }),
+        Err(e) => {
+            warn!(
+                tenant_id = %tenant.id,
+                "error getting notification template: {e:?}"
+            );
+            None
+        }
+    }
+}
 
-    if tenant.suspended {
-        warn!("tenant suspended");
-        return Err((Error::TenantSuspended, analytics.clone()));
+/// Give the message the collapse key shared by the client's current burst of
+/// messages for the topic, when the tenant coalesces bursts. Messages which
+/// already have a collapse key are left as they are, and failing to coalesce
+/// only means the message is sent on its own.
+pub async fn apply_coalescing(
+    state: &AppState,
+    tenant: &Tenant,
+    client_id: &str,
+    notification_id: &str,
+    push_message: &PushMessage,
+    body: &mut PushMessageBody,
+    options: &mut DeliveryOptions,
+) {
+    let Some(window) = tenant.coalesce_window() else {
+        return;
+    };
+    if options.collapse_key.is_some() {
+        return;
     }
 
+    match state
+        .notification_store
+        .coalesce_notification(
+            notification_id,
+            &tenant.id,
+            client_id,
+            &push_message.topic(),
+            window,
+        )
+        .await
+    {
+        Ok(collapse_key) => {
+            debug!(
+                tenant_id = %tenant.id,
+                %client_id,
+                %notification_id,
+                %collapse_key,
+                "coalescing notification"
+            );
+            options.collapse_key = Some(collapse_key);
+            body.options = Some(options.clone());
+        }
+        Err(e) => warn!(
+            tenant_id = %tenant.id,
+            %client_id,
+            %notification_id,
+            "error coalescing notification: {e:?}"
+        ),
+    }
+}
+
+/// Apply the client's quiet hours to an alert message, it is either sent as a
+/// background message or deferred until the quiet hours end, in which case the
+/// time to deliver it at is returned. Deferring relies on the delivery queue,
+/// without it the message is sent as a background message instead.
+pub fn apply_quiet_hours(
+    state: &AppState,
+    client: &Client,
+    body: &mut PushMessageBody,
+    options: &mut DeliveryOptions,
+) -> Option<DateTime<Utc>> {
+    if options.is_background() {
+        return None;
+    }
+    let end = client.settings.quiet_hours_end(Utc::now())?;
+
+    if client.settings.quiet_hours_mode == QuietHoursMode::Defer && state.config.queue_enabled {
+        if let Some(metrics) = &state.metrics {
+            metrics.quiet_hours_notification("defer");
+        }
+        return Some(end);
+    }
+
+    options.push_type = Some(DeliveryPushType::Background);
+    body.options = Some(options.clone());
+    if let Some(metrics) = &state.metrics {
+        metrics.quiet_hours_notification("silent");
+    }
+    None
+}
+
+/// Failing to record the status is only logged as it must not affect the
+/// delivery, e.g. the notification is gone when its client was deleted
+pub async fn record_notification_status(
+    state: &AppState,
+    tenant_id: &str,
+    client_id: &str,
+    notification_id: &str,
+    status: NotificationStatus,
+    failure_reason: Option<&str>,
+    provider_message_id: Option<&str>,
+) {
+    if let Err(e) = state
+        .notification_store
+        .update_notification_status(
+            notification_id,
+            client_id,
+            tenant_id,
+            status,
+            failure_reason,
+            provider_message_id,
+        )
+        .await
+    {
+        debug!(
+            %tenant_id,
+            %client_id,
+            %notification_id,
+            "error updating notification status: {e:?}"
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn send_to_provider(
+    state: &AppState,
+    tenant: &Tenant,
+    client_id: &str,
+    client: &Client,
+    notification_id: &str,
+    push_message: &PushMessage,
+    options: &DeliveryOptions,
+    retry_policy: &RetryPolicy,
+) -> Result<Option<String>, Error> {
+    let tenant_id = &tenant.id;
+
     let provider = tenant
         .provider(
             &client.push_type,
+            client.apns_topic.as_deref(),
             state.http_client.clone(),
             &state.provider_cache,
         )
         .await
-        .tap_err(|e| warn!("error fetching provider: {e:?}"))
-        .map_err(|e| (e, analytics.clone()))?;
+        .tap_err(|e| warn!("error fetching provider: {e:?}"))?;
     debug!(
         %tenant_id,
         client_id = %client_id,
-        notification_id = %notification.id,
+        %notification_id,
         push_type = client.push_type.as_str(),
         "fetched provider"
     );
 
-    match provider.send_notification(client.token, push_message).await {
-        Ok(()) => Ok(()),
+    let result = send_with_retry(
+        &provider,
+        &client.token,
+        push_message,
+        options,
+        retry_policy,
+        state.metrics.as_ref(),
+    )
+    .await;
+    let result = match result {
+        Err(Error::BadDeviceToken(reason)) if tenant.apns_environment_fallback => {
+            match send_to_other_apns_environment(
+                state,
+                tenant,
+                client_id,
+                client,
+                push_message,
+                options,
+                retry_policy,
+            )
+            .await
+            {
+                Some(provider_message_id) => Ok(provider_message_id),
+                None => Err(Error::BadDeviceToken(reason)),
+            }
+        }
+        result => result,
+    };
+
+    let provider_message_id = match result {
+        Ok(provider_message_id) => Ok(provider_message_id),
         Err(error) => {
             warn!("error sending notification: {error:?}");
             match error {
-                Error::BadDeviceToken(_) => {
-                    state
+                Error::BadDeviceToken(reason) => {
+                    let status = state
                         .client_store
-                        .delete_client(&tenant_id, &client_id)
+                        .record_client_strike(
+                            tenant_id,
+                            client_id,
+                            state.config.client_strike_limit,
+                        )
                         .await
-                        .map_err(|e| (Error::Store(e), analytics.clone()))?;
-                    increment_counter!(state.metrics, client_suspensions);
-                    warn!(
-                        %tenant_id,
-                        client_id = %client_id,
-                        notification_id = %notification.id,
-                        push_type = client.push_type.as_str(),
-                        "client has been deleted due to a bad device token"
-                    );
-                    Err(Error::ClientDeleted)
-                }
-                Error::BadApnsCredentials => {
-                    state
-                        .tenant_store
-                        .suspend_tenant(&tenant_id, "Invalid APNS Credentials")
-                        .await
-                        .map_err(|e| (e, analytics.clone()))?;
-                    increment_counter!(state.metrics, tenant_suspensions);
-                    warn!(
-                        %tenant_id,
-                        client_id = %client_id,
-                        notification_id = %notification.id,
-                        push_type = client.push_type.as_str(),
-                        "tenant has been suspended due to invalid provider credentials"
-                    );
-                    Err(Error::TenantSuspended)
-                }
-                Error::ApnsCertificateExpired => {
-                    let reason = "APNs certificate expired";
-                    state
-                        .tenant_store
-                        .suspend_tenant(&tenant_id, reason)
-                        .await
-                        .map_err(|e| (e, analytics.clone()))?;
-                    increment_counter!(state.metrics, tenant_suspensions);
-                    warn!(
-                        %tenant_id,
-                        client_id = %client_id,
-                        notification_id = %notification.id,
-                        push_type = client.push_type.as_str(),
-                        "tenant has been suspended due to: {reason}"
-                    );
-                    Err(Error::TenantSuspended)
-                }
-                Error::ApnsCertificateUnknownCA => {
-                    let reason = "Unknown APNs certificate's CA";
-                    state
-                        .tenant_store
-                        .suspend_tenant(&tenant_id, reason)
-                        .await
-                        .map_err(|e| (e, analytics.clone()))?;
-                    increment_counter!(state.metrics, tenant_suspensions);
-                    warn!(
-                        %tenant_id,
-                        client_id = %client_id,
-                        notification_id = %notification.id,
-                        push_type = client.push_type.as_str(),
-                        "tenant has been suspended due to: {reason}"
-                    );
-                    Err(Error::TenantSuspended)
-                }
-                Error::ApnsInvalidProviderToken => {
-                    let reason = "APNs certificate invalid provider token";
-                    state
-                        .tenant_store
-                        .suspend_tenant(&tenant_id, reason)
-                        .await
-                        .map_err(|e| (e, analytics.clone()))?;
-                    increment_counter!(state.metrics, tenant_suspensions);
-                    warn!(
-                        %tenant_id,
-                        client_id = %client_id,
-                        notification_id = %notification.id,
-                        push_type = client.push_type.as_str(),
-                        "tenant has been suspended due to: {reason}"
-                    );
-                    Err(Error::TenantSuspended)
-                }
-                Error::BadFcmApiKey => {
-                    state
-                        .tenant_store
-                        .suspend_tenant(&tenant_id, "Invalid FCM Credentials")
-                        .await
-                        .map_err(|e| (e, analytics.clone()))?;
-                    increment_counter!(state.metrics, tenant_suspensions);
-                    warn!(
-                        %tenant_id,
-                        client_id = %client_id,
-                        notification_id = %notification.id,
-                        push_type = client.push_type.as_str(),
-                        "tenant has been suspended due to invalid provider credentials"
-                    );
-                    Err(Error::TenantSuspended)
+                        .map_err(Error::Store)?;
+                    if status == ClientStatus::Suspended {
+                        increment_counter!(state.metrics, client_suspensions);
+                        warn!(
+                            %tenant_id,
+                            client_id = %client_id,
+                            %notification_id,
+                            push_type = client.push_type.as_str(),
+                            "client has been suspended due to a bad device token"
+                        );
+                        Err(Error::ClientSuspended)
+                    } else {
+                        Err(Error::BadDeviceToken(reason))
+                    }
                 }
+                Error::BadApnsCredentials => Err(suspend_provider(
+                    state,
+                    tenant_id,
+                    client_id,
+                    client,
+                    notification_id,
+                    "Invalid APNS Credentials",
+                )
+                .await?),
+                Error::ApnsCertificateExpired => Err(suspend_provider(
+                    state,
+                    tenant_id,
+                    client_id,
+                    client,
+                    notification_id,
+                    "APNs certificate expired",
+                )
+                .await?),
+                Error::ApnsCertificateUnknownCA => Err(suspend_provider(
+                    state,
+                    tenant_id,
+                    client_id,
+                    client,
+                    notification_id,
+                    "Unknown APNs certificate's CA",
+                )
+                .await?),
+                Error::ApnsInvalidProviderToken => Err(suspend_provider(
+                    state,
+                    tenant_id,
+                    client_id,
+                    client,
+                    notification_id,
+                    "APNs certificate invalid provider token",
+                )
+                .await?),
+                Error::BadFcmApiKey => Err(suspend_provider(
+                    state,
+                    tenant_id,
+                    client_id,
+                    client,
+                    notification_id,
+                    "Invalid FCM Credentials",
+                )
+                .await?),
+                Error::BadWebPushCredentials => Err(suspend_provider(
+                    state,
+                    tenant_id,
+                    client_id,
+                    client,
+                    notification_id,
+                    "Invalid Web Push VAPID credentials",
+                )
+                .await?),
                 e => Err(e),
             }
         }
-    }
-    .map_err(|e| (e, analytics.clone()))?;
+    }?;
 
     debug!(
         %tenant_id,
         client_id = %client_id,
-        notification_id = %notification.id,
+        %notification_id,
         push_type = client.push_type.as_str(),
         "sent notification"
     );
 
+    // Strikes only count consecutive bad token responses
+    if client.strikes > 0 {
+        if let Err(e) = state
+            .client_store
+            .reset_client_strikes(tenant_id, client_id)
+            .await
+        {
+            debug!(%tenant_id, %client_id, "error resetting client strikes: {e:?}");
+        }
+    }
+
     // Provider specific metrics
     match provider {
         Provider::Fcm(_) => increment_counter!(state.metrics, sent_fcm_notifications),
         Provider::FcmV1(_) => increment_counter!(state.metrics, sent_fcm_v1_notifications),
         Provider::Apns(_) => increment_counter!(state.metrics, sent_apns_notifications),
+        Provider::WebPush(_) => increment_counter!(state.metrics, sent_webpush_notifications),
         #[cfg(any(debug_assertions, test))]
         Provider::Noop(_) => {}
     }
 
-    #[cfg(feature = "analytics")]
+    Ok(provider_message_id)
+}
+
+/// Suspend the client's provider after it rejected the tenant's credentials,
+/// returning the error to report for the message
+async fn suspend_provider(
+    state: &AppState,
+    tenant_id: &str,
+    client_id: &str,
+    client: &Client,
+    notification_id: &str,
+    reason: &str,
+) -> Result<Error, Error> {
+    state
+        .tenant_store
+        .suspend_provider(tenant_id, &client.push_type, reason)
+        .await?;
+    increment_counter!(state.metrics, tenant_suspensions);
+    if let Some(metrics) = &state.metrics {
+        metrics.provider_suspension(client.push_type.as_str());
+    }
+    warn!(
+        %tenant_id,
+        client_id = %client_id,
+        %notification_id,
+        push_type = client.push_type.as_str(),
+        "provider has been suspended due to: {reason}"
+    );
+
+    Ok(Error::ProviderSuspended(client.push_type.into()))
+}
+
+/// Debug builds registered as `apns` (or release builds as `apns-sandbox`) have
+/// their tokens rejected as bad, retry once with the other environment and
+/// move the client there when it succeeds. Any failure leaves the original
+/// error to be handled.
+async fn send_to_other_apns_environment(
+    state: &AppState,
+    tenant: &Tenant,
+    client_id: &str,
+    client: &Client,
+    push_message: &PushMessage,
+    options: &DeliveryOptions,
+    retry_policy: &RetryPolicy,
+) -> Option<Option<String>> {
+    let other = match client.push_type {
+        ProviderKind::Apns => ProviderKind::ApnsSandbox,
+        ProviderKind::ApnsSandbox => ProviderKind::Apns,
+        _ => return None,
+    };
+
+    let provider = tenant
+        .provider(
+            &other,
+            client.apns_topic.as_deref(),
+            state.http_client.clone(),
+            &state.provider_cache,
+        )
+        .await
+        .tap_err(|e| warn!("error fetching provider: {e:?}"))
+        .ok()?;
+    let provider_message_id = send_with_retry(
+        &provider,
+        &client.token,
+        push_message,
+        options,
+        retry_policy,
+        state.metrics.as_ref(),
+    )
+    .await
+    .tap_err(|e| debug!("error sending to the other apns environment: {e:?}"))
+    .ok()?;
+
+    if let Err(e) = state
+        .client_store
+        .update_client_push_type(&tenant.id, client_id, other)
+        .await
     {
-        analytics = Some(MessageInfo {
-            response_message: Some("Delivered".into()),
-            ..analytics.unwrap()
-        });
+        warn!(
+            tenant_id = %tenant.id,
+            %client_id,
+            "error moving client to the other apns environment: {e:?}"
+        );
+    }
+    if let Some(metrics) = &state.metrics {
+        metrics.apns_environment_switch(other.as_str());
+    }
+    info!(
+        tenant_id = %tenant.id,
+        %client_id,
+        from = client.push_type.as_str(),
+        to = other.as_str(),
+        "moved client to the other apns environment"
+    );
 
-        return Ok(((StatusCode::ACCEPTED).into_response(), analytics));
+    Some(provider_message_id)
+}
+
+/// Select the message format the client registered for
+pub fn build_push_message(body: PushMessageBody, always_raw: bool) -> Result<PushMessage, Error> {
+    if always_raw {
+        body.raw
+            .map(PushMessage::RawPushMessage)
+            .ok_or_else(|| Error::EmptyField("missing topic, tag, or message field".to_string()))
+    } else {
+        body.legacy
+            .map(PushMessage::LegacyPushMessage)
+            .ok_or_else(|| Error::EmptyField("missing id or payload field".to_string()))
     }
+}
 
-    #[cfg(not(feature = "analytics"))]
-    Ok(((StatusCode::ACCEPTED).into_response(), None))
+/// Keep an undeliverable notification so it can be inspected and replayed
+/// once the tenant fixed its configuration. Failing to record it is only
+/// logged as the delivery error is what gets reported.
+pub async fn record_dead_letter(
+    state: &AppState,
+    tenant_id: &str,
+    client_id: &str,
+    notification_id: &str,
+    body: &PushMessageBody,
+    error: &Error,
+    attempts: i32,
+) {
+    match state
+        .dead_letter_store
+        .create_dead_letter(
+            tenant_id,
+            client_id,
+            notification_id,
+            &body.redacted(),
+            &error.to_string(),
+            attempts,
+        )
+        .await
+    {
+        Ok(dead_letter) => {
+            increment_counter!(state.metrics, dead_letters);
+            warn!(
+                %tenant_id,
+                %client_id,
+                %notification_id,
+                dead_letter_id = %dead_letter.id,
+                "notification has been dead-lettered: {error}"
+            );
+        }
+        Err(e) => warn!("error storing dead letter: {e:?}"),
+    }
 }
@@ -6,11 +6,15 @@ use {
             Error::{EmptyField, InvalidAuthentication, ProviderNotAvailable},
             Result,
         },
-        handlers::{authenticate_client, Response, DECENTRALIZED_IDENTIFIER_PREFIX},
+        handlers::{
+            authenticate_client, check_client_auth_mode, client_settings::ClientSettingsBody,
+            Response, DECENTRALIZED_IDENTIFIER_PREFIX,
+        },
         increment_counter,
         log::prelude::*,
+        providers::{webpush::WebPushSubscription, ProviderKind},
         state::AppState,
-        stores::client::Client,
+        stores::client::{Client, ClientSettings, ClientStatus},
     },
     axum::{
         extract::{Json, Path, State as StateExtractor},
@@ -29,6 +33,13 @@ pub struct RegisterBody {
     pub push_type: String,
     pub token: String,
     pub always_raw: Option<bool>,
+    /// Bundle id of the app, required when it is one of the tenant's
+    /// additional APNs topics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apns_topic: Option<String>,
+    /// Replaces the client's tag and topic filters when provided
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<ClientSettingsBody>,
 }
 
 #[instrument(skip_all, name = "register_client_handler")]
@@ -39,6 +50,9 @@ pub async fn handler(
     headers: HeaderMap,
     Json(body): Json<RegisterBody>,
 ) -> Result<Response> {
+    let tenant = state.tenant_store.get_tenant(&tenant_id).await?;
+    check_client_auth_mode(&state, &tenant, "register", &headers)?;
+
     if !authenticate_client(headers, &state.config.public_url, |client_id| {
         if let Some(client_id) = client_id {
             debug!(
@@ -68,7 +82,6 @@ pub async fn handler(
     }
 
     let push_type = body.push_type.as_str().try_into()?;
-    let tenant = state.tenant_store.get_tenant(&tenant_id).await?;
     let supported_providers = tenant.providers();
     if !supported_providers.contains(&push_type) {
         return Err(ProviderNotAvailable(push_type.into()));
@@ -78,6 +91,24 @@ pub async fn handler(
         return Err(EmptyField("token".to_string()));
     }
 
+    // Web Push tokens are the browser's serialized `PushSubscription`
+    if push_type == ProviderKind::WebPush {
+        WebPushSubscription::from_token(&body.token)?;
+    }
+
+    if let Some(settings) = &body.settings {
+        settings.validate()?;
+    }
+
+    // Other providers have no use for the bundle id
+    let apns_topic = match push_type {
+        ProviderKind::Apns | ProviderKind::ApnsSandbox => body.apns_topic,
+        _ => None,
+    };
+    if let Some(apns_topic) = &apns_topic {
+        tenant.apns_topic_for(Some(apns_topic))?;
+    }
+
     let client_id = body
         .client_id
         .as_ref()
@@ -95,11 +126,22 @@ pub async fn handler(
                 push_type,
                 token: body.token,
                 always_raw,
+                apns_topic,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             state.metrics.as_ref(),
         )
         .await?;
 
+    if let Some(settings) = body.settings {
+        state
+            .client_store
+            .update_client_settings(&tenant_id, &client_id, &settings.into())
+            .await?;
+    }
+
     debug!(
         %tenant_id, %client_id, %push_type, "registered client"
     );
@@ -3,7 +3,14 @@ use axum_client_ip::SecureClientIp;
 use {
     crate::{
         error::Result,
-        handlers::{push_message::PushMessageBody, register_client::RegisterBody, Response},
+        handlers::{
+            client_badge::BadgeBody,
+            client_settings::ClientSettingsBody,
+            push_batch::{BatchPushMessageBody, BatchPushResponse},
+            push_message::PushMessageBody,
+            register_client::RegisterBody,
+            Response,
+        },
         middleware::validate_signature::RequireValidSignature,
         state::AppState,
         stores::tenant::DEFAULT_TENANT_ID,
@@ -36,6 +43,61 @@ pub async fn delete_handler(
     .await
 }
 
+pub async fn get_settings_handler(
+    Path(id): Path<String>,
+    state: StateExtractor<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ClientSettingsBody>> {
+    #[cfg(feature = "multitenant")]
+    return Err(MissingTenantId);
+
+    #[cfg(not(feature = "multitenant"))]
+    crate::handlers::client_settings::get_handler(
+        Path((DEFAULT_TENANT_ID.to_string(), id)),
+        state,
+        headers,
+    )
+    .await
+}
+
+pub async fn update_settings_handler(
+    Path(id): Path<String>,
+    state: StateExtractor<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Json<ClientSettingsBody>,
+) -> Result<Response> {
+    #[cfg(feature = "multitenant")]
+    return Err(MissingTenantId);
+
+    #[cfg(not(feature = "multitenant"))]
+    crate::handlers::client_settings::update_handler(
+        Path((DEFAULT_TENANT_ID.to_string(), id)),
+        state,
+        headers,
+        body,
+    )
+    .await
+}
+
+pub async fn update_badge_handler(
+    Path(id): Path<String>,
+    state: StateExtractor<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Json<BadgeBody>,
+) -> Result<Response> {
+    #[cfg(feature = "multitenant")]
+    return Err(MissingTenantId);
+
+    #[cfg(not(feature = "multitenant"))]
+    crate::handlers::client_badge::handler(
+        Path((DEFAULT_TENANT_ID.to_string(), id)),
+        state,
+        headers,
+        body,
+    )
+    .await
+}
+
 pub async fn push_handler(
     #[cfg(feature = "analytics")] SecureClientIp(client_ip): SecureClientIp,
     Path(id): Path<String>,
@@ -92,3 +154,29 @@ pub async fn register_handler(
     )
     .await;
 }
+
+pub async fn push_batch_handler(
+    #[cfg(feature = "analytics")] SecureClientIp(client_ip): SecureClientIp,
+    state: StateExtractor<Arc<AppState>>,
+    valid_sig: RequireValidSignature<Json<BatchPushMessageBody>>,
+) -> Result<Json<BatchPushResponse>> {
+    #[cfg(feature = "multitenant")]
+    return Err(MissingTenantId);
+
+    #[cfg(all(not(feature = "multitenant"), feature = "analytics"))]
+    return crate::handlers::push_batch::handler(
+        SecureClientIp(client_ip),
+        Path(DEFAULT_TENANT_ID.to_string()),
+        state,
+        valid_sig,
+    )
+    .await;
+
+    #[cfg(all(not(feature = "multitenant"), not(feature = "analytics")))]
+    return crate::handlers::push_batch::handler(
+        Path(DEFAULT_TENANT_ID.to_string()),
+        state,
+        valid_sig,
+    )
+    .await;
+}
@@ -0,0 +1,194 @@
+use {
+    crate::{
+        error::{Error, Error::InvalidTemplates},
+        handlers::validate_tenant_request,
+        jwt_validation::TenantScope,
+        log::prelude::*,
+        state::AppState,
+        stores::tenant::{is_valid_locale, normalize_locale, NotificationTemplate},
+    },
+    axum::{
+        extract::{Path, State},
+        http::HeaderMap,
+        Json,
+    },
+    serde::{Deserialize, Serialize},
+    std::{collections::HashSet, sync::Arc},
+    tracing::instrument,
+};
+
+/// Maximum number of templates per tenant
+pub const MAX_TEMPLATES: usize = 256;
+/// Limits in bytes, keeping the templated alert well within the APNs payload
+/// limit
+pub const MAX_TEMPLATE_TITLE_LENGTH: usize = 256;
+pub const MAX_TEMPLATE_BODY_LENGTH: usize = 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct TemplateBody {
+    /// Relay tag of the messages to show the template for, untagged templates
+    /// apply to messages without a more specific template
+    #[serde(default)]
+    pub tag: Option<u32>,
+    /// BCP 47 language tag matched against the client's locale, templates
+    /// without a locale are used when none matches
+    #[serde(default)]
+    pub locale: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TemplatesBody {
+    pub templates: Vec<TemplateBody>,
+}
+
+impl TemplatesBody {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.templates.len() > MAX_TEMPLATES {
+            return Err(InvalidTemplates(format!(
+                "at most {MAX_TEMPLATES} templates are allowed"
+            )));
+        }
+
+        let mut keys = HashSet::new();
+        for template in &self.templates {
+            if let Some(locale) = &template.locale {
+                if !is_valid_locale(locale) {
+                    return Err(InvalidTemplates(format!("invalid locale {locale}")));
+                }
+            }
+            if template.title.is_empty() || template.title.len() > MAX_TEMPLATE_TITLE_LENGTH {
+                return Err(InvalidTemplates(format!(
+                    "titles must be between 1 and {MAX_TEMPLATE_TITLE_LENGTH} bytes"
+                )));
+            }
+            if template
+                .body
+                .as_ref()
+                .is_some_and(|body| body.len() > MAX_TEMPLATE_BODY_LENGTH)
+            {
+                return Err(InvalidTemplates(format!(
+                    "bodies must be at most {MAX_TEMPLATE_BODY_LENGTH} bytes"
+                )));
+            }
+            let locale = template.locale.as_deref().map(normalize_locale);
+            if !keys.insert((template.tag, locale)) {
+                return Err(InvalidTemplates(format!(
+                    "duplicate template for tag {:?} and locale {:?}",
+                    template.tag, template.locale
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<TemplateBody> for NotificationTemplate {
+    fn from(body: TemplateBody) -> Self {
+        NotificationTemplate {
+            tag: body.tag.map(i64::from),
+            locale: body
+                .locale
+                .as_deref()
+                .map(normalize_locale)
+                .unwrap_or_default(),
+            title: body.title,
+            body: body.body,
+        }
+    }
+}
+
+impl From<NotificationTemplate> for TemplateBody {
+    fn from(template: NotificationTemplate) -> Self {
+        TemplateBody {
+            // Tags are only ever written from `u32` values
+            tag: template.tag.and_then(|tag| u32::try_from(tag).ok()),
+            locale: (!template.locale.is_empty()).then_some(template.locale),
+            title: template.title,
+            body: template.body,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UpdateTemplatesResponse {
+    success: bool,
+}
+
+#[instrument(skip_all, name = "get_tenant_templates_handler")]
+pub async fn get_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<TemplatesBody>, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Read).await?;
+
+    // -- check if tenant is real
+    let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
+
+    let templates = state.tenant_store.get_templates(&id).await?;
+
+    Ok(Json(TemplatesBody {
+        templates: templates.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// Replaces all of the tenant's templates
+#[instrument(skip_all, name = "update_tenant_templates_handler")]
+pub async fn update_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<TemplatesBody>,
+) -> Result<Json<UpdateTemplatesResponse>, Error> {
+    verify_tenant(&state, &headers, &id, TenantScope::Write).await?;
+
+    body.validate()?;
+
+    // -- check if tenant is real
+    let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
+
+    let templates = body
+        .templates
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<NotificationTemplate>>();
+    state.tenant_store.set_templates(&id, &templates).await?;
+
+    debug!(
+        tenant_id = %id,
+        templates = templates.len(),
+        "updated tenant templates"
+    );
+
+    Ok(Json(UpdateTemplatesResponse { success: true }))
+}
+
+async fn verify_tenant(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: &str,
+    scope: TenantScope,
+) -> Result<(), Error> {
+    #[cfg(feature = "cloud")]
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, headers, id, scope).await;
+
+    #[cfg(not(feature = "cloud"))]
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, headers, scope);
+
+    if let Err(e) = jwt_verification_result {
+        error!(
+            tenant_id = %id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    Ok(())
+}
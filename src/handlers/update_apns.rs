@@ -3,6 +3,8 @@ use {
         error::{Error, Error::InvalidMultipartBody},
         handlers::validate_tenant_request,
         increment_counter,
+        jwt_validation::TenantScope,
+        providers::ProviderKind,
         state::AppState,
         stores::tenant::{TenantApnsUpdateAuth, TenantApnsUpdateParams},
     },
@@ -126,11 +128,17 @@ pub async fn handler(
 ) -> Result<Json<UpdateTenantApnsResponse>, Error> {
     // JWT verification
     #[cfg(feature = "cloud")]
-    let jwt_verification_result =
-        validate_tenant_request(&state.jwt_validation_client, &headers, &id).await;
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
 
     #[cfg(not(feature = "cloud"))]
-    let jwt_verification_result = validate_tenant_request(&state.jwt_validation_client, &headers);
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
 
     if let Err(e) = jwt_verification_result {
         error!(
@@ -199,6 +207,7 @@ pub async fn handler(
             .tenant_store
             .update_tenant_apns(&id, update_body)
             .await?;
+        state.provider_cache.invalidate_tenant(&id);
 
         if apns_updates.auth.is_none() {
             // Breakout early as there are no auth updates
@@ -259,13 +268,20 @@ pub async fn handler(
             .tenant_store
             .update_tenant_apns_auth(&id, auth)
             .await?;
+        state.provider_cache.invalidate_tenant(&id);
 
         increment_counter!(state.metrics, tenant_apns_updates);
 
-        if new_tenant.suspended {
+        if new_tenant
+            .provider_suspension(&ProviderKind::Apns)
+            .is_some()
+        {
             // If suspended, it can be restored now because valid credentials have been
             // provided
-            state.tenant_store.unsuspend_tenant(&new_tenant.id).await?;
+            state
+                .tenant_store
+                .unsuspend_provider(&new_tenant.id, &ProviderKind::Apns)
+                .await?;
         }
 
         return Ok(Json(UpdateTenantApnsResponse { success: true }));
@@ -0,0 +1,103 @@
+use {
+    crate::{
+        error::{Error, Error::InvalidApnsTopics},
+        handlers::validate_tenant_request,
+        increment_counter,
+        jwt_validation::TenantScope,
+        log::prelude::*,
+        state::AppState,
+        stores::tenant::{ApnsType, MAX_APNS_TOPICS},
+    },
+    axum::{
+        extract::{Path, State},
+        http::HeaderMap,
+        Json,
+    },
+    serde::{Deserialize, Serialize},
+    std::{collections::HashSet, sync::Arc},
+    tracing::instrument,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApnsTopicsBody {
+    /// Bundle ids sharing the tenant's token credentials besides the primary
+    /// `apns_topic`, replacing the existing ones
+    pub apns_topics: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateTenantApnsTopicsResponse {
+    success: bool,
+}
+
+#[instrument(skip_all, name = "update_apns_topics_handler")]
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<ApnsTopicsBody>,
+) -> Result<Json<UpdateTenantApnsTopicsResponse>, Error> {
+    // JWT token verification
+    #[cfg(feature = "cloud")]
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
+
+    #[cfg(not(feature = "cloud"))]
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
+
+    if let Err(e) = jwt_verification_result {
+        error!(
+            tenant_id = %id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    if body.apns_topics.len() > MAX_APNS_TOPICS {
+        return Err(InvalidApnsTopics(format!(
+            "at most {MAX_APNS_TOPICS} topics are allowed"
+        )));
+    }
+    let mut topics = HashSet::new();
+    for topic in &body.apns_topics {
+        if topic.is_empty() || topic.contains(char::is_whitespace) {
+            return Err(InvalidApnsTopics(format!("invalid topic {topic:?}")));
+        }
+        if !topics.insert(topic) {
+            return Err(InvalidApnsTopics(format!("duplicate topic {topic}")));
+        }
+    }
+
+    let tenant = state.tenant_store.get_tenant(&id).await?;
+
+    // Certificates are issued for a single topic, only token credentials can
+    // be shared between apps
+    if !body.apns_topics.is_empty() && tenant.get_apns_type() != Some(ApnsType::Token) {
+        return Err(InvalidApnsTopics(
+            "additional topics require APNs token authentication".to_string(),
+        ));
+    }
+
+    state
+        .tenant_store
+        .update_tenant_apns_topics(&id, &body.apns_topics)
+        .await?;
+    state.provider_cache.invalidate_tenant(&id);
+
+    increment_counter!(state.metrics, tenant_apns_updates);
+
+    debug!(
+        tenant_id = %id,
+        apns_topics = ?body.apns_topics,
+        "updated tenant apns topics"
+    );
+
+    Ok(Json(UpdateTenantApnsTopicsResponse { success: true }))
+}
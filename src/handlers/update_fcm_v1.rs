@@ -3,6 +3,8 @@ use {
         error::{Error, Error::InvalidMultipartBody},
         handlers::validate_tenant_request,
         increment_counter,
+        jwt_validation::TenantScope,
+        providers::ProviderKind,
         state::AppState,
         stores::tenant::TenantFcmV1UpdateParams,
     },
@@ -36,14 +38,20 @@ pub async fn handler(
 ) -> Result<Json<UpdateTenantFcmV1Response>, Error> {
     // JWT token verification
     #[cfg(feature = "cloud")]
-    let jwt_verification_result =
-        validate_tenant_request(&state.jwt_validation_client, &headers, &id).await;
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
 
     // -- check if tenant is real
     let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
 
     #[cfg(not(feature = "cloud"))]
-    let jwt_verification_result = validate_tenant_request(&state.jwt_validation_client, &headers);
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
 
     if let Err(e) = jwt_verification_result {
         error!(
@@ -91,11 +99,15 @@ pub async fn handler(
         .tenant_store
         .update_tenant_fcm_v1(&id, update_body)
         .await?;
+    state.provider_cache.invalidate_tenant(&id);
 
-    if new_tenant.suspended {
+    if new_tenant.provider_suspension(&ProviderKind::Fcm).is_some() {
         // If suspended, it can be restored now because valid credentials have been
         // provided
-        state.tenant_store.unsuspend_tenant(&new_tenant.id).await?;
+        state
+            .tenant_store
+            .unsuspend_provider(&new_tenant.id, &ProviderKind::Fcm)
+            .await?;
     }
 
     increment_counter!(state.metrics, tenant_fcm_v1_updates);
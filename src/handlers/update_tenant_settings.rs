@@ -0,0 +1,123 @@
+use {
+    crate::{
+        error::{Error, Error::InvalidTenantSettings},
+        handlers::validate_tenant_request,
+        jwt_validation::TenantScope,
+        log::prelude::*,
+        state::AppState,
+        stores::tenant::{ClientAuthMode, TenantSettingsUpdateParams, MAX_COALESCE_WINDOW_SECS},
+    },
+    axum::{
+        extract::{Path, State},
+        http::HeaderMap,
+        Json,
+    },
+    serde::{Deserialize, Deserializer, Serialize},
+    std::sync::Arc,
+    tracing::instrument,
+};
+
+/// Settings left out of the body are unchanged
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TenantSettingsBody {
+    /// Messages for the same client and topic received within this many
+    /// seconds replace each other on the device, `null` disables coalescing
+    #[serde(
+        default,
+        deserialize_with = "deserialize_nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub coalesce_window_secs: Option<Option<u32>>,
+    /// Retry APNs tokens rejected as bad by one environment against the other
+    /// one, moving the client on success instead of deleting it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apns_environment_fallback: Option<bool>,
+    /// Whether client registrations and deletions require an `Authorization`
+    /// header, `null` uses the server's mode
+    #[serde(
+        default,
+        deserialize_with = "deserialize_nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub client_auth_mode: Option<Option<ClientAuthMode>>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`)
+fn deserialize_nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize)]
+pub struct UpdateTenantSettingsResponse {
+    success: bool,
+}
+
+#[instrument(skip_all, name = "update_tenant_settings_handler")]
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<TenantSettingsBody>,
+) -> Result<Json<UpdateTenantSettingsResponse>, Error> {
+    // JWT token verification
+    #[cfg(feature = "cloud")]
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
+
+    #[cfg(not(feature = "cloud"))]
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
+
+    if let Err(e) = jwt_verification_result {
+        error!(
+            tenant_id = %id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    if body
+        .coalesce_window_secs
+        .flatten()
+        .is_some_and(|secs| secs > MAX_COALESCE_WINDOW_SECS)
+    {
+        return Err(InvalidTenantSettings(format!(
+            "coalesce_window_secs must be at most {MAX_COALESCE_WINDOW_SECS}"
+        )));
+    }
+
+    // -- check if tenant is real
+    let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
+
+    state
+        .tenant_store
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: body.coalesce_window_secs,
+                apns_environment_fallback: body.apns_environment_fallback,
+                client_auth_mode: body.client_auth_mode,
+            },
+        )
+        .await?;
+
+    debug!(
+        tenant_id = %id,
+        coalesce_window_secs = ?body.coalesce_window_secs,
+        apns_environment_fallback = ?body.apns_environment_fallback,
+        client_auth_mode = ?body.client_auth_mode,
+        "updated tenant settings"
+    );
+
+    Ok(Json(UpdateTenantSettingsResponse { success: true }))
+}
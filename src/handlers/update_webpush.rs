@@ -0,0 +1,122 @@
+use {
+    crate::{
+        error::{Error, Error::InvalidMultipartBody},
+        handlers::validate_tenant_request,
+        increment_counter,
+        jwt_validation::TenantScope,
+        providers::{webpush::VapidKey, ProviderKind},
+        state::AppState,
+        stores::tenant::TenantWebPushUpdateParams,
+    },
+    axum::{
+        extract::{Multipart, Path, State},
+        http::HeaderMap,
+        Json,
+    },
+    serde::Serialize,
+    std::sync::Arc,
+    tracing::{error, instrument},
+};
+
+pub struct WebPushUpdateBody {
+    vapid_private_key: Option<String>,
+    vapid_subject: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateTenantWebPushResponse {
+    success: bool,
+    vapid_public_key: String,
+}
+
+#[instrument(skip_all, name = "update_webpush_handler")]
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    mut form_body: Multipart,
+) -> Result<Json<UpdateTenantWebPushResponse>, Error> {
+    // JWT token verification
+    #[cfg(feature = "cloud")]
+    let jwt_verification_result = validate_tenant_request(
+        &state.jwt_validation_client,
+        &headers,
+        &id,
+        TenantScope::Write,
+    )
+    .await;
+
+    // -- check if tenant is real
+    let _existing_tenant = state.tenant_store.get_tenant(&id).await?;
+
+    #[cfg(not(feature = "cloud"))]
+    let jwt_verification_result =
+        validate_tenant_request(&state.jwt_validation_client, &headers, TenantScope::Write);
+
+    if let Err(e) = jwt_verification_result {
+        error!(
+            tenant_id = %id,
+            err = ?e,
+            "JWT verification failed"
+        );
+        return Err(e);
+    }
+
+    // ---- retrieve body from form
+    let mut body = WebPushUpdateBody {
+        vapid_private_key: None,
+        vapid_subject: None,
+    };
+    while let Some(field) = form_body.next_field().await? {
+        let name = field.name().unwrap_or("unknown").to_string();
+        let data = field.text().await?;
+
+        match name.to_lowercase().as_str() {
+            "vapid_private_key" => body.vapid_private_key = Some(data),
+            "vapid_subject" => body.vapid_subject = Some(data),
+            _ => {}
+        };
+    }
+    let (Some(vapid_private_key), Some(vapid_subject)) =
+        (body.vapid_private_key, body.vapid_subject)
+    else {
+        return Err(InvalidMultipartBody);
+    };
+
+    // Validate the key pair and derive the public key clients subscribe with
+    let vapid_public_key = VapidKey::new(&vapid_private_key, vapid_subject.clone())?
+        .public_key()
+        .to_string();
+
+    // ---- handler
+    let update_body = TenantWebPushUpdateParams {
+        vapid_public_key: vapid_public_key.clone(),
+        vapid_private_key,
+        vapid_subject,
+    };
+
+    let new_tenant = state
+        .tenant_store
+        .update_tenant_webpush(&id, update_body)
+        .await?;
+    state.provider_cache.invalidate_tenant(&id);
+
+    if new_tenant
+        .provider_suspension(&ProviderKind::WebPush)
+        .is_some()
+    {
+        // If suspended, it can be restored now because valid credentials have been
+        // provided
+        state
+            .tenant_store
+            .unsuspend_provider(&new_tenant.id, &ProviderKind::WebPush)
+            .await?;
+    }
+
+    increment_counter!(state.metrics, tenant_webpush_updates);
+
+    Ok(Json(UpdateTenantWebPushResponse {
+        success: true,
+        vapid_public_key,
+    }))
+}
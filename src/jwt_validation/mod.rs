@@ -1,33 +1,307 @@
+#[cfg(feature = "multitenant")]
+use {crate::state::AppState, std::time::Duration, tokio::sync::broadcast};
 use {
-    crate::error::Result,
-    jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation},
+    crate::{
+        error::{Error, Result},
+        log::prelude::*,
+    },
+    jsonwebtoken::{
+        jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet},
+        Algorithm, DecodingKey, TokenData, Validation,
+    },
     serde::{Deserialize, Serialize},
+    std::sync::{Arc, RwLock},
 };
 
+/// Scope granting read access to the tenant
+pub const SCOPE_TENANT_READ: &str = "tenant:read";
+/// Scope granting read and write access to the tenant
+pub const SCOPE_TENANT_WRITE: &str = "tenant:write";
+/// Scope granting access to the operator API across all tenants
+pub const SCOPE_ADMIN: &str = "admin";
+
 #[derive(Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    /// Space separated scopes, tokens without one predate scopes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// Access a tenant management route requires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantScope {
+    Read,
+    Write,
+}
+
+impl TenantScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => SCOPE_TENANT_READ,
+            Self::Write => SCOPE_TENANT_WRITE,
+        }
+    }
+}
+
+impl Claims {
+    /// Whether the token grants the scope, `tenant:write` implies
+    /// `tenant:read`. Tokens without scopes are only accepted when
+    /// `require_scopes` is off.
+    pub fn has_scope(&self, scope: TenantScope, require_scopes: bool) -> bool {
+        let Some(scopes) = &self.scope else {
+            return !require_scopes;
+        };
+
+        scopes.split_whitespace().any(|granted| {
+            granted == scope.as_str()
+                || (scope == TenantScope::Read && granted == SCOPE_TENANT_WRITE)
+        })
+    }
+
+    /// Whether the token grants the `admin` scope, which is never implied
+    pub fn is_admin(&self) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|scope| scope == SCOPE_ADMIN))
+    }
 }
 
+/// Key from the JWKS document, along with the algorithm it verifies
 #[derive(Clone)]
-pub struct JwtValidationClient {
+pub struct JwksKey {
+    pub key_id: Option<String>,
+    pub algorithm: Algorithm,
     decoding_key: DecodingKey,
-    validation: Validation,
+}
+
+impl JwksKey {
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let algorithm = match (jwk.common.algorithm, &jwk.algorithm) {
+            (Some(algorithm), _) => algorithm,
+            (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+            (None, AlgorithmParameters::EllipticCurve(params)) => match params.curve {
+                EllipticCurve::P384 => Algorithm::ES384,
+                _ => Algorithm::ES256,
+            },
+            (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+            (None, AlgorithmParameters::OctetKey(_)) => {
+                return Err(Error::InvalidJwks(
+                    "symmetric keys are not accepted".to_string(),
+                ))
+            }
+        };
+        if matches!(
+            algorithm,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(Error::InvalidJwks(
+                "symmetric keys are not accepted".to_string(),
+            ));
+        }
+
+        Ok(JwksKey {
+            key_id: jwk.common.key_id.clone(),
+            algorithm,
+            decoding_key: DecodingKey::from_jwk(jwk)
+                .map_err(|e| Error::InvalidJwks(e.to_string()))?,
+        })
+    }
+}
+
+/// Parse a JWKS document, keys that can't be used are skipped
+pub fn parse_jwks(document: &str) -> Result<Vec<JwksKey>> {
+    let jwks: JwkSet =
+        serde_json::from_str(document).map_err(|e| Error::InvalidJwks(e.to_string()))?;
+
+    Ok(jwks
+        .keys
+        .iter()
+        .filter_map(|jwk| match JwksKey::from_jwk(jwk) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                warn!(key_id = ?jwk.common.key_id, "skipping JWKS key: {e:?}");
+                None
+            }
+        })
+        .collect())
+}
+
+/// Where the JWKS document is reloaded from, a file path (optionally as a
+/// `file://` URL) or an HTTP endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwksSource {
+    File(String),
+    Url(String),
+}
+
+impl JwksSource {
+    pub fn parse(source: &str) -> Self {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            JwksSource::Url(source.to_string())
+        } else {
+            let path = source.strip_prefix("file://").unwrap_or(source);
+            JwksSource::File(path.to_string())
+        }
+    }
+
+    pub async fn fetch(&self, http_client: &reqwest::Client) -> Result<Vec<JwksKey>> {
+        let document = match self {
+            JwksSource::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| Error::InvalidJwks(format!("reading {path}: {e}")))?,
+            JwksSource::Url(url) => {
+                http_client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?
+            }
+        };
+
+        parse_jwks(&document)
+    }
+}
+
+#[derive(Clone)]
+pub struct JwtValidationClient {
+    /// Shared `JWT_SECRET` for HS256 tokens, unset when only JWKS keys are
+    /// accepted
+    secret_key: Option<DecodingKey>,
+    /// Keys from the JWKS source, replaced on every reload
+    jwks_keys: Arc<RwLock<Vec<JwksKey>>>,
+    audience: Option<String>,
+    issuer: Option<String>,
+    require_scopes: bool,
 }
 
 impl JwtValidationClient {
     pub fn new(jwt_secret: String) -> JwtValidationClient {
         JwtValidationClient {
-            decoding_key: DecodingKey::from_secret(jwt_secret.as_bytes()),
-            validation: Validation::new(Algorithm::HS256),
+            secret_key: (!jwt_secret.is_empty())
+                .then(|| DecodingKey::from_secret(jwt_secret.as_bytes())),
+            jwks_keys: Arc::new(RwLock::new(vec![])),
+            audience: None,
+            issuer: None,
+            require_scopes: false,
         }
     }
 
+    /// Require the `aud` claim to match
+    pub fn with_audience(mut self, audience: Option<String>) -> Self {
+        self.audience = audience;
+        self
+    }
+
+    /// Require the `iss` claim to match
+    pub fn with_issuer(mut self, issuer: Option<String>) -> Self {
+        self.issuer = issuer;
+        self
+    }
+
+    /// Reject tokens without a `scope` claim
+    pub fn with_require_scopes(mut self, require_scopes: bool) -> Self {
+        self.require_scopes = require_scopes;
+        self
+    }
+
+    pub fn require_scopes(&self) -> bool {
+        self.require_scopes
+    }
+
+    pub fn set_jwks_keys(&self, keys: Vec<JwksKey>) {
+        *self
+            .jwks_keys
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = keys;
+    }
+
+    fn validation(&self, algorithm: Algorithm) -> Validation {
+        let mut validation = Validation::new(algorithm);
+        if let Some(audience) = &self.audience {
+            validation.set_audience(&[audience]);
+            validation.required_spec_claims.insert("aud".to_string());
+        }
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+        validation
+    }
+
     pub fn is_valid_token(&self, jwt: String) -> Result<TokenData<Claims>> {
-        Ok(jsonwebtoken::decode::<Claims>(
-            &jwt,
-            &self.decoding_key,
-            &self.validation,
-        )?)
+        let header = jsonwebtoken::decode_header(&jwt)?;
+
+        if header.alg == Algorithm::HS256 {
+            let Some(secret_key) = &self.secret_key else {
+                return Err(Error::InvalidAuthentication);
+            };
+            return Ok(jsonwebtoken::decode::<Claims>(
+                &jwt,
+                secret_key,
+                &self.validation(Algorithm::HS256),
+            )?);
+        }
+
+        let keys = self
+            .jwks_keys
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .filter(|key| key.algorithm == header.alg)
+            .filter(|key| header.kid.is_none() || key.key_id == header.kid)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let validation = self.validation(header.alg);
+        let mut result = Err(Error::InvalidAuthentication);
+        for key in keys {
+            match jsonwebtoken::decode::<Claims>(&jwt, &key.decoding_key, &validation) {
+                Ok(token_data) => return Ok(token_data),
+                Err(e) => result = Err(e.into()),
+            }
+        }
+
+        result
+    }
+}
+
+/// Spawn the task periodically reloading the JWKS document, a failed reload
+/// keeps the previously loaded keys
+#[cfg(feature = "multitenant")]
+pub fn spawn_jwks_refresh(state: Arc<AppState>, shutdown: &broadcast::Receiver<()>) {
+    let Some(source) = state.config.jwt_jwks_source.as_deref() else {
+        return;
+    };
+    let source = JwksSource::parse(source);
+    tokio::spawn(run_jwks_refresh(state, source, shutdown.resubscribe()));
+}
+
+#[cfg(feature = "multitenant")]
+async fn run_jwks_refresh(
+    state: Arc<AppState>,
+    source: JwksSource,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(state.config.jwt_jwks_refresh_secs));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => match source.fetch(&state.http_client).await {
+                Ok(keys) => {
+                    debug!(keys = keys.len(), "reloaded JWKS keys");
+                    state.jwt_validation_client.set_jwks_keys(keys);
+                }
+                Err(e) => warn!("error reloading JWKS keys from {source:?}: {e:?}"),
+            },
+            _ = shutdown.recv() => {
+                info!("JWKS refresh stopping");
+                return;
+            }
+        }
     }
 }
@@ -11,13 +11,15 @@ use {
     crate::{log::prelude::*, state::TenantStoreArc},
     axum::{
         extract::Request,
-        routing::{delete, get, post},
+        routing::{delete, get, post, put},
         Router,
     },
     axum_client_ip::SecureClientIpSource,
     config::Config,
     hyper::http::Method,
-    middleware::rate_limit::rate_limit_middleware,
+    middleware::{
+        rate_limit::rate_limit_middleware, validate_signature::forget_signature_on_failure,
+    },
     sqlx::{
         postgres::{PgConnectOptions, PgPoolOptions},
         ConnectOptions,
@@ -60,6 +62,8 @@ pub mod metrics;
 pub mod middleware;
 pub mod networking;
 pub mod providers;
+pub mod pruner;
+pub mod queue;
 pub mod relay;
 pub mod state;
 pub mod stores;
@@ -113,6 +117,8 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
         Arc::new(store.clone()),
         Arc::new(store.clone()),
         tenant_store,
+        Arc::new(store.clone()),
+        Arc::new(store.clone()),
     )?;
 
     #[cfg(any(feature = "analytics", feature = "geoblock"))]
@@ -170,8 +176,19 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
     // TODO use value again
     let _allowed_origins = state.config.cors_allowed_origins.clone();
 
+    relay::load_keys(&state).await?;
+
     let state_arc = Arc::new(state);
 
+    if state_arc.config.queue_enabled {
+        queue::spawn_workers(state_arc.clone(), &shutdown);
+    }
+
+    pruner::spawn(state_arc.clone(), &shutdown);
+    relay::spawn_key_refresh(state_arc.clone(), &shutdown);
+    #[cfg(feature = "multitenant")]
+    jwt_validation::spawn_jwks_refresh(state_arc.clone(), &shutdown);
+
     let global_middleware = ServiceBuilder::new()
         .set_x_request_id(MakeRequestUuid)
         .layer(
@@ -215,7 +232,30 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
             .route("/:id/fcm_v1", post(handlers::update_fcm_v1::handler))
             .route("/:id/fcm_v1", delete(handlers::delete_fcm_v1::handler))
             .route("/:id/apns", post(handlers::update_apns::handler))
+            .route("/:id/apns/topics", post(handlers::update_apns_topics::handler))
             .route("/:id/apns", delete(handlers::delete_apns::handler))
+            .route("/:id/webpush", post(handlers::update_webpush::handler))
+            .route("/:id/webpush", delete(handlers::delete_webpush::handler))
+            .route("/:id/settings", post(handlers::update_tenant_settings::handler))
+            .route(
+                "/:id/templates",
+                get(handlers::tenant_templates::get_handler)
+                    .put(handlers::tenant_templates::update_handler),
+            )
+            .route(
+                "/:id/dead-letters",
+                get(handlers::dead_letters::list_handler)
+                    .delete(handlers::dead_letters::purge_handler),
+            )
+            .route(
+                "/:id/dead-letters/:dead_letter_id",
+                get(handlers::dead_letters::get_handler)
+                    .delete(handlers::dead_letters::delete_handler),
+            )
+            .route(
+                "/:id/dead-letters/:dead_letter_id/replay",
+                post(handlers::dead_letters::replay_handler),
+            )
             .layer(
                 global_middleware.clone().layer(
                     CorsLayer::new()
@@ -232,6 +272,26 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
             )
             .layer(axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware));
 
+        // Operator API, authenticated with tokens carrying the `admin` scope
+        let admin_routes = Router::new()
+            .route("/tenants", get(handlers::admin_tenants::list_handler))
+            .route(
+                "/tenants/:id/suspend",
+                post(handlers::admin_tenants::suspend_handler),
+            )
+            .route(
+                "/tenants/:id/unsuspend",
+                post(handlers::admin_tenants::unsuspend_handler),
+            )
+            .route(
+                "/tenants/:id/suspensions",
+                get(handlers::admin_tenants::history_handler),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                state_arc.clone(),
+                rate_limit_middleware,
+            ));
+
         Router::new()
             .route("/health", get(handlers::health::handler))
             .route("/rate_limit_test", get(handlers::rate_limit_test::handler).layer(
@@ -240,6 +300,7 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
             .nest("/tenants", tenancy_routes.layer(
                 axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware),
             ))
+            .nest("/admin", admin_routes)
             .route(
                 "/:tenant_id/clients",
                 post(handlers::register_client::handler).layer(
@@ -252,10 +313,39 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
                     axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware),
                 ),
             )
+            .route(
+                "/:tenant_id/clients/:id/settings",
+                get(handlers::client_settings::get_handler)
+                    .put(handlers::client_settings::update_handler)
+                    .layer(axum::middleware::from_fn_with_state(
+                        state_arc.clone(),
+                        rate_limit_middleware,
+                    )),
+            )
+            .route(
+                "/:tenant_id/clients/:id/badge",
+                put(handlers::client_badge::handler).layer(
+                    axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware),
+                ),
+            )
+            .route(
+                "/:tenant_id/clients/:id/notifications/:notification_id",
+                get(handlers::get_notification::handler).layer(
+                    axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware),
+                ),
+            )
             // Rate limiting middleware is not applying to push_handler because it is used by the relay
+            .route(
+                "/:tenant_id/clients/batch",
+                post(handlers::push_batch::handler).layer(
+                    axum::middleware::from_fn_with_state(state_arc.clone(), forget_signature_on_failure),
+                ),
+            )
             .route(
                 "/:tenant_id/clients/:id",
-                post(handlers::push_message::handler),
+                post(handlers::push_message::handler).layer(
+                    axum::middleware::from_fn_with_state(state_arc.clone(), forget_signature_on_failure),
+                ),
             )
             .layer(global_middleware)
     };
@@ -278,10 +368,33 @@ pub async fn bootstap(mut shutdown: broadcast::Receiver<()>, config: Config) ->
                 axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware),
             ),
         )
+        .route(
+            "/clients/:id/settings",
+            get(handlers::single_tenant_wrappers::get_settings_handler)
+                .put(handlers::single_tenant_wrappers::update_settings_handler)
+                .layer(axum::middleware::from_fn_with_state(
+                    state_arc.clone(),
+                    rate_limit_middleware,
+                )),
+        )
+        .route(
+            "/clients/:id/badge",
+            put(handlers::single_tenant_wrappers::update_badge_handler).layer(
+                axum::middleware::from_fn_with_state(state_arc.clone(), rate_limit_middleware),
+            ),
+        )
         // Rate limiting middleware is not applying to push_handler because it is used by the relay
+        .route(
+            "/clients/batch",
+            post(handlers::single_tenant_wrappers::push_batch_handler).layer(
+                axum::middleware::from_fn_with_state(state_arc.clone(), forget_signature_on_failure),
+            ),
+        )
         .route(
             "/clients/:id",
-            post(handlers::single_tenant_wrappers::push_handler),
+            post(handlers::single_tenant_wrappers::push_handler).layer(
+                axum::middleware::from_fn_with_state(state_arc.clone(), forget_signature_on_failure),
+            ),
         )
         .layer(global_middleware);
 
@@ -15,6 +15,11 @@ pub struct Metrics {
     pub sent_fcm_notifications: Counter<u64>,
     pub sent_fcm_v1_notifications: Counter<u64>,
     pub sent_apns_notifications: Counter<u64>,
+    pub sent_webpush_notifications: Counter<u64>,
+    pub queued_notifications: Counter<u64>,
+    pub filtered_notifications: Counter<u64>,
+    pub dead_letters: Counter<u64>,
+    pub dead_letter_replays: Counter<u64>,
 
     pub registered_clients: Counter<u64>,
     pub registered_tenants: Counter<u64>,
@@ -22,10 +27,24 @@ pub struct Metrics {
     pub tenant_apns_updates: Counter<u64>,
     pub tenant_fcm_updates: Counter<u64>,
     pub tenant_fcm_v1_updates: Counter<u64>,
+    pub tenant_webpush_updates: Counter<u64>,
 
     pub tenant_suspensions: Counter<u64>,
     pub client_suspensions: Counter<u64>,
 
+    provider_retries: Counter<u64>,
+    provider_send_outcomes: Counter<u64>,
+    queue_deliveries: Counter<u64>,
+    quiet_hours_notifications: Counter<u64>,
+    apns_environment_switches: Counter<u64>,
+    provider_suspensions: Counter<u64>,
+    rejected_signatures: Counter<u64>,
+    unauthenticated_client_requests: Counter<u64>,
+
+    pruned_notifications: Counter<u64>,
+    notification_pruning_duration: Histogram<u64>,
+    pruned_suspended_clients: Counter<u64>,
+
     postgres_queries: Counter<u64>,
     postgres_query_latency: Histogram<u64>,
 }
@@ -71,6 +90,31 @@ impl Metrics {
             .with_description("The number of notifications sent to APNS")
             .init();
 
+        let sent_webpush_notification_counter = meter
+            .u64_counter("sent_webpush_notifications")
+            .with_description("The number of notifications sent to Web Push services")
+            .init();
+
+        let queued_notifications_counter = meter
+            .u64_counter("queued_notifications")
+            .with_description("The number of notifications persisted to the delivery queue")
+            .init();
+
+        let filtered_notifications_counter = meter
+            .u64_counter("filtered_notifications")
+            .with_description("The number of notifications dropped by client tag or topic filters")
+            .init();
+
+        let dead_letters_counter = meter
+            .u64_counter("dead_letters")
+            .with_description("The number of undeliverable notifications stored as dead letters")
+            .init();
+
+        let dead_letter_replays_counter = meter
+            .u64_counter("dead_letter_replays")
+            .with_description("The number of dead letters successfully replayed")
+            .init();
+
         let tenant_apns_updates_counter = meter
             .u64_counter("tenant_apns_updates")
             .with_description("The number of times tenants have updated their APNS")
@@ -86,6 +130,11 @@ impl Metrics {
             .with_description("The number of times tenants have updated their FCM")
             .init();
 
+        let tenant_webpush_updates_counter = meter
+            .u64_counter("tenant_webpush_updates")
+            .with_description("The number of times tenants have updated their Web Push")
+            .init();
+
         let tenant_suspensions_counter = meter
             .u64_counter("tenant_suspensions")
             .with_description("The number of tenants that have been suspended")
@@ -96,6 +145,74 @@ impl Metrics {
             .with_description("The number of clients that have been suspended")
             .init();
 
+        let provider_retries: Counter<u64> = meter
+            .u64_counter("provider_retries")
+            .with_description("The number of retried provider sends")
+            .init();
+
+        let provider_send_outcomes: Counter<u64> = meter
+            .u64_counter("provider_send_outcomes")
+            .with_description("The final outcome of provider sends, including retries")
+            .init();
+
+        let queue_deliveries: Counter<u64> = meter
+            .u64_counter("queue_deliveries")
+            .with_description("The outcome of delivery attempts made by the queue workers")
+            .init();
+
+        let quiet_hours_notifications: Counter<u64> = meter
+            .u64_counter("quiet_hours_notifications")
+            .with_description(
+                "The number of alert notifications silenced or deferred by quiet hours",
+            )
+            .init();
+
+        let apns_environment_switches: Counter<u64> = meter
+            .u64_counter("apns_environment_switches")
+            .with_description(
+                "The number of clients moved to the other APNs environment after their token was \
+                 rejected",
+            )
+            .init();
+
+        let provider_suspensions: Counter<u64> = meter
+            .u64_counter("provider_suspensions")
+            .with_description(
+                "The number of tenant providers that have been suspended due to invalid credentials",
+            )
+            .init();
+
+        let rejected_signatures: Counter<u64> = meter
+            .u64_counter("rejected_signatures")
+            .with_description(
+                "The number of validly signed relay requests rejected as stale or replayed",
+            )
+            .init();
+
+        let unauthenticated_client_requests: Counter<u64> = meter
+            .u64_counter("unauthenticated_client_requests")
+            .with_description(
+                "The number of client registrations and deletions without an authorization token",
+            )
+            .init();
+
+        let pruned_notifications: Counter<u64> = meter
+            .u64_counter("pruned_notifications")
+            .with_description("The number of notifications deleted by the retention pruning")
+            .init();
+
+        let notification_pruning_duration: Histogram<u64> = meter
+            .u64_histogram("notification_pruning_duration")
+            .with_description("The duration of notification pruning runs in milliseconds")
+            .init();
+
+        let pruned_suspended_clients: Counter<u64> = meter
+            .u64_counter("pruned_suspended_clients")
+            .with_description(
+                "The number of clients deleted after being suspended for the grace period",
+            )
+            .init();
+
         let postgres_queries: Counter<u64> = meter
             .u64_counter("postgres_queries")
             .with_description("The number of Postgres queries executed")
@@ -112,17 +229,100 @@ impl Metrics {
             sent_fcm_notifications: sent_fcm_notification_counter,
             sent_fcm_v1_notifications: sent_fcm_v1_notification_counter,
             sent_apns_notifications: sent_apns_notification_counter,
+            sent_webpush_notifications: sent_webpush_notification_counter,
+            queued_notifications: queued_notifications_counter,
+            filtered_notifications: filtered_notifications_counter,
+            dead_letters: dead_letters_counter,
+            dead_letter_replays: dead_letter_replays_counter,
             registered_tenants: tenants_counter,
             tenant_apns_updates: tenant_apns_updates_counter,
             tenant_fcm_updates: tenant_fcm_updates_counter,
             tenant_fcm_v1_updates: tenant_fcm_v1_updates_counter,
+            tenant_webpush_updates: tenant_webpush_updates_counter,
             tenant_suspensions: tenant_suspensions_counter,
             client_suspensions: client_suspensions_counter,
+            provider_retries,
+            provider_send_outcomes,
+            queue_deliveries,
+            quiet_hours_notifications,
+            apns_environment_switches,
+            provider_suspensions,
+            rejected_signatures,
+            unauthenticated_client_requests,
+            pruned_notifications,
+            notification_pruning_duration,
+            pruned_suspended_clients,
             postgres_queries,
             postgres_query_latency,
         }
     }
 
+    pub fn provider_retry(&self, provider: &'static str) {
+        self.provider_retries
+            .add(1, &[KeyValue::new("provider", provider)]);
+    }
+
+    pub fn provider_send_outcome(&self, provider: &'static str, outcome: &'static str) {
+        self.provider_send_outcomes.add(
+            1,
+            &[
+                KeyValue::new("provider", provider),
+                KeyValue::new("outcome", outcome),
+            ],
+        );
+    }
+
+    pub fn queue_delivery(&self, outcome: &'static str) {
+        self.queue_deliveries
+            .add(1, &[KeyValue::new("outcome", outcome)]);
+    }
+
+    pub fn quiet_hours_notification(&self, mode: &'static str) {
+        self.quiet_hours_notifications
+            .add(1, &[KeyValue::new("mode", mode)]);
+    }
+
+    pub fn apns_environment_switch(&self, to: &'static str) {
+        self.apns_environment_switches
+            .add(1, &[KeyValue::new("to", to)]);
+    }
+
+    pub fn provider_suspension(&self, provider: &'static str) {
+        self.provider_suspensions
+            .add(1, &[KeyValue::new("provider", provider)]);
+    }
+
+    pub fn rejected_signature(&self, reason: &'static str) {
+        self.rejected_signatures
+            .add(1, &[KeyValue::new("reason", reason)]);
+    }
+
+    pub fn unauthenticated_client_request(
+        &self,
+        tenant_id: &str,
+        action: &'static str,
+        mode: &'static str,
+    ) {
+        self.unauthenticated_client_requests.add(
+            1,
+            &[
+                KeyValue::new("tenant_id", tenant_id.to_string()),
+                KeyValue::new("action", action),
+                KeyValue::new("mode", mode),
+            ],
+        );
+    }
+
+    pub fn notification_pruning(&self, pruned: u64, start: Instant) {
+        self.pruned_notifications.add(pruned, &[]);
+        self.notification_pruning_duration
+            .record(start.elapsed().as_millis() as u64, &[]);
+    }
+
+    pub fn suspended_client_pruning(&self, pruned: u64) {
+        self.pruned_suspended_clients.add(pruned, &[]);
+    }
+
     pub fn postgres_query(&self, query_name: &'static str, start: Instant) {
         let elapsed = start.elapsed();
 
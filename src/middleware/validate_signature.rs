@@ -2,22 +2,36 @@ use {
     crate::{
         error::Error::{
             FromRequestError, MissingAllSignatureHeader, MissingSignatureHeader,
-            MissingTimestampHeader, ToBytesError,
+            MissingTimestampHeader, ReplayedSignature, RequestBodyTooLarge,
+            StaleSignatureTimestamp, ToBytesError,
         },
-        state::State,
+        state::{AppState, State},
     },
     async_trait::async_trait,
     axum::{
         body::to_bytes,
-        extract::{FromRequest, Request},
+        extract::{self, FromRequest, Request},
+        http::header::CONTENT_LENGTH,
+        middleware::Next,
+        response::Response,
     },
+    chrono::Utc,
     ed25519_dalek::{Signature, VerifyingKey},
-    tracing::instrument,
+    moka::future::Cache,
+    std::{
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+    tracing::{instrument, warn},
 };
 
 pub const SIGNATURE_HEADER_NAME: &str = "X-Ed25519-Signature";
 pub const TIMESTAMP_HEADER_NAME: &str = "X-Ed25519-Timestamp";
 
+/// Upper bound of remembered signatures, well above the relay's request rate
+/// within the replay window
+const REPLAY_CACHE_CAPACITY: u64 = 1_000_000;
+
 pub struct RequireValidSignature<T>(pub T);
 
 #[async_trait]
@@ -38,12 +52,19 @@ where
                 .map_err(|_| FromRequestError);
         }
 
-        let state_binding = state.relay_client();
-        let public_key = state_binding.get_verifying_key();
+        let public_keys = state.relay_client().get_verifying_keys(Utc::now());
 
         let (parts, body_raw) = req.into_parts();
-        const MAX_BODY: usize = 1024 * 1024 * 100; // prolly too big but better than usize::MAX
-        let bytes = to_bytes(body_raw, MAX_BODY)
+        let max_body = state.max_request_body_bytes();
+        let content_length = parts
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.parse::<usize>().ok());
+        if content_length.is_some_and(|length| length > max_body) {
+            return Err(RequestBodyTooLarge(max_body));
+        }
+        let bytes = to_bytes(body_raw, max_body)
             .await
             .map_err(|_| ToBytesError)?;
         let body = String::from_utf8_lossy(&bytes);
@@ -60,8 +81,25 @@ where
 
         match (signature_header, timestamp_header) {
             (Some(signature), Some(timestamp))
-                if signature_is_valid(signature, timestamp, &body, public_key).await? =>
+                if signature_is_valid_for_any(signature, timestamp, &body, &public_keys)
+                    .await? =>
             {
+                let replay_cache = state.replay_cache();
+                if !replay_cache.timestamp_is_fresh(timestamp, SystemTime::now()) {
+                    warn!(%timestamp, "rejected stale signature timestamp");
+                    if let Some(metrics) = state.metrics() {
+                        metrics.rejected_signature("stale");
+                    }
+                    return Err(StaleSignatureTimestamp);
+                }
+                if !replay_cache.insert(signature, timestamp).await {
+                    warn!(%timestamp, "rejected replayed signature");
+                    if let Some(metrics) = state.metrics() {
+                        metrics.rejected_signature("replayed");
+                    }
+                    return Err(ReplayedSignature);
+                }
+
                 let req = Request::from_parts(parts, bytes.into());
                 Ok(T::from_request(req, state)
                     .await
@@ -76,6 +114,34 @@ where
     }
 }
 
+/// Forget the request's signature when handling it failed with a server error,
+/// so the relay's retry isn't rejected as a replay. Signatures are remembered
+/// before the handler runs so concurrent copies of a request are still
+/// rejected.
+pub async fn forget_signature_on_failure(
+    extract::State(state): extract::State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|header| header.to_str().ok())
+            .map(str::to_string)
+    };
+    let signature = header(SIGNATURE_HEADER_NAME).zip(header(TIMESTAMP_HEADER_NAME));
+
+    let response = next.run(req).await;
+
+    if response.status().is_server_error() {
+        if let Some((signature, timestamp)) = signature {
+            state.replay_cache.remove(&signature, &timestamp).await;
+        }
+    }
+
+    response
+}
+
 pub async fn signature_is_valid(
     signature: &str,
     timestamp: &str,
@@ -89,3 +155,76 @@ pub async fn signature_is_valid(
 
     Ok(public_key.verify_strict(sig_body.as_bytes(), &sig).is_ok())
 }
+
+/// Whether the signature is valid for one of the relay's current keys
+pub async fn signature_is_valid_for_any(
+    signature: &str,
+    timestamp: &str,
+    body: &str,
+    public_keys: &[VerifyingKey],
+) -> Result<bool, crate::error::Error> {
+    for public_key in public_keys {
+        if signature_is_valid(signature, timestamp, body, public_key).await? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Signatures accepted within the clock skew window, a request carrying one of
+/// them again is a replay. Older signatures are rejected by their timestamp
+/// instead, so they don't need to be remembered.
+///
+/// The cache is local to the process, a replay sent to another replica within
+/// the window is not caught.
+#[derive(Clone)]
+pub struct ReplayCache {
+    seen: Arc<Cache<(String, String), ()>>,
+    max_clock_skew: Duration,
+}
+
+impl ReplayCache {
+    pub fn new(max_clock_skew: Duration) -> Self {
+        ReplayCache {
+            seen: Arc::new(
+                Cache::builder()
+                    .max_capacity(REPLAY_CACHE_CAPACITY)
+                    // A timestamp at the far end of the skew in either
+                    // direction stays fresh for twice the skew
+                    .time_to_live(max_clock_skew * 2)
+                    .build(),
+            ),
+            max_clock_skew,
+        }
+    }
+
+    /// Whether the unix timestamp (in seconds) is within the clock skew of
+    /// `now`, unparsable timestamps are never fresh
+    pub fn timestamp_is_fresh(&self, timestamp: &str, now: SystemTime) -> bool {
+        let Ok(timestamp) = timestamp.parse::<u64>() else {
+            return false;
+        };
+        let Ok(now) = now.duration_since(UNIX_EPOCH) else {
+            return false;
+        };
+
+        now.as_secs().abs_diff(timestamp) <= self.max_clock_skew.as_secs()
+    }
+
+    /// Remember the signature, returns false when it has been seen before
+    pub async fn insert(&self, signature: &str, timestamp: &str) -> bool {
+        self.seen
+            .entry((signature.to_ascii_lowercase(), timestamp.to_string()))
+            .or_insert(())
+            .await
+            .is_fresh()
+    }
+
+    /// Forget the signature, e.g. when the request it was seen on failed
+    pub async fn remove(&self, signature: &str, timestamp: &str) {
+        self.seen
+            .invalidate(&(signature.to_ascii_lowercase(), timestamp.to_string()))
+            .await;
+    }
+}
@@ -1,12 +1,28 @@
 use {
-    super::{LegacyPushMessage, PushMessage, RawPushMessage},
-    crate::{blob::DecryptedPayloadBlob, error::Error, providers::PushProvider},
-    a2::{ClientConfig, ErrorReason, NotificationBuilder, NotificationOptions},
+    super::{
+        AlertText, DeliveryOptions, DeliveryPriority, DeliveryPushType, LegacyPushMessage,
+        PushMessage, RawPushMessage,
+    },
+    crate::{
+        blob::{DecryptedPayloadBlob, IMAGE_DATA_KEY, URL_DATA_KEY},
+        error::Error,
+        providers::PushProvider,
+    },
+    a2::{
+        ClientConfig, CollapseId, ErrorReason, NotificationBuilder, NotificationOptions, Priority,
+        PushType,
+    },
     async_trait::async_trait,
-    std::io::Read,
+    std::{
+        io::Read,
+        time::{SystemTime, UNIX_EPOCH},
+    },
     tracing::{debug, info, instrument, warn},
 };
 
+/// Shown for messages the server can't read when the tenant has no template
+const PLACEHOLDER_TITLE: &str = "You have new notifications. Open to view";
+
 #[derive(Debug, Clone)]
 pub struct ApnsProvider {
     client: a2::Client,
@@ -53,16 +69,63 @@ impl PushProvider for ApnsProvider {
         &self,
         token: String,
         body: PushMessage,
-    ) -> crate::error::Result<()> {
+        options: &DeliveryOptions,
+    ) -> crate::error::Result<Option<String>> {
         let opt = NotificationOptions {
             apns_id: None,
-            apns_expiration: None,
-            apns_priority: None,
+            // APNs expects an absolute UNIX timestamp, `0` means deliver once or drop
+            apns_expiration: options.ttl().map(|ttl| match ttl {
+                0 => 0,
+                ttl => SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    .saturating_add(ttl),
+            }),
+            apns_priority: options.priority().map(|priority| match priority {
+                DeliveryPriority::High => Priority::High,
+                DeliveryPriority::Normal => Priority::Normal,
+            }),
             apns_topic: Some(&self.topic),
-            apns_collapse_id: None,
-            apns_push_type: None,
+            apns_collapse_id: options
+                .collapse_key
+                .as_deref()
+                .map(CollapseId::new)
+                .transpose()
+                .map_err(|_| Error::InvalidOptionsProvided("collapse_key".to_string()))?,
+            apns_push_type: options.push_type.map(|push_type| match push_type {
+                DeliveryPushType::Alert => PushType::Alert,
+                DeliveryPushType::Background => PushType::Background,
+            }),
         };
 
+        // Background notifications must only carry `content-available` and no
+        // alert, otherwise APNs will refuse them
+        if options.is_background() {
+            debug!("Sending background message");
+            let mut notification_payload = a2::DefaultNotificationBuilder::new()
+                .set_content_available()
+                .build(token.as_str(), opt);
+
+            match &body {
+                PushMessage::RawPushMessage(RawPushMessage {
+                    topic,
+                    tag,
+                    message,
+                }) => {
+                    notification_payload.add_custom_data("topic", topic)?;
+                    notification_payload.add_custom_data("tag", tag)?;
+                    notification_payload.add_custom_data("message", message)?;
+                }
+                PushMessage::LegacyPushMessage(LegacyPushMessage { id: _, payload }) => {
+                    notification_payload.add_custom_data("topic", &payload.topic)?;
+                    notification_payload.add_custom_data("blob", &payload.blob)?;
+                }
+            }
+
+            return handle_response(self.client.send(notification_payload).await);
+        }
+
         let result = match body {
             PushMessage::RawPushMessage(RawPushMessage {
                 topic,
@@ -71,11 +134,8 @@ impl PushProvider for ApnsProvider {
             }) => {
                 // Sending `always_raw` encrypted message
                 debug!("Sending raw encrypted message");
-                let mut notification_payload = a2::DefaultNotificationBuilder::new()
-                    .set_content_available()
-                    .set_mutable_content()
-                    .set_title("You have new notifications. Open to view")
-                    .build(token.as_str(), opt);
+                let mut notification_payload =
+                    encrypted_alert_builder(options).build(token.as_str(), opt);
 
                 notification_payload.add_custom_data("topic", &topic)?;
                 notification_payload.add_custom_data("tag", &tag)?;
@@ -87,11 +147,8 @@ impl PushProvider for ApnsProvider {
                 // TODO tidy after https://github.com/WalletConnect/a2/issues/67 is closed
                 if payload.is_encrypted() {
                     debug!("Sending legacy `is_encrypted` message");
-                    let mut notification_payload = a2::DefaultNotificationBuilder::new()
-                        .set_content_available()
-                        .set_mutable_content()
-                        .set_title("You have new notifications. Open to view")
-                        .build(token.as_str(), opt);
+                    let mut notification_payload =
+                        encrypted_alert_builder(options).build(token.as_str(), opt);
 
                     notification_payload.add_custom_data("topic", &payload.topic)?;
                     notification_payload.add_custom_data("blob", &payload.blob)?;
@@ -101,7 +158,7 @@ impl PushProvider for ApnsProvider {
                     debug!("Sending plain message");
                     let blob = DecryptedPayloadBlob::from_base64_encoded(&payload.blob)?;
 
-                    let mut notification_payload = a2::DefaultNotificationBuilder::new()
+                    let mut notification_payload = alert_builder(options)
                         .set_content_available()
                         .set_mutable_content()
                         .set_title(&blob.title)
@@ -109,77 +166,115 @@ impl PushProvider for ApnsProvider {
                         .build(token.as_str(), opt);
 
                     notification_payload.add_custom_data("topic", &payload.topic)?;
+                    // `mutable-content` lets the app's notification service extension
+                    // download the image as an attachment
+                    if let Some(image) = &blob.image {
+                        notification_payload.add_custom_data(IMAGE_DATA_KEY, image)?;
+                    }
+                    if let Some(url) = &blob.url {
+                        notification_payload.add_custom_data(URL_DATA_KEY, url)?;
+                    }
 
                     self.client.send(notification_payload).await
                 }
             }
         };
 
-        match result {
-            Ok(response) => {
-                if response.error.is_some() {
-                    warn!(
-                        "Unexpected APNS error. a2 lib shouldn't allow returning Ok containing \
+        handle_response(result)
+    }
+}
+
+/// Alert notifications carry the client's unread count, background ones must
+/// not as APNs refuses them
+fn alert_builder<'a>(options: &DeliveryOptions) -> a2::DefaultNotificationBuilder<'a> {
+    let builder = a2::DefaultNotificationBuilder::new();
+    match options.badge {
+        Some(badge) => builder.set_badge(badge),
+        None => builder,
+    }
+}
+
+/// Messages the server can't read show the tenant's template, or a generic
+/// placeholder, until the app's notification service extension decrypts them
+fn encrypted_alert_builder(options: &DeliveryOptions) -> a2::DefaultNotificationBuilder<'_> {
+    let builder = alert_builder(options)
+        .set_content_available()
+        .set_mutable_content();
+    match &options.alert {
+        Some(AlertText { title, body }) => {
+            let builder = builder.set_title(title);
+            match body {
+                Some(body) => builder.set_body(body),
+                None => builder,
+            }
+        }
+        None => builder.set_title(PLACEHOLDER_TITLE),
+    }
+}
+
+fn handle_response(
+    result: Result<a2::Response, a2::Error>,
+) -> crate::error::Result<Option<String>> {
+    match result {
+        Ok(response) => {
+            if response.error.is_some() {
+                warn!(
+                    "Unexpected APNS error. a2 lib shouldn't allow returning Ok containing \
                          error response. Status: {} Error: {:?}",
-                        response.code, response.error
-                    );
-                    Err(Error::Apns(a2::Error::ResponseError(response)))
-                } else {
-                    Ok(())
-                }
+                    response.code, response.error
+                );
+                Err(Error::Apns(a2::Error::ResponseError(response)))
+            } else {
+                Ok(response.apns_id)
             }
-            Err(e) => match e {
-                a2::Error::ResponseError(res) => match res.error {
-                    None => Err(Error::Apns(a2::Error::ResponseError(res))),
-                    Some(response) => match response.reason {
-                        ErrorReason::BadDeviceToken => {
-                            Err(Error::BadDeviceToken("Bad device token".to_string()))
-                        }
-                        // Note: This will have the device deleted because the token was not for the
-                        // configured topic
-                        ErrorReason::DeviceTokenNotForTopic => Err(Error::BadDeviceToken(
-                            "The device token does not match the specified topic".to_string(),
-                        )),
-                        ErrorReason::Unregistered => Err(Error::BadDeviceToken(
-                            "The device token is inactive for the specified topic".to_string(),
-                        )),
-                        ErrorReason::TopicDisallowed => Err(Error::BadApnsCredentials),
-                        // InvalidProviderToken reflecting that APNS certificate must be reissued
-                        ErrorReason::InvalidProviderToken => Err(Error::ApnsInvalidProviderToken),
-                        ErrorReason::PayloadTooLarge => Err(Error::PayloadTooLarge),
-                        reason => Err(Error::ApnsResponse(reason)),
-                    },
+        }
+        Err(e) => match e {
+            a2::Error::ResponseError(res) => match res.error {
+                None => Err(Error::Apns(a2::Error::ResponseError(res))),
+                Some(response) => match response.reason {
+                    ErrorReason::BadDeviceToken => {
+                        Err(Error::BadDeviceToken("Bad device token".to_string()))
+                    }
+                    // The token is valid but for another bundle id, so the tenant's topics or
+                    // the client's registration need fixing rather than the client deleted
+                    ErrorReason::DeviceTokenNotForTopic => Err(Error::ApnsTopicMismatch),
+                    ErrorReason::Unregistered => Err(Error::BadDeviceToken(
+                        "The device token is inactive for the specified topic".to_string(),
+                    )),
+                    ErrorReason::TopicDisallowed => Err(Error::BadApnsCredentials),
+                    // InvalidProviderToken reflecting that APNS certificate must be reissued
+                    ErrorReason::InvalidProviderToken => Err(Error::ApnsInvalidProviderToken),
+                    ErrorReason::PayloadTooLarge => Err(Error::PayloadTooLarge),
+                    reason => Err(Error::ApnsResponse(reason)),
                 },
-                a2::Error::ConnectionError(ref hyper_error) => {
-                    let dbg = format!("{hyper_error:?}");
-                    // e.g. Apns(ConnectionError(hyper::Error(Io, Custom { kind: InvalidData, error: "received fatal alert: CertificateExpired" })))
-                    // Checking if debug fmt contains something is strange.
-                    // Logging stuff here temporarily so we can determine better
-                    // ways to detect this error (e.g. display). Ideally we can extract
-                    // the error field directly and check if exactly equal to the above
-                    // rather than using contains()
-                    match dbg {
-                        dbg if dbg.contains("received fatal alert: CertificateExpired") => {
-                            info!("APNs certificate expired: debug:{dbg}, display: {hyper_error}");
-                            Err(Error::ApnsCertificateExpired)
-                        }
-                        _ => Err(Error::Apns(e)),
+            },
+            a2::Error::ConnectionError(ref hyper_error) => {
+                let dbg = format!("{hyper_error:?}");
+                // e.g. Apns(ConnectionError(hyper::Error(Io, Custom { kind: InvalidData, error: "received fatal alert: CertificateExpired" })))
+                // Checking if debug fmt contains something is strange.
+                // Logging stuff here temporarily so we can determine better
+                // ways to detect this error (e.g. display). Ideally we can extract
+                // the error field directly and check if exactly equal to the above
+                // rather than using contains()
+                match dbg {
+                    dbg if dbg.contains("received fatal alert: CertificateExpired") => {
+                        info!("APNs certificate expired: debug:{dbg}, display: {hyper_error}");
+                        Err(Error::ApnsCertificateExpired)
                     }
+                    _ => Err(Error::Apns(e)),
                 }
-                a2::Error::ClientError(ref client_error) => {
-                    let dbg = format!("{client_error:?}");
-                    match dbg {
-                        dbg if dbg.contains("received fatal alert: UnknownCA") => {
-                            info!(
-                                "APNs certificate unknown CA: debug:{dbg}, display: {client_error}"
-                            );
-                            Err(Error::ApnsCertificateUnknownCA)
-                        }
-                        _ => Err(Error::Apns(e)),
+            }
+            a2::Error::ClientError(ref client_error) => {
+                let dbg = format!("{client_error:?}");
+                match dbg {
+                    dbg if dbg.contains("received fatal alert: UnknownCA") => {
+                        info!("APNs certificate unknown CA: debug:{dbg}, display: {client_error}");
+                        Err(Error::ApnsCertificateUnknownCA)
                     }
+                    _ => Err(Error::Apns(e)),
                 }
-                e => Err(Error::Apns(e)),
-            },
-        }
+            }
+            e => Err(Error::Apns(e)),
+        },
     }
 }
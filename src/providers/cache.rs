@@ -0,0 +1,81 @@
+use {
+    super::{Provider, ProviderKind},
+    moka::future::Cache,
+    sha2::{Digest, Sha256},
+    std::time::Duration,
+    tracing::warn,
+};
+
+/// Upper bound of cached provider clients across all tenants
+const MAX_CAPACITY: u64 = 1000;
+/// Clients unused for this long are dropped, closing their connections
+const TIME_TO_IDLE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ProviderCacheKey {
+    pub tenant_id: String,
+    pub provider: ProviderKind,
+    /// Hash of the credentials used to build the client, so a credential change
+    /// never resolves to a stale client
+    pub fingerprint: String,
+}
+
+impl ProviderCacheKey {
+    pub fn new(tenant_id: &str, provider: ProviderKind, credentials: &[&str]) -> Self {
+        let mut hasher = Sha256::new();
+        for credential in credentials {
+            hasher.update(credential.as_bytes());
+            // Separator so that ("ab", "c") and ("a", "bc") differ
+            hasher.update([0u8]);
+        }
+
+        ProviderCacheKey {
+            tenant_id: tenant_id.to_string(),
+            provider,
+            fingerprint: hex::encode(hasher.finalize()),
+        }
+    }
+}
+
+/// Provider clients kept alive across pushes so their HTTP connections are
+/// reused
+#[derive(Clone)]
+pub struct ProviderCache(Cache<ProviderCacheKey, Provider>);
+
+impl Default for ProviderCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProviderCache {
+    pub fn new() -> Self {
+        ProviderCache(
+            Cache::builder()
+                .max_capacity(MAX_CAPACITY)
+                .time_to_idle(TIME_TO_IDLE)
+                .support_invalidation_closures()
+                .build(),
+        )
+    }
+
+    pub async fn get(&self, key: &ProviderCacheKey) -> Option<Provider> {
+        self.0.get(key).await
+    }
+
+    pub async fn insert(&self, key: ProviderCacheKey, provider: Provider) {
+        self.0.insert(key, provider).await
+    }
+
+    /// Evict every client of the tenant, must be called whenever its
+    /// credentials are changed or removed
+    pub fn invalidate_tenant(&self, tenant_id: &str) {
+        let tenant_id = tenant_id.to_string();
+        if let Err(e) = self
+            .0
+            .invalidate_entries_if(move |key, _| key.tenant_id == tenant_id)
+        {
+            warn!("failed to invalidate cached providers: {e:?}");
+        }
+    }
+}
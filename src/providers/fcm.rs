@@ -1,22 +1,27 @@
 use {
-    super::{LegacyPushMessage, PushMessage},
+    super::{DeliveryOptions, DeliveryPriority, LegacyPushMessage, PushMessage},
     crate::{blob::DecryptedPayloadBlob, error::Error, providers::PushProvider},
     async_trait::async_trait,
     fcm::{ErrorReason, FcmError, FcmResponse, MessageBuilder, NotificationBuilder, Priority},
-    std::fmt::{Debug, Formatter},
+    std::{
+        fmt::{Debug, Formatter},
+        sync::Arc,
+    },
     tracing::{debug, instrument},
 };
 
+#[derive(Clone)]
 pub struct FcmProvider {
     api_key: String,
-    client: fcm::Client,
+    /// Shared between clones so the underlying connection pool is reused
+    client: Arc<fcm::Client>,
 }
 
 impl FcmProvider {
     pub fn new(api_key: String) -> Self {
         FcmProvider {
             api_key,
-            client: fcm::Client::new(),
+            client: Arc::new(fcm::Client::new()),
         }
     }
 }
@@ -28,8 +33,10 @@ impl PushProvider for FcmProvider {
         &self,
         token: String,
         body: PushMessage,
-    ) -> crate::error::Result<()> {
+        options: &DeliveryOptions,
+    ) -> crate::error::Result<Option<String>> {
         let mut message_builder = MessageBuilder::new(self.api_key.as_str(), token.as_str());
+        apply_delivery_options(&mut message_builder, options);
 
         let result = match body {
             PushMessage::RawPushMessage(message) => {
@@ -38,7 +45,7 @@ impl PushProvider for FcmProvider {
                 message_builder
                     .data(&message)
                     .map_err(Error::InternalSerializationError)?;
-                set_message_priority_high(&mut message_builder);
+                set_message_priority_high(&mut message_builder, options);
                 let fcm_message = message_builder.finalize();
                 self.client.send(fcm_message).await
             }
@@ -48,21 +55,29 @@ impl PushProvider for FcmProvider {
                     message_builder
                         .data(&payload)
                         .map_err(Error::InternalSerializationError)?;
-                    set_message_priority_high(&mut message_builder);
+                    set_message_priority_high(&mut message_builder, options);
                     let fcm_message = message_builder.finalize();
                     self.client.send(fcm_message).await
                 } else {
                     debug!("Sending plain message");
                     let blob = DecryptedPayloadBlob::from_base64_encoded(&payload.blob)?;
 
-                    let mut notification_builder = NotificationBuilder::new();
-                    notification_builder.title(blob.title.as_str());
-                    notification_builder.body(blob.body.as_str());
-                    let notification = notification_builder.finalize();
+                    // Background messages are data only
+                    if !options.is_background() {
+                        let mut notification_builder = NotificationBuilder::new();
+                        notification_builder.title(blob.title.as_str());
+                        notification_builder.body(blob.body.as_str());
+                        let notification = notification_builder.finalize();
 
-                    message_builder.notification(notification);
+                        message_builder.notification(notification);
+                    }
+                    let mut data = serde_json::to_value(&payload)
+                        .map_err(Error::InternalSerializationError)?;
+                    if let Some(data) = data.as_object_mut() {
+                        data.extend(blob.rich_data());
+                    }
                     message_builder
-                        .data(&payload.to_owned())
+                        .data(&data)
                         .map_err(Error::InternalSerializationError)?;
                     let fcm_message = message_builder.finalize();
                     self.client.send(fcm_message).await
@@ -72,7 +87,7 @@ impl PushProvider for FcmProvider {
 
         match result {
             Ok(val) => {
-                let FcmResponse { error, .. } = val;
+                let FcmResponse { error, results, .. } = val;
                 if let Some(error) = error {
                     match error {
                         ErrorReason::MissingRegistration => Err(Error::BadDeviceToken(
@@ -88,7 +103,10 @@ impl PushProvider for FcmProvider {
                         e => Err(Error::FcmResponse(e)),
                     }
                 } else {
-                    Ok(())
+                    // A single registration token was targeted, so there's one result
+                    Ok(results
+                        .and_then(|results| results.into_iter().next())
+                        .and_then(|result| result.message_id))
                 }
             }
             Err(e) => match e {
@@ -102,20 +120,6 @@ impl PushProvider for FcmProvider {
 // Manual Impl Because `fcm::Client` does not derive anything and doesn't need
 // to be accounted for
 
-impl Clone for FcmProvider {
-    fn clone(&self) -> Self {
-        FcmProvider {
-            api_key: self.api_key.clone(),
-            client: fcm::Client::new(),
-        }
-    }
-
-    fn clone_from(&mut self, source: &Self) {
-        self.api_key.clone_from(&source.api_key);
-        self.client = fcm::Client::new();
-    }
-}
-
 impl PartialEq for FcmProvider {
     fn eq(&self, other: &Self) -> bool {
         self.api_key == other.api_key
@@ -131,7 +135,25 @@ impl Debug for FcmProvider {
 /// Setting message priority to high and content-available to true
 /// on data-only messages or they don't show unless app is active
 /// https://rnfirebase.io/messaging/usage#data-only-messages
-fn set_message_priority_high(builder: &mut MessageBuilder) {
-    builder.priority(Priority::High);
+/// An explicitly requested priority takes precedence
+fn set_message_priority_high(builder: &mut MessageBuilder, options: &DeliveryOptions) {
+    if options.priority().is_none() {
+        builder.priority(Priority::High);
+    }
     builder.content_available(true);
 }
+
+fn apply_delivery_options<'a>(builder: &mut MessageBuilder<'a>, options: &'a DeliveryOptions) {
+    if let Some(priority) = options.priority() {
+        builder.priority(match priority {
+            DeliveryPriority::High => Priority::High,
+            DeliveryPriority::Normal => Priority::Normal,
+        });
+    }
+    if let Some(ttl) = options.ttl() {
+        builder.time_to_live(ttl as i32);
+    }
+    if let Some(collapse_key) = &options.collapse_key {
+        builder.collapse_key(collapse_key);
+    }
+}
@@ -1,10 +1,11 @@
 use {
-    super::{LegacyPushMessage, PushMessage},
+    super::{DeliveryOptions, DeliveryPriority, LegacyPushMessage, PushMessage},
     crate::{blob::DecryptedPayloadBlob, error::Error, providers::PushProvider},
     async_trait::async_trait,
     fcm_v1::{
-        gauth::serv_account::ServiceAccountKey, AndroidConfig, AndroidMessagePriority, ApnsConfig,
-        Client, ClientBuildError, Message, Notification, SendError, Target,
+        gauth::serv_account::ServiceAccountKey, AndroidConfig, AndroidMessagePriority,
+        AndroidNotification, ApnsConfig, Client, ClientBuildError, Message, Notification,
+        SendError, Target,
     },
     serde::Serialize,
     serde_json::json,
@@ -37,27 +38,70 @@ impl PushProvider for FcmV1Provider {
         &self,
         token: String,
         body: PushMessage,
-    ) -> crate::error::Result<()> {
+        options: &DeliveryOptions,
+    ) -> crate::error::Result<Option<String>> {
         fn make_message(
             token: String,
             notification: Option<Notification>,
             data: serde_json::Value,
+            options: &DeliveryOptions,
         ) -> Message {
+            // Background messages are data only
+            let notification = notification.filter(|_| !options.is_background());
+            let priority = options.priority();
+
+            // Mirror the hints as APNs headers for iOS devices registered through FCM
+            let mut apns_headers = serde_json::Map::new();
+            if let Some(priority) = priority {
+                let apns_priority = match priority {
+                    DeliveryPriority::High => "10",
+                    DeliveryPriority::Normal => "5",
+                };
+                apns_headers.insert("apns-priority".to_string(), json!(apns_priority));
+            }
+            if let Some(collapse_key) = &options.collapse_key {
+                apns_headers.insert("apns-collapse-id".to_string(), json!(collapse_key));
+            }
+            if options.is_background() {
+                apns_headers.insert("apns-push-type".to_string(), json!("background"));
+            }
+
+            let mut aps = json!({
+                "content-available": 1,
+            });
+            // Background messages must not carry a badge on APNs
+            let badge = options.badge.filter(|_| !options.is_background());
+            if let Some(badge) = badge {
+                aps["badge"] = json!(badge);
+            }
+            // Android only shows the count alongside a notification
+            let android_notification =
+                badge
+                    .filter(|_| notification.is_some())
+                    .map(|badge| AndroidNotification {
+                        notification_count: Some(i32::try_from(badge).unwrap_or(i32::MAX)),
+                        ..Default::default()
+                    });
+
             Message {
                 data: Some(data),
                 notification,
                 target: Target::Token(token),
                 android: Some(AndroidConfig {
-                    priority: Some(AndroidMessagePriority::High),
+                    priority: Some(match priority {
+                        Some(DeliveryPriority::Normal) => AndroidMessagePriority::Normal,
+                        Some(DeliveryPriority::High) | None => AndroidMessagePriority::High,
+                    }),
+                    ttl: options.ttl().map(|ttl| format!("{ttl}s")),
+                    collapse_key: options.collapse_key.clone(),
+                    notification: android_notification,
                     ..Default::default()
                 }),
                 webpush: None,
                 apns: Some(ApnsConfig {
-                    payload: Some(json!({
-                        "aps": {
-                            "content-available": 1,
-                        }
-                    })),
+                    headers: (!apns_headers.is_empty())
+                        .then_some(serde_json::Value::Object(apns_headers)),
+                    payload: Some(json!({ "aps": aps })),
                     ..Default::default()
                 }),
                 fcm_options: None,
@@ -81,7 +125,7 @@ impl PushProvider for FcmV1Provider {
                     message: message.message,
                 })
                 .map_err(Error::InternalSerializationError)?;
-                let message = make_message(token, None, data);
+                let message = make_message(token, template_notification(options), data, options);
                 self.client.send(message).await
             }
             PushMessage::LegacyPushMessage(LegacyPushMessage { id: _, payload }) => {
@@ -101,27 +145,46 @@ impl PushProvider for FcmV1Provider {
 
                 if payload.is_encrypted() {
                     debug!("Sending legacy `is_encrypted` message");
-                    let message = make_message(token, None, data);
+                    let message =
+                        make_message(token, template_notification(options), data, options);
                     self.client.send(message).await
                 } else {
                     debug!("Sending plain message");
                     let blob = DecryptedPayloadBlob::from_base64_encoded(&payload.blob)?;
 
+                    let mut data = data;
+                    if let Some(data) = data.as_object_mut() {
+                        data.extend(blob.rich_data());
+                    }
                     let notification = Notification {
                         title: Some(blob.title),
                         body: Some(blob.body),
+                        image: blob.image,
                         ..Default::default()
                     };
-                    let message = make_message(token, Some(notification), data);
+                    let message = make_message(token, Some(notification), data, options);
                     self.client.send(message).await
                 }
             }
         };
 
-        result.map(|_| ()).map_err(|e| match e {
-            SendError::Unregistered => Error::BadDeviceToken("Token was unregistered".into()),
-            SendError::Forbidden => Error::BadFcmV1Credentials,
-            e => Error::FcmV1(e),
-        })
+        // The message name, e.g. `projects/{project_id}/messages/{message_id}`
+        result
+            .map(|response| Some(response.name))
+            .map_err(|e| match e {
+                SendError::Unregistered => Error::BadDeviceToken("Token was unregistered".into()),
+                SendError::Forbidden => Error::BadFcmV1Credentials,
+                e => Error::FcmV1(e),
+            })
     }
 }
+
+/// Messages the server can't read are data only, unless the tenant has a
+/// template for them
+fn template_notification(options: &DeliveryOptions) -> Option<Notification> {
+    options.alert.as_ref().map(|alert| Notification {
+        title: Some(alert.title.clone()),
+        body: alert.body.clone(),
+        ..Default::default()
+    })
+}
@@ -1,15 +1,18 @@
 pub mod apns;
+pub mod cache;
 pub mod fcm;
 pub mod fcm_v1;
 #[cfg(any(debug_assertions, test))]
 pub mod noop;
+pub mod retry;
+pub mod webpush;
 
 use {
     self::fcm_v1::FcmV1Provider,
     crate::{
         blob::ENCRYPTED_FLAG,
         error,
-        providers::{apns::ApnsProvider, fcm::FcmProvider},
+        providers::{apns::ApnsProvider, fcm::FcmProvider, webpush::WebPushProvider},
     },
     async_trait::async_trait,
     relay_rpc::rpc::msg_id::get_message_id,
@@ -44,6 +47,23 @@ impl PushMessage {
             Self::LegacyPushMessage(msg) => msg.payload.topic.clone(),
         }
     }
+
+    /// Whether the server can't read the message, so it is shown with a
+    /// placeholder or template text
+    pub fn is_encrypted(&self) -> bool {
+        match self {
+            Self::RawPushMessage(_) => true,
+            Self::LegacyPushMessage(msg) => msg.payload.is_encrypted(),
+        }
+    }
+
+    /// Relay filtering tag, legacy messages are not tagged
+    pub fn tag(&self) -> Option<u32> {
+        match self {
+            Self::RawPushMessage(msg) => Some(msg.tag),
+            Self::LegacyPushMessage(_) => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -76,19 +96,109 @@ pub struct RawPushMessage {
     pub message: Arc<str>,
 }
 
+/// Maximum length of a collapse key, this is the APNs `apns-collapse-id` limit
+/// which is the strictest of the supported providers
+pub const MAX_COLLAPSE_KEY_LENGTH: usize = 64;
+
+/// Longest time to live (4 weeks) providers accept, this is the FCM limit
+pub const MAX_TTL_SECS: u64 = 2_419_200;
+
+/// Optional per-message hints that providers map to their own delivery
+/// headers, so that one request means the same thing on every platform
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeliveryOptions {
+    /// How long (in seconds) the provider should keep trying to deliver the
+    /// message, `0` means deliver now or drop it
+    pub ttl: Option<u64>,
+    pub priority: Option<DeliveryPriority>,
+    /// Messages with the same key replace each other on the device
+    pub collapse_key: Option<String>,
+    pub push_type: Option<DeliveryPushType>,
+    /// The client's unread count, set by the server when delivering alert
+    /// messages rather than by the sender
+    #[serde(skip)]
+    pub badge: Option<u32>,
+    /// Text from the tenant's templates shown for messages the server can't
+    /// read, instead of the generic placeholder
+    #[serde(skip)]
+    pub alert: Option<AlertText>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AlertText {
+    pub title: String,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryPriority {
+    High,
+    Normal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryPushType {
+    /// A user visible notification
+    Alert,
+    /// A silent, data only, notification that wakes the app
+    Background,
+}
+
+impl DeliveryOptions {
+    pub fn validate(&self) -> error::Result<()> {
+        if let Some(collapse_key) = &self.collapse_key {
+            if collapse_key.is_empty() || collapse_key.len() > MAX_COLLAPSE_KEY_LENGTH {
+                return Err(error::Error::InvalidOptionsProvided(
+                    "collapse_key".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The time to live capped to what every provider accepts
+    pub fn ttl(&self) -> Option<u64> {
+        self.ttl.map(|ttl| ttl.min(MAX_TTL_SECS))
+    }
+
+    pub fn is_background(&self) -> bool {
+        self.push_type == Some(DeliveryPushType::Background)
+    }
+
+    /// Background messages are always sent with normal priority as both APNs
+    /// and FCM deprioritize or reject high priority silent messages
+    pub fn priority(&self) -> Option<DeliveryPriority> {
+        if self.is_background() {
+            Some(DeliveryPriority::Normal)
+        } else {
+            self.priority
+        }
+    }
+}
+
 #[async_trait]
 pub trait PushProvider {
-    async fn send_notification(&self, token: String, body: PushMessage) -> error::Result<()>;
+    /// Returns the id the provider assigned to the message, if it sends one
+    async fn send_notification(
+        &self,
+        token: String,
+        body: PushMessage,
+        options: &DeliveryOptions,
+    ) -> error::Result<Option<String>>;
 }
 
 pub const PROVIDER_APNS: &str = "apns";
 pub const PROVIDER_APNS_SANDBOX: &str = "apns-sandbox";
 pub const PROVIDER_FCM: &str = "fcm";
 pub const PROVIDER_FCM_V1: &str = "fcm_v1";
+pub const PROVIDER_WEBPUSH: &str = "webpush";
 #[cfg(any(debug_assertions, test))]
 pub const PROVIDER_NOOP: &str = "noop";
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, sqlx::Type)]
 #[sqlx(type_name = "provider")]
 #[sqlx(rename_all = "lowercase")]
 pub enum ProviderKind {
@@ -96,6 +206,7 @@ pub enum ProviderKind {
     ApnsSandbox,
     Fcm,
     // Intentionally no FcmV1 variant because ProviderKind is also used to determine token type (of which FCM and FCM V1 are the same)
+    WebPush,
     #[cfg(any(debug_assertions, test))]
     Noop,
 }
@@ -106,6 +217,7 @@ impl ProviderKind {
             Self::Apns => PROVIDER_APNS,
             Self::ApnsSandbox => PROVIDER_APNS_SANDBOX,
             Self::Fcm => PROVIDER_FCM,
+            Self::WebPush => PROVIDER_WEBPUSH,
             #[cfg(any(debug_assertions, test))]
             Self::Noop => PROVIDER_NOOP,
         }
@@ -144,6 +256,7 @@ impl TryFrom<&str> for ProviderKind {
             PROVIDER_APNS => Ok(Self::Apns),
             PROVIDER_APNS_SANDBOX => Ok(Self::ApnsSandbox),
             PROVIDER_FCM => Ok(Self::Fcm),
+            PROVIDER_WEBPUSH => Ok(Self::WebPush),
             #[cfg(any(debug_assertions, test))]
             PROVIDER_NOOP => Ok(Self::Noop),
             _ => Err(error::Error::ProviderNotFound(value.to_owned())),
@@ -157,20 +270,41 @@ pub enum Provider {
     Fcm(FcmProvider),
     FcmV1(FcmV1Provider),
     Apns(ApnsProvider),
+    WebPush(WebPushProvider),
     #[cfg(any(debug_assertions, test))]
     Noop(NoopProvider),
 }
 
+impl Provider {
+    /// Name used to label provider metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::Fcm(_) => PROVIDER_FCM,
+            Provider::FcmV1(_) => PROVIDER_FCM_V1,
+            Provider::Apns(_) => PROVIDER_APNS,
+            Provider::WebPush(_) => PROVIDER_WEBPUSH,
+            #[cfg(any(debug_assertions, test))]
+            Provider::Noop(_) => PROVIDER_NOOP,
+        }
+    }
+}
+
 #[async_trait]
 impl PushProvider for Provider {
     #[instrument(name = "send_notification")]
-    async fn send_notification(&self, token: String, body: PushMessage) -> error::Result<()> {
+    async fn send_notification(
+        &self,
+        token: String,
+        body: PushMessage,
+        options: &DeliveryOptions,
+    ) -> error::Result<Option<String>> {
         match self {
-            Provider::Fcm(p) => p.send_notification(token, body).await,
-            Provider::FcmV1(p) => p.send_notification(token, body).await,
-            Provider::Apns(p) => p.send_notification(token, body).await,
+            Provider::Fcm(p) => p.send_notification(token, body, options).await,
+            Provider::FcmV1(p) => p.send_notification(token, body, options).await,
+            Provider::Apns(p) => p.send_notification(token, body, options).await,
+            Provider::WebPush(p) => p.send_notification(token, body, options).await,
             #[cfg(any(debug_assertions, test))]
-            Provider::Noop(p) => p.send_notification(token, body).await,
+            Provider::Noop(p) => p.send_notification(token, body, options).await,
         }
     }
 }
@@ -1,5 +1,5 @@
 use {
-    super::PushMessage,
+    super::{DeliveryOptions, PushMessage},
     crate::providers::PushProvider,
     async_trait::async_trait,
     reqwest::Url,
@@ -26,7 +26,8 @@ impl PushProvider for NoopProvider {
         &self,
         token: String,
         body: PushMessage,
-    ) -> crate::error::Result<()> {
+        _options: &DeliveryOptions,
+    ) -> crate::error::Result<Option<String>> {
         self.bootstrap(token.clone()).await;
 
         let mut lock = self.notifications.write().await;
@@ -37,7 +38,7 @@ impl PushProvider for NoopProvider {
             assert!(reqwest::get(url).await?.status().is_success());
         }
 
-        Ok(())
+        Ok(None)
     }
 }
 
@@ -0,0 +1,124 @@
+use {
+    super::{DeliveryOptions, Provider, PushMessage, PushProvider},
+    crate::{config::Config, error::Result, metrics::Metrics},
+    rand::Rng,
+    std::time::{Duration, Instant},
+    tracing::warn,
+};
+
+const OUTCOME_SUCCESS: &str = "success";
+const OUTCOME_RETRIED_SUCCESS: &str = "retried_success";
+const OUTCOME_FAILED: &str = "failed";
+const OUTCOME_EXHAUSTED: &str = "exhausted";
+
+/// Exponential backoff with full jitter, capped by attempts and total time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub max_elapsed: Duration,
+}
+
+impl From<&Config> for RetryPolicy {
+    fn from(config: &Config) -> Self {
+        RetryPolicy {
+            max_attempts: config.retry_max_attempts.max(1),
+            initial_backoff: Duration::from_millis(config.retry_initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.retry_max_backoff_ms),
+            max_elapsed: Duration::from_millis(config.retry_max_elapsed_ms),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt, for callers that retry transient failures themselves
+    /// (e.g. the delivery queue reschedules them)
+    pub fn no_retries() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            max_elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Upper bound of the delay after the given (1-based) failed attempt
+    pub fn max_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff)
+    }
+
+    /// Randomized delay after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let max_delay = self.max_delay(attempt).as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=max_delay))
+    }
+}
+
+/// Send the notification, retrying transient provider failures according to
+/// the policy. A provider requested `Retry-After` delay takes precedence over
+/// the backoff, as long as it fits in the remaining time budget.
+pub async fn send_with_retry(
+    provider: &Provider,
+    token: &str,
+    body: &PushMessage,
+    options: &DeliveryOptions,
+    policy: &RetryPolicy,
+    metrics: Option<&Metrics>,
+) -> Result<Option<String>> {
+    let start = Instant::now();
+    let mut attempt = 1;
+
+    loop {
+        let error = match provider
+            .send_notification(token.to_string(), body.clone(), options)
+            .await
+        {
+            Ok(provider_message_id) => {
+                let outcome = if attempt == 1 {
+                    OUTCOME_SUCCESS
+                } else {
+                    OUTCOME_RETRIED_SUCCESS
+                };
+                if let Some(metrics) = metrics {
+                    metrics.provider_send_outcome(provider.name(), outcome);
+                }
+                return Ok(provider_message_id);
+            }
+            Err(e) => e,
+        };
+
+        if !error.is_retryable() {
+            if let Some(metrics) = metrics {
+                metrics.provider_send_outcome(provider.name(), OUTCOME_FAILED);
+            }
+            return Err(error);
+        }
+
+        let delay = error
+            .retry_after()
+            .unwrap_or_else(|| policy.backoff(attempt));
+        if attempt >= policy.max_attempts || start.elapsed() + delay > policy.max_elapsed {
+            if let Some(metrics) = metrics {
+                metrics.provider_send_outcome(provider.name(), OUTCOME_EXHAUSTED);
+            }
+            return Err(error);
+        }
+
+        warn!(
+            provider = provider.name(),
+            attempt,
+            delay_ms = delay.as_millis() as u64,
+            "retrying transient provider error: {error:?}"
+        );
+        if let Some(metrics) = metrics {
+            metrics.provider_retry(provider.name());
+        }
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
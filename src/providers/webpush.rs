@@ -0,0 +1,450 @@
+use {
+    super::{DeliveryOptions, DeliveryPriority, LegacyPushMessage, PushMessage},
+    crate::{error::Error, providers::PushProvider},
+    aes_gcm::{
+        aead::{Aead, KeyInit},
+        Aes128Gcm, Nonce,
+    },
+    async_trait::async_trait,
+    base64::Engine as _,
+    hkdf::Hkdf,
+    p256::{
+        ecdsa::{signature::Signer, Signature, SigningKey},
+        elliptic_curve::sec1::ToEncodedPoint,
+        PublicKey, SecretKey,
+    },
+    rand::{rngs::OsRng, RngCore},
+    reqwest::{
+        dns::{Addrs, Name, Resolve, Resolving},
+        header::{HeaderMap, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, LOCATION, RETRY_AFTER},
+        redirect, StatusCode, Url,
+    },
+    serde::{Deserialize, Serialize},
+    serde_json::json,
+    sha2::Sha256,
+    std::{
+        fmt::{Debug, Formatter},
+        net::IpAddr,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+    tracing::{debug, instrument},
+};
+
+/// Record size advertised in the aes128gcm header, payloads are always sent as
+/// a single record so this is also the upper bound of the encrypted payload
+const RECORD_SIZE: u32 = 4096;
+/// Length of the AES-GCM authentication tag appended to the record
+const TAG_LENGTH: usize = 16;
+/// How long (in seconds) the push service should retain an undelivered message
+const DEFAULT_TTL: u64 = 60 * 60 * 24;
+/// Maximum length of the `Topic` header, which must use the base64url alphabet
+const MAX_TOPIC_LENGTH: usize = 32;
+/// Validity (in seconds) of the VAPID JWT sent to the push service
+const VAPID_TOKEN_TTL: u64 = 60 * 60 * 12;
+
+/// The `PushSubscription` as serialized by the browser with
+/// `subscription.toJSON()`, Web Push clients register this JSON as their token
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct WebPushSubscription {
+    pub endpoint: String,
+    pub keys: WebPushSubscriptionKeys,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct WebPushSubscriptionKeys {
+    /// base64url encoded P-256 public key of the user agent
+    pub p256dh: String,
+    /// base64url encoded authentication secret
+    pub auth: String,
+}
+
+impl WebPushSubscription {
+    pub fn from_token(token: &str) -> crate::error::Result<Self> {
+        let subscription: WebPushSubscription = serde_json::from_str(token)
+            .map_err(|e| Error::InvalidWebPushSubscription(e.to_string()))?;
+
+        let endpoint = subscription.endpoint()?;
+        if endpoint.scheme() != "https" {
+            return Err(Error::InvalidWebPushSubscription(
+                "endpoint must use https".to_string(),
+            ));
+        }
+        if !is_public_host(&endpoint) {
+            return Err(Error::InvalidWebPushSubscription(
+                "endpoint must be a public push service".to_string(),
+            ));
+        }
+        PublicKey::from_sec1_bytes(&subscription.p256dh()?).map_err(|_| {
+            Error::InvalidWebPushSubscription("p256dh is not a valid P-256 key".to_string())
+        })?;
+        if subscription.auth()?.len() != 16 {
+            return Err(Error::InvalidWebPushSubscription(
+                "auth must be 16 bytes".to_string(),
+            ));
+        }
+
+        Ok(subscription)
+    }
+
+    pub fn endpoint(&self) -> crate::error::Result<Url> {
+        self.endpoint
+            .parse()
+            .map_err(|_| Error::InvalidWebPushSubscription("endpoint is not a url".to_string()))
+    }
+
+    pub fn p256dh(&self) -> crate::error::Result<Vec<u8>> {
+        decode_base64url(&self.keys.p256dh)
+            .map_err(|_| Error::InvalidWebPushSubscription("p256dh is not base64url".to_string()))
+    }
+
+    pub fn auth(&self) -> crate::error::Result<Vec<u8>> {
+        decode_base64url(&self.keys.auth)
+            .map_err(|_| Error::InvalidWebPushSubscription("auth is not base64url".to_string()))
+    }
+}
+
+/// VAPID (RFC 8292) application server identity used to sign push requests
+#[derive(Clone)]
+pub struct VapidKey {
+    signing_key: SigningKey,
+    public_key: String,
+    subject: String,
+}
+
+impl VapidKey {
+    /// `private_key` is the base64url encoded P-256 private scalar, as
+    /// generated by e.g. `web-push generate-vapid-keys`
+    pub fn new(private_key: &str, subject: String) -> crate::error::Result<Self> {
+        if !subject.starts_with("mailto:") && !subject.starts_with("https:") {
+            return Err(Error::BadWebPushCredentials);
+        }
+
+        let private_key =
+            decode_base64url(private_key).map_err(|_| Error::BadWebPushCredentials)?;
+        let secret_key =
+            SecretKey::from_slice(&private_key).map_err(|_| Error::BadWebPushCredentials)?;
+        let public_key =
+            encode_base64url(secret_key.public_key().to_encoded_point(false).as_bytes());
+
+        Ok(VapidKey {
+            signing_key: SigningKey::from(&secret_key),
+            public_key,
+            subject,
+        })
+    }
+
+    /// base64url encoded uncompressed public key, used by clients as the
+    /// `applicationServerKey` when subscribing
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    fn authorization(&self, endpoint: &Url) -> String {
+        let expiration = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            + VAPID_TOKEN_TTL;
+
+        let header = encode_base64url(json!({"typ": "JWT", "alg": "ES256"}).to_string());
+        let claims = encode_base64url(
+            json!({
+                "aud": endpoint.origin().ascii_serialization(),
+                "exp": expiration,
+                "sub": self.subject,
+            })
+            .to_string(),
+        );
+        let signing_input = format!("{header}.{claims}");
+        let signature: Signature = self.signing_key.sign(signing_input.as_bytes());
+
+        format!(
+            "vapid t={signing_input}.{}, k={}",
+            encode_base64url(signature.to_bytes()),
+            self.public_key
+        )
+    }
+}
+
+/// Push services are public hosts, endpoints naming an IP address or a local
+/// host are rejected so subscriptions can't point the server at internal
+/// services
+pub fn is_public_host(endpoint: &Url) -> bool {
+    let Some(host) = endpoint.host_str() else {
+        return false;
+    };
+
+    // `Url` normalizes every IPv4 notation (e.g. `0x7f.1`) to dotted decimal,
+    // IPv6 addresses are bracketed
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.parse::<IpAddr>().is_ok() {
+        return false;
+    }
+
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    host.contains('.')
+        && ![".localhost", ".local", ".internal"]
+            .iter()
+            .any(|suffix| host.ends_with(suffix))
+}
+
+/// Whether the address is publicly routable, i.e. not loopback, private,
+/// link-local, shared (CGNAT) or otherwise reserved
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                || first >= 240
+                || (first == 100 && (second & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local `fc00::/7` and link-local `fe80::/10`
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves push service hosts at send time, failing when a name resolves to
+/// an address that isn't public so DNS can't be used to reach internal services
+struct PublicAddressResolver;
+
+impl Resolve for PublicAddressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .collect::<Vec<_>>();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!(
+                    "{} resolves to the non-public address {}",
+                    name.as_str(),
+                    addr.ip()
+                )
+                .into());
+            }
+            Ok::<Addrs, Box<dyn std::error::Error + Send + Sync>>(Box::new(addrs.into_iter()))
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct WebPushProvider {
+    vapid: VapidKey,
+    http_client: reqwest::Client,
+}
+
+impl WebPushProvider {
+    /// Endpoints are chosen by the clients, so requests go through a dedicated
+    /// HTTP client which only connects to public addresses and doesn't follow
+    /// redirects
+    pub fn new(vapid_private_key: &str, vapid_subject: String) -> crate::error::Result<Self> {
+        Ok(WebPushProvider {
+            vapid: VapidKey::new(vapid_private_key, vapid_subject)?,
+            http_client: reqwest::Client::builder()
+                .dns_resolver(Arc::new(PublicAddressResolver))
+                .redirect(redirect::Policy::none())
+                .build()?,
+        })
+    }
+}
+
+#[async_trait]
+impl PushProvider for WebPushProvider {
+    #[instrument(name = "send_webpush_notification", skip_all)]
+    async fn send_notification(
+        &self,
+        token: String,
+        body: PushMessage,
+        options: &DeliveryOptions,
+    ) -> crate::error::Result<Option<String>> {
+        // The token was validated on registration, if it cannot be parsed now the
+        // client should be removed
+        let subscription = WebPushSubscription::from_token(&token)
+            .map_err(|e| Error::BadDeviceToken(e.to_string()))?;
+        let endpoint = subscription.endpoint()?;
+
+        let payload = match body {
+            PushMessage::RawPushMessage(message) => {
+                debug!("Sending raw encrypted message");
+                serde_json::to_vec(&message).map_err(Error::InternalSerializationError)?
+            }
+            PushMessage::LegacyPushMessage(LegacyPushMessage { id: _, payload }) => {
+                debug!("Sending legacy message");
+                serde_json::to_vec(&payload).map_err(Error::InternalSerializationError)?
+            }
+        };
+        let encrypted = encrypt(&payload, &subscription.p256dh()?, &subscription.auth()?)?;
+
+        let mut request = self
+            .http_client
+            .post(endpoint.clone())
+            .header("TTL", options.ttl().unwrap_or(DEFAULT_TTL))
+            .header(CONTENT_ENCODING, "aes128gcm")
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .header(AUTHORIZATION, self.vapid.authorization(&endpoint));
+        if let Some(priority) = options.priority() {
+            request = request.header(
+                "Urgency",
+                match priority {
+                    DeliveryPriority::High => "high",
+                    DeliveryPriority::Normal => "normal",
+                },
+            );
+        }
+        // Push services reject topics that are not short base64url strings, in that
+        // case the message is sent without being collapsible
+        if let Some(topic) = options.collapse_key.as_deref().filter(|topic| {
+            topic.len() <= MAX_TOPIC_LENGTH
+                && topic
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }) {
+            request = request.header("Topic", topic);
+        }
+
+        let response = request.body(encrypted).send().await?;
+
+        match response.status() {
+            // RFC 8030 push services identify the message with the `Location` header
+            status if status.is_success() => Ok(response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(ToString::to_string)),
+            StatusCode::NOT_FOUND | StatusCode::GONE => Err(Error::BadDeviceToken(
+                "The Web Push subscription has expired or is no longer valid".to_string(),
+            )),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(Error::BadWebPushCredentials),
+            StatusCode::PAYLOAD_TOO_LARGE => Err(Error::PayloadTooLarge),
+            status => Err(Error::WebPushResponse(
+                status.as_u16(),
+                retry_after(response.headers()),
+            )),
+        }
+    }
+}
+
+// Manual Impl to ensure the VAPID private key is never logged
+impl Debug for WebPushProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[WebPushProvider] vapid_public_key = {}",
+            self.vapid.public_key
+        )
+    }
+}
+
+/// Encrypt `payload` for the user agent using the `aes128gcm` content coding
+/// (RFC 8188) with the key derivation from RFC 8291
+pub fn encrypt(
+    payload: &[u8],
+    ua_public: &[u8],
+    auth_secret: &[u8],
+) -> crate::error::Result<Vec<u8>> {
+    let as_secret = SecretKey::random(&mut OsRng);
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    encrypt_with_keys(payload, ua_public, auth_secret, &as_secret, &salt)
+}
+
+/// Same as [`encrypt`] but with a caller provided application server key
+/// and salt, both must be freshly generated for every message
+pub fn encrypt_with_keys(
+    payload: &[u8],
+    ua_public: &[u8],
+    auth_secret: &[u8],
+    as_secret: &SecretKey,
+    salt: &[u8; 16],
+) -> crate::error::Result<Vec<u8>> {
+    let ua_key = PublicKey::from_sec1_bytes(ua_public).map_err(|_| {
+        Error::InvalidWebPushSubscription("p256dh is not a valid P-256 key".to_string())
+    })?;
+    let ua_public = ua_key.to_encoded_point(false);
+    let as_public = as_secret.public_key().to_encoded_point(false);
+
+    let shared_secret =
+        p256::ecdh::diffie_hellman(as_secret.to_nonzero_scalar(), ua_key.as_affine());
+
+    // IKM = HKDF(auth_secret, ecdh_secret, "WebPush: info" || 0x00 || ua_public || as_public)
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public.as_bytes());
+    key_info.extend_from_slice(as_public.as_bytes());
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(
+        Some(auth_secret),
+        shared_secret.raw_secret_bytes().as_slice(),
+    )
+    .expand(&key_info, &mut ikm)
+    .map_err(|_| Error::WebPushEncryption)?;
+
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), &ikm);
+    let mut content_encryption_key = [0u8; 16];
+    hkdf.expand(
+        b"Content-Encoding: aes128gcm\0",
+        &mut content_encryption_key,
+    )
+    .map_err(|_| Error::WebPushEncryption)?;
+    let mut nonce = [0u8; 12];
+    hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce)
+        .map_err(|_| Error::WebPushEncryption)?;
+
+    // Single record, terminated by the last record padding delimiter
+    let mut plaintext = payload.to_vec();
+    plaintext.push(0x02);
+    if plaintext.len() + TAG_LENGTH > RECORD_SIZE as usize {
+        return Err(Error::PayloadTooLarge);
+    }
+
+    let ciphertext = Aes128Gcm::new_from_slice(&content_encryption_key)
+        .map_err(|_| Error::WebPushEncryption)?
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| Error::WebPushEncryption)?;
+
+    // Header: salt (16) || rs (4) || idlen (1) || keyid (as_public)
+    let mut body = Vec::with_capacity(21 + as_public.len() + ciphertext.len());
+    body.extend_from_slice(salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.len() as u8);
+    body.extend_from_slice(as_public.as_bytes());
+    body.extend_from_slice(&ciphertext);
+
+    Ok(body)
+}
+
+/// Parse the `Retry-After` header, either a delay in seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => (chrono::DateTime::parse_from_rfc2822(value).ok()? - chrono::Utc::now())
+            .to_std()
+            .ok(),
+    }
+}
+
+fn decode_base64url(value: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(value.trim_end_matches('='))
+}
+
+fn encode_base64url(value: impl AsRef<[u8]>) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value)
+}
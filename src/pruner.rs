@@ -0,0 +1,100 @@
+use {
+    crate::{log::prelude::*, state::AppState},
+    std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    tokio::sync::broadcast,
+};
+
+/// Spawn the tasks periodically deleting notifications older than the
+/// retention window and clients suspended for longer than the grace period
+pub fn spawn(state: Arc<AppState>, shutdown: &broadcast::Receiver<()>) {
+    tokio::spawn(run_notification_pruning(
+        state.clone(),
+        shutdown.resubscribe(),
+    ));
+    tokio::spawn(run_client_pruning(state, shutdown.resubscribe()));
+}
+
+async fn run_notification_pruning(state: Arc<AppState>, mut shutdown: broadcast::Receiver<()>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        state.config.notification_pruning_interval_secs,
+    ));
+    // Skip missed ticks instead of pruning repeatedly after a long run
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => prune_notifications(&state).await,
+            _ = shutdown.recv() => {
+                info!("notification pruning stopping");
+                return;
+            }
+        }
+    }
+}
+
+/// Only one instance prunes notifications at a time
+async fn prune_notifications(state: &AppState) {
+    let start = Instant::now();
+
+    match state
+        .notification_store
+        .prune_notifications(
+            Duration::from_secs(state.config.notification_retention_secs),
+            state.config.notification_pruning_batch_size,
+        )
+        .await
+    {
+        Ok(Some(pruned)) => {
+            info!(
+                pruned,
+                duration_ms = start.elapsed().as_millis() as u64,
+                "pruned notifications"
+            );
+            if let Some(metrics) = &state.metrics {
+                metrics.notification_pruning(pruned, start);
+            }
+        }
+        Ok(None) => debug!("notification pruning is running on another instance"),
+        Err(e) => warn!("error pruning notifications: {e:?}"),
+    }
+}
+
+async fn run_client_pruning(state: Arc<AppState>, mut shutdown: broadcast::Receiver<()>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        state.config.client_pruning_interval_secs,
+    ));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => prune_suspended_clients(&state).await,
+            _ = shutdown.recv() => {
+                info!("client pruning stopping");
+                return;
+            }
+        }
+    }
+}
+
+async fn prune_suspended_clients(state: &AppState) {
+    match state
+        .client_store
+        .delete_suspended_clients(
+            Duration::from_secs(state.config.client_suspension_grace_secs),
+            state.config.client_pruning_batch_size,
+        )
+        .await
+    {
+        Ok(0) => {}
+        Ok(pruned) => {
+            info!(pruned, "pruned suspended clients");
+            if let Some(metrics) = &state.metrics {
+                metrics.suspended_client_pruning(pruned);
+            }
+        }
+        Err(e) => warn!("error pruning suspended clients: {e:?}"),
+    }
+}
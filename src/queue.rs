@@ -0,0 +1,185 @@
+use {
+    crate::{
+        error::Error,
+        handlers::push_message::{
+            build_push_message, deliver_notification, record_dead_letter,
+            record_notification_status,
+        },
+        log::prelude::*,
+        providers::retry::RetryPolicy,
+        state::AppState,
+        stores::{
+            client::ClientStatus, notification::NotificationStatus, queue::QueuedDelivery,
+            StoreError,
+        },
+    },
+    std::{sync::Arc, time::Duration},
+    tokio::sync::broadcast::{self, error::TryRecvError},
+};
+
+const OUTCOME_DELIVERED: &str = "delivered";
+const OUTCOME_RESCHEDULED: &str = "rescheduled";
+const OUTCOME_FAILED: &str = "failed";
+const OUTCOME_DEAD_LETTERED: &str = "dead_lettered";
+
+/// Spawn the configured number of delivery workers, they stop once the
+/// shutdown signal has been received
+pub fn spawn_workers(state: Arc<AppState>, shutdown: &broadcast::Receiver<()>) {
+    for worker_id in 0..state.config.queue_workers {
+        tokio::spawn(run_worker(state.clone(), worker_id, shutdown.resubscribe()));
+    }
+    info!(
+        workers = state.config.queue_workers,
+        "delivery queue workers started"
+    );
+}
+
+async fn run_worker(state: Arc<AppState>, worker_id: usize, mut shutdown: broadcast::Receiver<()>) {
+    let lease = Duration::from_secs(state.config.queue_lease_secs);
+    let poll_interval = Duration::from_millis(state.config.queue_poll_interval_ms);
+
+    loop {
+        if !matches!(shutdown.try_recv(), Err(TryRecvError::Empty)) {
+            info!(worker_id, "delivery queue worker stopping");
+            return;
+        }
+
+        match state.queue_store.claim_delivery(lease).await {
+            Ok(Some(delivery)) => process_delivery(&state, delivery).await,
+            Ok(None) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(poll_interval) => {}
+                    _ = shutdown.recv() => {
+                        info!(worker_id, "delivery queue worker stopping");
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(worker_id, "error claiming queued delivery: {e:?}");
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+#[instrument(skip_all, fields(tenant_id = %delivery.tenant_id, client_id = %delivery.client_id, notification_id = %delivery.notification_id))]
+async fn process_delivery(state: &AppState, delivery: QueuedDelivery) {
+    let result = attempt_delivery(state, &delivery).await;
+
+    // A rescheduled delivery stays `accepted`, its error is kept with the
+    // queued delivery until it is given up on
+    let outcome = match result {
+        Ok(()) => state
+            .queue_store
+            .complete_delivery(&delivery.id)
+            .await
+            .map(|_| OUTCOME_DELIVERED),
+        Err(error)
+            if error.is_retryable()
+                && (delivery.attempts as u32) < state.config.queue_max_attempts =>
+        {
+            let delay = error.retry_after().unwrap_or_else(|| {
+                RetryPolicy::from(&state.config).max_delay(delivery.attempts as u32)
+            });
+            warn!(
+                attempts = delivery.attempts,
+                "rescheduling queued delivery: {error:?}"
+            );
+            state
+                .queue_store
+                .reschedule_delivery(&delivery.id, delay, &error.to_string())
+                .await
+                .map(|_| OUTCOME_RESCHEDULED)
+        }
+        Err(error) if error.is_dead_letter() => {
+            record_failure(state, &delivery, &error).await;
+            record_dead_letter(
+                state,
+                &delivery.tenant_id,
+                &delivery.client_id,
+                &delivery.notification_id,
+                &delivery.payload.0,
+                &error,
+                delivery.attempts,
+            )
+            .await;
+            state
+                .queue_store
+                .complete_delivery(&delivery.id)
+                .await
+                .map(|_| OUTCOME_DEAD_LETTERED)
+        }
+        Err(error) => {
+            warn!(
+                attempts = delivery.attempts,
+                "queued delivery failed: {error:?}"
+            );
+            record_failure(state, &delivery, &error).await;
+            state
+                .queue_store
+                .fail_delivery(&delivery.id, &error.to_string())
+                .await
+                .map(|_| OUTCOME_FAILED)
+        }
+    };
+
+    match outcome {
+        Ok(outcome) => {
+            if let Some(metrics) = &state.metrics {
+                metrics.queue_delivery(outcome);
+            }
+        }
+        // The lease expires and the delivery will be claimed again
+        Err(e) => warn!("error recording queued delivery outcome: {e:?}"),
+    }
+}
+
+async fn record_failure(state: &AppState, delivery: &QueuedDelivery, error: &Error) {
+    record_notification_status(
+        state,
+        &delivery.tenant_id,
+        &delivery.client_id,
+        &delivery.notification_id,
+        NotificationStatus::Failed,
+        Some(&error.to_string()),
+        None,
+    )
+    .await;
+}
+
+async fn attempt_delivery(state: &AppState, delivery: &QueuedDelivery) -> Result<(), Error> {
+    let client = match state
+        .client_store
+        .get_client(&delivery.tenant_id, &delivery.client_id)
+        .await
+    {
+        Ok(c) => Ok(c),
+        Err(StoreError::NotFound(_, _)) => Err(Error::ClientNotFound),
+        Err(e) => Err(Error::Store(e)),
+    }?;
+    if client.status == ClientStatus::Suspended {
+        return Err(Error::ClientSuspended);
+    }
+
+    let tenant = state.tenant_store.get_tenant(&delivery.tenant_id).await?;
+    tenant.check_suspended(&client.push_type)?;
+
+    let body = delivery.payload.0.clone();
+    let options = body.options.clone().unwrap_or_default();
+    let push_message = build_push_message(body, client.always_raw)?;
+
+    deliver_notification(
+        state,
+        &tenant,
+        &delivery.client_id,
+        &client,
+        &delivery.notification_id,
+        &push_message,
+        &options,
+        // Transient failures are rescheduled instead of retried inline
+        &RetryPolicy::no_retries(),
+    )
+    .await
+    .map(|_| ())
+}
@@ -1,26 +1,238 @@
-use ed25519_dalek::VerifyingKey;
+use {
+    crate::{
+        error::{Error, Result},
+        log::prelude::*,
+        state::AppState,
+    },
+    chrono::{DateTime, Utc},
+    ed25519_dalek::VerifyingKey,
+    serde::Deserialize,
+    std::{
+        sync::{Arc, RwLock},
+        time::Duration,
+    },
+    tokio::sync::broadcast,
+};
+
+/// One of the relay's public keys, keys overlap during a rotation so requests
+/// signed with either of them are accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayKey {
+    pub public_key: VerifyingKey,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl RelayKey {
+    pub fn is_valid_at(&self, now: DateTime<Utc>) -> bool {
+        self.not_before.map_or(true, |not_before| now >= not_before)
+            && self.not_after.map_or(true, |not_after| now < not_after)
+    }
+}
+
+/// Key set document served by the key source
+#[derive(Deserialize)]
+struct RelayKeySet {
+    keys: Vec<RelayKeySetEntry>,
+}
+
+#[derive(Deserialize)]
+struct RelayKeySetEntry {
+    public_key: String,
+    #[serde(default)]
+    not_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    not_after: Option<DateTime<Utc>>,
+}
+
+/// Parse a key set, either a JSON document (`{"keys": [{"public_key": ...,
+/// "not_before": ..., "not_after": ...}]}`) or a single hex encoded key as
+/// served by the relay's `/public-key` endpoint
+pub fn parse_relay_keys(document: &str) -> Result<Vec<RelayKey>> {
+    let document = document.trim();
+    if !document.starts_with('{') {
+        return Ok(vec![RelayKey {
+            public_key: string_to_verifying_key(document)?,
+            not_before: None,
+            not_after: None,
+        }]);
+    }
+
+    let key_set: RelayKeySet =
+        serde_json::from_str(document).map_err(|e| Error::InvalidRelayKeySet(e.to_string()))?;
+    key_set
+        .keys
+        .into_iter()
+        .map(|entry| {
+            Ok(RelayKey {
+                public_key: string_to_verifying_key(&entry.public_key)?,
+                not_before: entry.not_before,
+                not_after: entry.not_after,
+            })
+        })
+        .collect()
+}
+
+fn string_to_verifying_key(string_key: &str) -> Result<VerifyingKey> {
+    let key_bytes = hex::decode(string_key.trim()).map_err(Error::Hex)?;
+    let key_bytes = <&[u8; 32]>::try_from(key_bytes.as_slice()).map_err(|_| {
+        Error::InvalidRelayKeySet(format!(
+            "public key must be 32 bytes, got {}",
+            key_bytes.len()
+        ))
+    })?;
+
+    Ok(VerifyingKey::from_bytes(key_bytes)?)
+}
+
+/// Where the relay's key set is reloaded from, a file path (optionally as a
+/// `file://` URL) or an HTTP endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayKeySource {
+    File(String),
+    Url(String),
+}
+
+impl RelayKeySource {
+    pub fn parse(source: &str) -> Self {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            RelayKeySource::Url(source.to_string())
+        } else {
+            let path = source.strip_prefix("file://").unwrap_or(source);
+            RelayKeySource::File(path.to_string())
+        }
+    }
+
+    pub async fn fetch(&self, http_client: &reqwest::Client) -> Result<Vec<RelayKey>> {
+        let document = match self {
+            RelayKeySource::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| Error::InvalidRelayKeySet(format!("reading {path}: {e}")))?,
+            RelayKeySource::Url(url) => {
+                http_client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?
+            }
+        };
+
+        parse_relay_keys(&document)
+    }
+}
 
 #[derive(Clone)]
 pub struct RelayClient {
-    public_key: VerifyingKey,
+    /// Keys configured through `RELAY_PUBLIC_KEY`, always accepted
+    static_keys: Arc<Vec<RelayKey>>,
+    /// Keys from the key source, replaced on every reload
+    loaded_keys: Arc<RwLock<Vec<RelayKey>>>,
 }
 
 impl RelayClient {
-    pub fn new(string_public_key: String) -> crate::error::Result<RelayClient> {
-        let verifying_key = Self::string_to_verifying_key(&string_public_key)?;
+    /// Accepts a comma separated list of hex encoded keys, which may be empty
+    /// when the keys are loaded from a key source
+    pub fn new(string_public_key: String) -> Result<RelayClient> {
+        let static_keys = string_public_key
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                Ok(RelayKey {
+                    public_key: string_to_verifying_key(key)?,
+                    not_before: None,
+                    not_after: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         Ok(RelayClient {
-            public_key: verifying_key,
+            static_keys: Arc::new(static_keys),
+            loaded_keys: Arc::new(RwLock::new(vec![])),
         })
     }
 
-    pub fn get_verifying_key(&self) -> &VerifyingKey {
-        &self.public_key
+    /// Keys a request signed at `now` may be verified with
+    pub fn get_verifying_keys(&self, now: DateTime<Utc>) -> Vec<VerifyingKey> {
+        let loaded_keys = self
+            .loaded_keys
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.static_keys
+            .iter()
+            .chain(loaded_keys.iter())
+            .filter(|key| key.is_valid_at(now))
+            .map(|key| key.public_key)
+            .collect()
     }
 
-    fn string_to_verifying_key(string_key: &str) -> crate::error::Result<VerifyingKey> {
-        let key_bytes = hex::decode(string_key).map_err(crate::error::Error::Hex)?;
-        Ok(VerifyingKey::from_bytes(
-            <&[u8; 32]>::try_from(key_bytes.as_slice()).unwrap(),
-        )?)
+    pub fn set_loaded_keys(&self, keys: Vec<RelayKey>) {
+        *self
+            .loaded_keys
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = keys;
+    }
+}
+
+/// Load the relay's key set once before serving, so relay requests aren't
+/// rejected until the first reload. Fails when the source can't be loaded or
+/// no key is currently valid.
+pub async fn load_keys(state: &AppState) -> Result<()> {
+    let Some(source) = state.config.relay_public_keys_source.as_deref() else {
+        return Ok(());
+    };
+    let source = RelayKeySource::parse(source);
+
+    let keys = source.fetch(&state.http_client).await?;
+    info!(keys = keys.len(), "loaded relay public keys");
+    state.relay_client.set_loaded_keys(keys);
+
+    if state.relay_client.get_verifying_keys(Utc::now()).is_empty() {
+        return Err(Error::InvalidRelayKeySet(format!(
+            "no valid relay public key was loaded from {source:?}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Spawn the task periodically reloading the relay's key set, a failed reload
+/// keeps the previously loaded keys
+pub fn spawn_key_refresh(state: Arc<AppState>, shutdown: &broadcast::Receiver<()>) {
+    let Some(source) = state.config.relay_public_keys_source.as_deref() else {
+        return;
+    };
+    let source = RelayKeySource::parse(source);
+    tokio::spawn(run_key_refresh(state, source, shutdown.resubscribe()));
+}
+
+async fn run_key_refresh(
+    state: Arc<AppState>,
+    source: RelayKeySource,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        state.config.relay_public_keys_refresh_secs,
+    ));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately, the keys were loaded on startup
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => match source.fetch(&state.http_client).await {
+                Ok(keys) => {
+                    debug!(keys = keys.len(), "reloaded relay public keys");
+                    state.relay_client.set_loaded_keys(keys);
+                }
+                Err(e) => warn!("error reloading relay public keys from {source:?}: {e:?}"),
+            },
+            _ = shutdown.recv() => {
+                info!("relay key refresh stopping");
+                return;
+            }
+        }
     }
 }
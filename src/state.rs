@@ -2,14 +2,16 @@ use {
     crate::{
         config::Config,
         metrics::Metrics,
-        middleware::rate_limit,
+        middleware::{rate_limit, validate_signature::ReplayCache},
         networking,
-        providers::Provider,
+        providers::cache::ProviderCache,
         relay::RelayClient,
-        stores::{client::ClientStore, notification::NotificationStore, tenant::TenantStore},
+        stores::{
+            client::ClientStore, dead_letter::DeadLetterStore, notification::NotificationStore,
+            queue::QueueStore, tenant::TenantStore,
+        },
     },
     build_info::BuildInfo,
-    moka::future::Cache,
     std::{net::IpAddr, sync::Arc},
     tokio::time::Duration,
     wc::geoip::{block::middleware::GeoBlockLayer, MaxMindResolver},
@@ -23,6 +25,8 @@ use crate::jwt_validation::JwtValidationClient;
 pub type ClientStoreArc = Arc<dyn ClientStore + Send + Sync + 'static>;
 pub type NotificationStoreArc = Arc<dyn NotificationStore + Send + Sync + 'static>;
 pub type TenantStoreArc = Arc<dyn TenantStore + Send + Sync + 'static>;
+pub type QueueStoreArc = Arc<dyn QueueStore + Send + Sync + 'static>;
+pub type DeadLetterStoreArc = Arc<dyn DeadLetterStore + Send + Sync + 'static>;
 
 pub trait State {
     fn config(&self) -> Config;
@@ -33,6 +37,9 @@ pub trait State {
     fn relay_client(&self) -> RelayClient;
     fn is_multitenant(&self) -> bool;
     fn validate_signatures(&self) -> bool;
+    fn replay_cache(&self) -> ReplayCache;
+    fn max_request_body_bytes(&self) -> usize;
+    fn metrics(&self) -> Option<Metrics>;
 }
 
 #[derive(Clone)]
@@ -45,6 +52,8 @@ pub struct AppState {
     pub client_store: ClientStoreArc,
     pub notification_store: NotificationStoreArc,
     pub tenant_store: TenantStoreArc,
+    pub queue_store: QueueStoreArc,
+    pub dead_letter_store: DeadLetterStoreArc,
     pub relay_client: RelayClient,
     #[cfg(feature = "multitenant")]
     pub jwt_validation_client: JwtValidationClient,
@@ -56,8 +65,9 @@ pub struct AppState {
     /// Service instance uptime measurement
     pub uptime: std::time::Instant,
     pub http_client: reqwest::Client,
-    pub provider_cache: Cache<String, Provider>,
+    pub provider_cache: ProviderCache,
     pub rate_limit: rate_limit::RateLimiter,
+    pub replay_cache: ReplayCache,
 }
 
 build_info::build_info!(fn build_info);
@@ -67,6 +77,8 @@ pub fn new_state(
     client_store: ClientStoreArc,
     notification_store: NotificationStoreArc,
     tenant_store: TenantStoreArc,
+    queue_store: QueueStoreArc,
+    dead_letter_store: DeadLetterStoreArc,
 ) -> crate::error::Result<AppState> {
     let build_info: &BuildInfo = build_info();
 
@@ -77,7 +89,10 @@ pub fn new_state(
     let is_multitenant = false;
 
     #[cfg(feature = "multitenant")]
-    let jwt_secret = config.jwt_secret.clone();
+    let jwt_validation_client = JwtValidationClient::new(config.jwt_secret.clone())
+        .with_audience(config.jwt_audience.clone())
+        .with_issuer(config.jwt_issuer.clone())
+        .with_require_scopes(config.jwt_require_scopes);
 
     let public_ip = match networking::find_public_ip_addr() {
         Ok(ip) => Some(ip),
@@ -94,17 +109,20 @@ pub fn new_state(
         client_store,
         notification_store,
         tenant_store,
+        queue_store,
+        dead_letter_store,
         relay_client: RelayClient::new(config.relay_public_key)?,
         #[cfg(feature = "multitenant")]
-        jwt_validation_client: JwtValidationClient::new(jwt_secret),
+        jwt_validation_client,
         public_ip,
         is_multitenant,
         geoblock: None,
         instance_id: uuid::Uuid::new_v4(),
         uptime: std::time::Instant::now(),
         http_client: reqwest::Client::new(),
-        provider_cache: Cache::new(100),
+        provider_cache: ProviderCache::new(),
         rate_limit: rate_limit::RateLimiter::new(100, Duration::from_secs(60)),
+        replay_cache: ReplayCache::new(Duration::from_secs(config.signature_max_clock_skew_secs)),
     })
 }
 
@@ -146,4 +164,16 @@ impl State for Arc<AppState> {
     fn validate_signatures(&self) -> bool {
         self.config.validate_signatures
     }
+
+    fn replay_cache(&self) -> ReplayCache {
+        self.replay_cache.clone()
+    }
+
+    fn max_request_body_bytes(&self) -> usize {
+        self.config.max_request_body_bytes
+    }
+
+    fn metrics(&self) -> Option<Metrics> {
+        self.metrics.clone()
+    }
 }
@@ -5,8 +5,14 @@ use {
         stores::{self, StoreError::NotFound},
     },
     async_trait::async_trait,
+    chrono::{DateTime, NaiveTime, Offset, TimeZone, Utc},
+    chrono_tz::Tz,
+    serde::{Deserialize, Serialize},
     sqlx::Executor,
-    std::time::Instant,
+    std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    },
     tracing::{debug, instrument},
 };
 
@@ -17,6 +23,120 @@ pub struct Client {
     #[sqlx(rename = "device_token")]
     pub token: String,
     pub always_raw: bool,
+    /// Bundle id of the app, selecting one of the tenant's APNs topics
+    pub apns_topic: Option<String>,
+    /// Managed through `record_client_strike`, registering the client again
+    /// resets both and reactivates it
+    pub status: ClientStatus,
+    /// Consecutive bad device token responses
+    pub strikes: i32,
+    /// Managed through `update_client_settings`, `create_client` leaves the
+    /// stored settings untouched
+    #[sqlx(flatten)]
+    pub settings: ClientSettings,
+}
+
+/// Suspended clients aren't sent to and are pruned after a grace period
+/// unless they register again
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "client_status")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ClientStatus {
+    #[default]
+    Active,
+    Suspended,
+}
+
+/// Tag and topic filters evaluated before a message is sent to the client
+#[derive(Debug, Clone, Default, PartialEq, Eq, sqlx::FromRow)]
+pub struct ClientSettings {
+    /// Only messages with one of these tags are sent, all tags are allowed
+    /// when not set
+    pub allowed_tags: Option<Vec<i64>>,
+    pub denied_tags: Vec<i64>,
+    pub muted_topics: Vec<String>,
+
+    /// IANA name of the client's timezone, quiet hours are in local time
+    pub timezone: Option<String>,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    pub quiet_hours_mode: QuietHoursMode,
+
+    /// Lowercase BCP 47 tag (e.g. `pt-br`) choosing the language of the
+    /// tenant's notification templates
+    pub locale: Option<String>,
+}
+
+/// What happens to alert messages sent during the client's quiet hours
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "quiet_hours_mode")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum QuietHoursMode {
+    /// Sent straight away as a background message
+    #[default]
+    Silent,
+    /// Held back and sent once the quiet hours end
+    Defer,
+}
+
+impl ClientSettings {
+    /// Whether a message with the given tag and topic should be sent, legacy
+    /// messages have no tag so only the muted topics apply to them
+    pub fn allows(&self, tag: Option<u32>, topic: &str) -> bool {
+        if self.muted_topics.iter().any(|muted| muted == topic) {
+            return false;
+        }
+
+        let Some(tag) = tag.map(i64::from) else {
+            return true;
+        };
+        if self.denied_tags.contains(&tag) {
+            return false;
+        }
+        self.allowed_tags
+            .as_ref()
+            .map_or(true, |allowed| allowed.contains(&tag))
+    }
+
+    /// End of the quiet hours window `now` falls in, windows may wrap past
+    /// midnight (e.g. 22:00 to 07:00)
+    pub fn quiet_hours_end(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (Some(timezone), Some(start), Some(end)) = (
+            self.timezone.as_deref(),
+            self.quiet_hours_start,
+            self.quiet_hours_end,
+        ) else {
+            return None;
+        };
+        let timezone = timezone.parse::<Tz>().ok()?;
+
+        let local = now.with_timezone(&timezone);
+        let time = local.time();
+        let end_date = if start < end {
+            if time < start || time >= end {
+                return None;
+            }
+            local.date_naive()
+        } else if start > end && time >= start {
+            local.date_naive().succ_opt()?
+        } else if start > end && time < end {
+            local.date_naive()
+        } else {
+            return None;
+        };
+
+        let end = end_date.and_time(end);
+        let end = match timezone.from_local_datetime(&end).earliest() {
+            Some(end) => end.with_timezone(&Utc),
+            // The end falls in a DST gap, use the current offset instead
+            None => Utc.from_utc_datetime(
+                &(end - chrono::Duration::seconds(local.offset().fix().local_minus_utc().into())),
+            ),
+        };
+        Some(end)
+    }
 }
 
 #[async_trait]
@@ -29,7 +149,46 @@ pub trait ClientStore {
         metrics: Option<&Metrics>,
     ) -> stores::Result<()>;
     async fn get_client(&self, tenant_id: &str, id: &str) -> stores::Result<Client>;
+    /// Bulk variant of `get_client`, unknown ids are missing from the result
+    async fn get_clients(
+        &self,
+        tenant_id: &str,
+        ids: &[String],
+    ) -> stores::Result<HashMap<String, Client>>;
     async fn delete_client(&self, tenant_id: &str, id: &str) -> stores::Result<()>;
+    async fn update_client_settings(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        settings: &ClientSettings,
+    ) -> stores::Result<()>;
+    /// Add `delta` to the client's unread counter, which never goes below
+    /// zero, and return the new count
+    async fn add_badge_count(&self, tenant_id: &str, id: &str, delta: i32) -> stores::Result<u32>;
+    async fn set_badge_count(&self, tenant_id: &str, id: &str, count: u32) -> stores::Result<()>;
+    async fn update_client_push_type(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        push_type: ProviderKind,
+    ) -> stores::Result<()>;
+    /// Count a bad device token response against the client, suspending it
+    /// once `limit` consecutive strikes are reached, and return its status
+    async fn record_client_strike(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        limit: u32,
+    ) -> stores::Result<ClientStatus>;
+    async fn reset_client_strikes(&self, tenant_id: &str, id: &str) -> stores::Result<()>;
+    async fn count_suspended_clients(&self, tenant_id: &str) -> stores::Result<i64>;
+    /// Delete clients suspended for longer than `grace` in batches, returning
+    /// how many were deleted
+    async fn delete_suspended_clients(
+        &self,
+        grace: Duration,
+        batch_size: i64,
+    ) -> stores::Result<u64>;
 }
 
 #[async_trait]
@@ -107,7 +266,11 @@ impl ClientStore for sqlx::PgPool {
                     SET device_token = $2,
                         push_type = $3,
                         always_raw = $4,
-                        tenant_id = $5
+                        tenant_id = $5,
+                        apns_topic = $6,
+                        status = 'active',
+                        strikes = 0,
+                        suspended_at = NULL
                     WHERE id = $1
                 ";
                 let start = Instant::now();
@@ -117,6 +280,7 @@ impl ClientStore for sqlx::PgPool {
                     .bind(client.push_type)
                     .bind(client.always_raw)
                     .bind(tenant_id)
+                    .bind(client.apns_topic)
                     .execute(&mut transaction)
                     .await?;
                 if let Some(metrics) = metrics {
@@ -130,20 +294,53 @@ impl ClientStore for sqlx::PgPool {
                 ";
                 let start = Instant::now();
                 sqlx::query(query)
-                    .bind(existing_client.id)
-                    .bind(existing_client.tenant_id)
+                    .bind(&existing_client.id)
+                    .bind(&existing_client.tenant_id)
                     .execute(&mut transaction)
                     .await?;
                 if let Some(metrics) = metrics {
                     metrics.postgres_query("create_client_delete_notifications", start);
                 }
 
+                // Queued deliveries were for the previous client id, and keep
+                // the id from being updated
+                let query = "
+                    DELETE FROM public.delivery_queue
+                    WHERE client_id = $1
+                ";
+                let start = Instant::now();
+                sqlx::query(query)
+                    .bind(&existing_client.id)
+                    .execute(&mut transaction)
+                    .await?;
+                if let Some(metrics) = metrics {
+                    metrics.postgres_query("create_client_delete_deliveries", start);
+                }
+
+                // So are the dead letters, their notifications were deleted above
+                let query = "
+                    DELETE FROM public.dead_letters
+                    WHERE client_id = $1
+                ";
+                let start = Instant::now();
+                sqlx::query(query)
+                    .bind(&existing_client.id)
+                    .execute(&mut transaction)
+                    .await?;
+                if let Some(metrics) = metrics {
+                    metrics.postgres_query("create_client_delete_dead_letters", start);
+                }
+
                 let query = "
                     UPDATE public.clients
                     SET id = $2,
                         push_type = $3,
                         always_raw = $4,
-                        tenant_id = $5
+                        tenant_id = $5,
+                        apns_topic = $6,
+                        status = 'active',
+                        strikes = 0,
+                        suspended_at = NULL
                     WHERE device_token = $1
                 ";
                 let start = Instant::now();
@@ -153,6 +350,7 @@ impl ClientStore for sqlx::PgPool {
                     .bind(client.push_type)
                     .bind(client.always_raw)
                     .bind(tenant_id)
+                    .bind(client.apns_topic)
                     .execute(&mut transaction)
                     .await?;
                 if let Some(metrics) = metrics {
@@ -163,7 +361,11 @@ impl ClientStore for sqlx::PgPool {
                     UPDATE public.clients
                     SET push_type = $2,
                         always_raw = $3,
-                        tenant_id = $4
+                        tenant_id = $4,
+                        apns_topic = $5,
+                        status = 'active',
+                        strikes = 0,
+                        suspended_at = NULL
                     WHERE id = $1
                 ";
                 let start = Instant::now();
@@ -172,6 +374,7 @@ impl ClientStore for sqlx::PgPool {
                     .bind(client.push_type)
                     .bind(client.always_raw)
                     .bind(tenant_id)
+                    .bind(client.apns_topic)
                     .execute(&mut transaction)
                     .await?;
                 if let Some(metrics) = metrics {
@@ -181,7 +384,8 @@ impl ClientStore for sqlx::PgPool {
         } else {
             let start = Instant::now();
             let mut insert_query = sqlx::QueryBuilder::new(
-                "INSERT INTO public.clients (id, tenant_id, push_type, device_token, always_raw)",
+                "INSERT INTO public.clients (id, tenant_id, push_type, device_token, always_raw, \
+                 apns_topic)",
             );
             insert_query.push_values(
                 vec![(
@@ -190,13 +394,15 @@ impl ClientStore for sqlx::PgPool {
                     client.push_type,
                     client.token,
                     client.always_raw,
+                    client.apns_topic,
                 )],
                 |mut b, client| {
                     b.push_bind(client.0)
                         .push_bind(client.1)
                         .push_bind(client.2)
                         .push_bind(client.3)
-                        .push_bind(client.4);
+                        .push_bind(client.4)
+                        .push_bind(client.5);
                 },
             );
             insert_query.build().execute(&mut transaction).await?;
@@ -217,8 +423,9 @@ impl ClientStore for sqlx::PgPool {
     #[instrument(skip(self))]
     async fn get_client(&self, tenant_id: &str, id: &str) -> stores::Result<Client> {
         let res = sqlx::query_as::<sqlx::postgres::Postgres, Client>(
-            "SELECT tenant_id, push_type, device_token, always_raw FROM public.clients WHERE id = \
-             $1 and tenant_id = $2",
+            "SELECT tenant_id, push_type, device_token, always_raw, apns_topic, status, strikes, \
+             allowed_tags, denied_tags, muted_topics, timezone, quiet_hours_start, quiet_hours_end, quiet_hours_mode, locale \
+             FROM public.clients WHERE id = $1 and tenant_id = $2",
         )
         .bind(id)
         .bind(tenant_id)
@@ -232,6 +439,56 @@ impl ClientStore for sqlx::PgPool {
         }
     }
 
+    #[instrument(skip(self, ids))]
+    async fn get_clients(
+        &self,
+        tenant_id: &str,
+        ids: &[String],
+    ) -> stores::Result<HashMap<String, Client>> {
+        #[derive(sqlx::FromRow)]
+        struct ClientRow {
+            id: String,
+            tenant_id: String,
+            push_type: ProviderKind,
+            device_token: String,
+            always_raw: bool,
+            apns_topic: Option<String>,
+            status: ClientStatus,
+            strikes: i32,
+            #[sqlx(flatten)]
+            settings: ClientSettings,
+        }
+
+        let rows = sqlx::query_as::<sqlx::postgres::Postgres, ClientRow>(
+            "SELECT id, tenant_id, push_type, device_token, always_raw, apns_topic, status, \
+             strikes, allowed_tags, denied_tags, muted_topics, timezone, quiet_hours_start, quiet_hours_end, \
+             quiet_hours_mode, locale FROM public.clients WHERE id = ANY($1) and tenant_id = $2",
+        )
+        .bind(ids)
+        .bind(tenant_id)
+        .fetch_all(self)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.id,
+                    Client {
+                        tenant_id: row.tenant_id,
+                        push_type: row.push_type,
+                        token: row.device_token,
+                        always_raw: row.always_raw,
+                        apns_topic: row.apns_topic,
+                        status: row.status,
+                        strikes: row.strikes,
+                        settings: row.settings,
+                    },
+                )
+            })
+            .collect())
+    }
+
     #[instrument(skip(self))]
     async fn delete_client(&self, tenant_id: &str, id: &str) -> stores::Result<()> {
         debug!("ClientStore::delete_client tenant_id={tenant_id} id={id}");
@@ -256,4 +513,236 @@ impl ClientStore for sqlx::PgPool {
             Err(e) => Err(e.into()),
         }
     }
+
+    #[instrument(skip(self, settings))]
+    async fn update_client_settings(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        settings: &ClientSettings,
+    ) -> stores::Result<()> {
+        let res = sqlx::query(
+            "
+            UPDATE public.clients
+            SET allowed_tags = $3,
+                denied_tags = $4,
+                muted_topics = $5,
+                timezone = $6,
+                quiet_hours_start = $7,
+                quiet_hours_end = $8,
+                quiet_hours_mode = $9,
+                locale = $10
+            WHERE id = $1
+                  AND tenant_id = $2
+        ",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(settings.allowed_tags.as_deref())
+        .bind(&settings.denied_tags)
+        .bind(&settings.muted_topics)
+        .bind(settings.timezone.as_deref())
+        .bind(settings.quiet_hours_start)
+        .bind(settings.quiet_hours_end)
+        .bind(settings.quiet_hours_mode)
+        .bind(settings.locale.as_deref())
+        .execute(self)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(NotFound("client".to_string(), id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn add_badge_count(&self, tenant_id: &str, id: &str, delta: i32) -> stores::Result<u32> {
+        let res = sqlx::query_scalar::<sqlx::postgres::Postgres, i32>(
+            "
+            UPDATE public.clients
+            SET badge_count = GREATEST(badge_count + $3, 0)
+            WHERE id = $1
+                  AND tenant_id = $2
+            RETURNING badge_count
+        ",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(delta)
+        .fetch_one(self)
+        .await;
+
+        match res {
+            Err(sqlx::Error::RowNotFound) => Err(NotFound("client".to_string(), id.to_string())),
+            Err(e) => Err(e.into()),
+            Ok(count) => Ok(count.max(0) as u32),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn set_badge_count(&self, tenant_id: &str, id: &str, count: u32) -> stores::Result<()> {
+        let res = sqlx::query(
+            "
+            UPDATE public.clients
+            SET badge_count = $3
+            WHERE id = $1
+                  AND tenant_id = $2
+        ",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(i32::try_from(count).unwrap_or(i32::MAX))
+        .execute(self)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(NotFound("client".to_string(), id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn update_client_push_type(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        push_type: ProviderKind,
+    ) -> stores::Result<()> {
+        let res = sqlx::query(
+            "
+            UPDATE public.clients
+            SET push_type = $3
+            WHERE id = $1
+                  AND tenant_id = $2
+        ",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(push_type)
+        .execute(self)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(NotFound("client".to_string(), id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn record_client_strike(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        limit: u32,
+    ) -> stores::Result<ClientStatus> {
+        // Assignments see the old row, so `strikes + 1` is the new count
+        let res = sqlx::query_scalar::<sqlx::postgres::Postgres, ClientStatus>(
+            "
+            UPDATE public.clients
+            SET strikes = strikes + 1,
+                status = CASE
+                    WHEN strikes + 1 >= $3 THEN 'suspended'::client_status
+                    ELSE status
+                END,
+                suspended_at = CASE
+                    WHEN strikes + 1 >= $3 THEN COALESCE(suspended_at, now())
+                    ELSE suspended_at
+                END
+            WHERE id = $1
+                  AND tenant_id = $2
+            RETURNING status
+        ",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(i32::try_from(limit).unwrap_or(i32::MAX))
+        .fetch_one(self)
+        .await;
+
+        match res {
+            Err(sqlx::Error::RowNotFound) => Err(NotFound("client".to_string(), id.to_string())),
+            Err(e) => Err(e.into()),
+            Ok(status) => Ok(status),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn reset_client_strikes(&self, tenant_id: &str, id: &str) -> stores::Result<()> {
+        sqlx::query(
+            "
+            UPDATE public.clients
+            SET strikes = 0
+            WHERE id = $1
+                  AND tenant_id = $2
+                  AND status = 'active'
+                  AND strikes > 0
+        ",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .execute(self)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn count_suspended_clients(&self, tenant_id: &str) -> stores::Result<i64> {
+        let count = sqlx::query_scalar::<sqlx::postgres::Postgres, i64>(
+            "SELECT count(*) FROM public.clients WHERE tenant_id = $1 AND status = 'suspended'",
+        )
+        .bind(tenant_id)
+        .fetch_one(self)
+        .await?;
+
+        Ok(count)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_suspended_clients(
+        &self,
+        grace: Duration,
+        batch_size: i64,
+    ) -> stores::Result<u64> {
+        let mut deleted = 0;
+        loop {
+            let mut transaction = self.begin().await?;
+
+            // Skip clients locked by a concurrent registration, which may be
+            // reactivating them
+            let ids = sqlx::query_scalar::<sqlx::postgres::Postgres, String>(
+                "
+                SELECT id
+                FROM public.clients
+                WHERE status = 'suspended'
+                      AND suspended_at < now() - make_interval(secs => $1)
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            ",
+            )
+            .bind(grace.as_secs_f64())
+            .bind(batch_size)
+            .fetch_all(&mut transaction)
+            .await?;
+
+            sqlx::query("DELETE FROM public.notifications WHERE client_id = ANY($1)")
+                .bind(&ids)
+                .execute(&mut transaction)
+                .await?;
+            let res = sqlx::query("DELETE FROM public.clients WHERE id = ANY($1)")
+                .bind(&ids)
+                .execute(&mut transaction)
+                .await?;
+
+            transaction.commit().await?;
+
+            deleted += res.rows_affected();
+            if (ids.len() as i64) < batch_size {
+                return Ok(deleted);
+            }
+        }
+    }
 }
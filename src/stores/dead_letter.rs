@@ -0,0 +1,188 @@
+use {
+    crate::{
+        handlers::push_message::PushMessageBody,
+        stores::{self, StoreError::NotFound},
+    },
+    async_trait::async_trait,
+    chrono::{DateTime, Utc},
+    sqlx::types::Json,
+    tracing::instrument,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct DeadLetter {
+    pub id: String,
+    pub tenant_id: String,
+    pub client_id: String,
+    pub notification_id: String,
+
+    /// Redacted payload, see [`PushMessageBody::redacted`]
+    pub payload: Json<PushMessageBody>,
+
+    pub error: String,
+    pub attempts: i32,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait DeadLetterStore {
+    async fn create_dead_letter(
+        &self,
+        tenant_id: &str,
+        client_id: &str,
+        notification_id: &str,
+        payload: &PushMessageBody,
+        error: &str,
+        attempts: i32,
+    ) -> stores::Result<DeadLetter>;
+    async fn get_dead_letters(
+        &self,
+        tenant_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> stores::Result<Vec<DeadLetter>>;
+    async fn get_dead_letter(&self, tenant_id: &str, id: &str) -> stores::Result<DeadLetter>;
+    /// Record another failed replay of the dead letter
+    async fn update_dead_letter_error(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        error: &str,
+    ) -> stores::Result<DeadLetter>;
+    async fn delete_dead_letter(&self, tenant_id: &str, id: &str) -> stores::Result<()>;
+    /// Delete all dead letters of the tenant, returns the number of deleted rows
+    async fn purge_dead_letters(&self, tenant_id: &str) -> stores::Result<u64>;
+}
+
+#[async_trait]
+impl DeadLetterStore for sqlx::PgPool {
+    #[instrument(skip(self, payload))]
+    async fn create_dead_letter(
+        &self,
+        tenant_id: &str,
+        client_id: &str,
+        notification_id: &str,
+        payload: &PushMessageBody,
+        error: &str,
+        attempts: i32,
+    ) -> stores::Result<DeadLetter> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, DeadLetter>(
+            "
+            INSERT INTO public.dead_letters
+                (tenant_id, client_id, notification_id, payload, error, attempts)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *",
+        )
+        .bind(tenant_id)
+        .bind(client_id)
+        .bind(notification_id)
+        .bind(Json(payload))
+        .bind(error)
+        .bind(attempts)
+        .fetch_one(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_dead_letters(
+        &self,
+        tenant_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> stores::Result<Vec<DeadLetter>> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, DeadLetter>(
+            "
+            SELECT *
+            FROM public.dead_letters
+            WHERE tenant_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3",
+        )
+        .bind(tenant_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_dead_letter(&self, tenant_id: &str, id: &str) -> stores::Result<DeadLetter> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, DeadLetter>(
+            "SELECT * FROM public.dead_letters WHERE id = $1 AND tenant_id = $2",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_one(self)
+        .await;
+
+        match res {
+            Err(sqlx::Error::RowNotFound) => {
+                Err(NotFound("dead letter".to_string(), id.to_string()))
+            }
+            Err(e) => Err(e.into()),
+            Ok(row) => Ok(row),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn update_dead_letter_error(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        error: &str,
+    ) -> stores::Result<DeadLetter> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, DeadLetter>(
+            "
+            UPDATE public.dead_letters
+            SET error = $3,
+                attempts = attempts + 1,
+                updated_at = now()
+            WHERE id = $1 AND tenant_id = $2
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(error)
+        .fetch_one(self)
+        .await;
+
+        match res {
+            Err(sqlx::Error::RowNotFound) => {
+                Err(NotFound("dead letter".to_string(), id.to_string()))
+            }
+            Err(e) => Err(e.into()),
+            Ok(row) => Ok(row),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_dead_letter(&self, tenant_id: &str, id: &str) -> stores::Result<()> {
+        let res = sqlx::query("DELETE FROM public.dead_letters WHERE id = $1 AND tenant_id = $2")
+            .bind(id)
+            .bind(tenant_id)
+            .execute(self)
+            .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(NotFound("dead letter".to_string(), id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_dead_letters(&self, tenant_id: &str) -> stores::Result<u64> {
+        let res = sqlx::query("DELETE FROM public.dead_letters WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(self)
+            .await?;
+
+        Ok(res.rows_affected())
+    }
+}
@@ -1,5 +1,7 @@
 pub mod client;
+pub mod dead_letter;
 pub mod notification;
+pub mod queue;
 pub mod tenant;
 
 type Result<T> = std::result::Result<T, StoreError>;
@@ -1,15 +1,31 @@
 use {
     crate::{
         handlers::push_message::PushMessageBody,
+        providers::MAX_COLLAPSE_KEY_LENGTH,
         stores::{self, StoreError::NotFound},
     },
     async_trait::async_trait,
     chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
     serde_json::Value,
-    sqlx::{types::Json, Executor},
+    sqlx::{types::Json, Executor, Postgres, Transaction},
+    std::time::Duration,
     tracing::instrument,
 };
 
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "notification_status")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationStatus {
+    /// Stored and waiting to be sent (inline or by the delivery queue)
+    Accepted,
+    /// Accepted by the push provider
+    Sent,
+    /// The provider rejected the notification, see `failure_reason`
+    Failed,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
 pub struct Notification {
     pub id: String,
@@ -20,6 +36,32 @@ pub struct Notification {
 
     pub last_received_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+
+    pub status: NotificationStatus,
+    pub failure_reason: Option<String>,
+    /// Id assigned by the provider, e.g. the `apns-id`
+    pub provider_message_id: Option<String>,
+    pub status_updated_at: DateTime<Utc>,
+    /// Receipts discarded while the notification was in flight
+    pub duplicate_count: i32,
+
+    /// Only set for notifications of tenants which coalesce bursts
+    pub topic: Option<String>,
+    pub collapse_key: Option<String>,
+}
+
+const NOTIFICATION_PRUNING_LOCK: &str = "notification_pruning";
+
+/// Outcome of receiving a notification for a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationClaim {
+    /// First receipt, or a redelivery of a failed or abandoned send, the
+    /// caller is responsible for sending it
+    Claimed(Notification),
+    /// The notification was already sent to the provider
+    AlreadyDelivered(Notification),
+    /// Another receipt of the notification is still being sent
+    InFlight(Notification),
 }
 
 #[async_trait]
@@ -31,6 +73,26 @@ pub trait NotificationStore {
         client_id: &str,
         payload: &PushMessageBody,
     ) -> stores::Result<Notification>;
+    /// Store the notification and decide whether it should be sent. Receipts
+    /// that are `accepted` for longer than `in_flight_timeout` are assumed to
+    /// have crashed mid-send and are claimed again, unless they are still
+    /// waiting in the delivery queue.
+    async fn claim_notification(
+        &self,
+        id: &str,
+        tenant_id: &str,
+        client_id: &str,
+        payload: &PushMessageBody,
+        in_flight_timeout: Duration,
+    ) -> stores::Result<NotificationClaim>;
+    /// Bulk variant of `claim_notification` for `(id, client_id, payload)`
+    /// entries, claims are returned in the same order
+    async fn claim_notifications(
+        &self,
+        tenant_id: &str,
+        notifications: &[(String, String, PushMessageBody)],
+        in_flight_timeout: Duration,
+    ) -> stores::Result<Vec<NotificationClaim>>;
     async fn get_notification(
         &self,
         id: &str,
@@ -38,6 +100,34 @@ pub trait NotificationStore {
         tenant_id: &str,
     ) -> stores::Result<Notification>;
     async fn delete_notification(&self, id: &str, tenant_id: &str) -> stores::Result<()>;
+    async fn update_notification_status(
+        &self,
+        id: &str,
+        client_id: &str,
+        tenant_id: &str,
+        status: NotificationStatus,
+        failure_reason: Option<&str>,
+        provider_message_id: Option<&str>,
+    ) -> stores::Result<()>;
+    /// Give the notification the collapse key of the client's latest
+    /// notification for the same topic received within `window`, or a new key
+    /// when there is none, so bursts replace each other on the device
+    async fn coalesce_notification(
+        &self,
+        id: &str,
+        tenant_id: &str,
+        client_id: &str,
+        topic: &str,
+        window: Duration,
+    ) -> stores::Result<String>;
+    /// Delete notifications last received before `retention`, in batches of
+    /// `batch_size`. Notifications a dead letter still replays are kept.
+    /// Returns `None` when another instance is already pruning.
+    async fn prune_notifications(
+        &self,
+        retention: Duration,
+        batch_size: i64,
+    ) -> stores::Result<Option<u64>>;
 }
 
 #[async_trait]
@@ -80,6 +170,83 @@ impl NotificationStore for sqlx::PgPool {
         }
     }
 
+    #[instrument(skip(self, payload))]
+    async fn claim_notification(
+        &self,
+        id: &str,
+        tenant_id: &str,
+        client_id: &str,
+        payload: &PushMessageBody,
+        in_flight_timeout: Duration,
+    ) -> stores::Result<NotificationClaim> {
+        let mut transaction = self.begin().await?;
+
+        // Serializes concurrent receipts for the client, so only one of them
+        // can claim the notification
+        sqlx::query("SELECT pg_advisory_xact_lock(abs(hashtext($1::text)))")
+            .bind(client_id)
+            .execute(&mut transaction)
+            .await?;
+
+        let claim = claim_locked(
+            &mut transaction,
+            id,
+            tenant_id,
+            client_id,
+            payload,
+            in_flight_timeout,
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(claim)
+    }
+
+    #[instrument(skip(self, notifications))]
+    async fn claim_notifications(
+        &self,
+        tenant_id: &str,
+        notifications: &[(String, String, PushMessageBody)],
+        in_flight_timeout: Duration,
+    ) -> stores::Result<Vec<NotificationClaim>> {
+        let mut transaction = self.begin().await?;
+
+        // Locking in a stable order so concurrent batches sharing clients
+        // can't deadlock
+        let mut client_ids = notifications
+            .iter()
+            .map(|(_, client_id, _)| client_id.as_str())
+            .collect::<Vec<_>>();
+        client_ids.sort_unstable();
+        client_ids.dedup();
+        for client_id in client_ids {
+            sqlx::query("SELECT pg_advisory_xact_lock(abs(hashtext($1::text)))")
+                .bind(client_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        let mut claims = Vec::with_capacity(notifications.len());
+        for (id, client_id, payload) in notifications {
+            claims.push(
+                claim_locked(
+                    &mut transaction,
+                    id,
+                    tenant_id,
+                    client_id,
+                    payload,
+                    in_flight_timeout,
+                )
+                .await?,
+            );
+        }
+
+        transaction.commit().await?;
+
+        Ok(claims)
+    }
+
     #[instrument(skip(self))]
     async fn get_notification(
         &self,
@@ -121,4 +288,248 @@ impl NotificationStore for sqlx::PgPool {
 
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    async fn update_notification_status(
+        &self,
+        id: &str,
+        client_id: &str,
+        tenant_id: &str,
+        status: NotificationStatus,
+        failure_reason: Option<&str>,
+        provider_message_id: Option<&str>,
+    ) -> stores::Result<()> {
+        let res = sqlx::query(
+            "
+            UPDATE public.notifications
+            SET status = $4,
+                failure_reason = $5,
+                provider_message_id = COALESCE($6, provider_message_id),
+                status_updated_at = now()
+            WHERE id = $1 AND client_id = $2 AND tenant_id = $3",
+        )
+        .bind(id)
+        .bind(client_id)
+        .bind(tenant_id)
+        .bind(status)
+        .bind(failure_reason)
+        .bind(provider_message_id)
+        .execute(self)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(NotFound("notification".to_string(), id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn coalesce_notification(
+        &self,
+        id: &str,
+        tenant_id: &str,
+        client_id: &str,
+        topic: &str,
+        window: Duration,
+    ) -> stores::Result<String> {
+        // New keys are the notification's id, cut to the APNs collapse id limit
+        let res = sqlx::query_scalar::<sqlx::postgres::Postgres, String>(
+            "
+            UPDATE public.notifications
+            SET topic = $4,
+                collapse_key = COALESCE(
+                    (
+                        SELECT collapse_key
+                        FROM public.notifications
+                        WHERE tenant_id = $2
+                          AND client_id = $3
+                          AND topic = $4
+                          AND id != $1
+                          AND collapse_key IS NOT NULL
+                          AND last_received_at > now() - make_interval(secs => $5)
+                        ORDER BY last_received_at DESC
+                        LIMIT 1
+                    ),
+                    collapse_key,
+                    left($1, $6)
+                )
+            WHERE id = $1 AND tenant_id = $2 AND client_id = $3
+            RETURNING collapse_key",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(client_id)
+        .bind(topic)
+        .bind(window.as_secs_f64())
+        .bind(MAX_COLLAPSE_KEY_LENGTH as i32)
+        .fetch_one(self)
+        .await;
+
+        match res {
+            Err(sqlx::Error::RowNotFound) => {
+                Err(NotFound("notification".to_string(), id.to_string()))
+            }
+            Err(e) => Err(e.into()),
+            Ok(collapse_key) => Ok(collapse_key),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn prune_notifications(
+        &self,
+        retention: Duration,
+        batch_size: i64,
+    ) -> stores::Result<Option<u64>> {
+        // The lock is held by this transaction until it's dropped, the batches
+        // are committed separately so rows aren't locked for the whole run
+        let mut lock = self.begin().await?;
+        let locked: bool =
+            sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(abs(hashtext($1::text)))")
+                .bind(NOTIFICATION_PRUNING_LOCK)
+                .fetch_one(&mut lock)
+                .await?;
+        if !locked {
+            return Ok(None);
+        }
+
+        let mut pruned = 0;
+        loop {
+            let res = sqlx::query(
+                "
+                DELETE FROM public.notifications
+                WHERE (id, client_id) IN (
+                    SELECT n.id, n.client_id
+                    FROM public.notifications n
+                    WHERE n.last_received_at < now() - make_interval(secs => $1)
+                      AND NOT EXISTS (
+                          SELECT 1
+                          FROM public.dead_letters d
+                          WHERE d.client_id = n.client_id AND d.notification_id = n.id
+                      )
+                    LIMIT $2
+                )",
+            )
+            .bind(retention.as_secs_f64())
+            .bind(batch_size)
+            .execute(self)
+            .await?;
+
+            pruned += res.rows_affected();
+            if res.rows_affected() < batch_size as u64 {
+                break;
+            }
+        }
+
+        lock.commit().await?;
+
+        Ok(Some(pruned))
+    }
+}
+
+/// Claim logic of `claim_notification`, the caller must hold the client's
+/// advisory lock in `transaction`
+async fn claim_locked(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    tenant_id: &str,
+    client_id: &str,
+    payload: &PushMessageBody,
+    in_flight_timeout: Duration,
+) -> stores::Result<NotificationClaim> {
+    let existing = sqlx::query_as::<sqlx::postgres::Postgres, Notification>(
+        "
+        SELECT *
+        FROM public.notifications
+        WHERE id = $1 AND client_id = $2",
+    )
+    .bind(id)
+    .bind(client_id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    // Queued (possibly deferred or rescheduled) deliveries can wait longer than
+    // the timeout
+    let queued = match &existing {
+        Some(notification) if notification.status != NotificationStatus::Sent => {
+            sqlx::query_scalar::<_, bool>(
+                "
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM public.delivery_queue
+                    WHERE client_id = $1 AND notification_id = $2 AND failed_at IS NULL
+                )",
+            )
+            .bind(client_id)
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await?
+        }
+        _ => false,
+    };
+
+    let claim = match existing {
+        None => NotificationClaim::Claimed(
+            sqlx::query_as::<sqlx::postgres::Postgres, Notification>(
+                "
+                INSERT INTO public.notifications (id, tenant_id, client_id, last_payload)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *",
+            )
+            .bind(id)
+            .bind(tenant_id)
+            .bind(client_id)
+            .bind(Json(payload))
+            .fetch_one(&mut *transaction)
+            .await?,
+        ),
+        Some(notification) if notification.status == NotificationStatus::Sent => {
+            NotificationClaim::AlreadyDelivered(notification)
+        }
+        Some(notification)
+            if queued
+                || (notification.status != NotificationStatus::Failed
+                    && chrono::Duration::from_std(in_flight_timeout)
+                        .map(|timeout| notification.status_updated_at + timeout > Utc::now())
+                        .unwrap_or(true)) =>
+        {
+            // The status belongs to the original send, and `status_updated_at`
+            // is left untouched so retries can't keep an abandoned send in
+            // flight forever
+            sqlx::query(
+                "
+                UPDATE public.notifications
+                SET duplicate_count = duplicate_count + 1,
+                    last_received_at = now()
+                WHERE id = $1 AND client_id = $2",
+            )
+            .bind(id)
+            .bind(client_id)
+            .execute(&mut *transaction)
+            .await?;
+
+            NotificationClaim::InFlight(notification)
+        }
+        Some(_) => NotificationClaim::Claimed(
+            sqlx::query_as::<sqlx::postgres::Postgres, Notification>(
+                "
+                UPDATE public.notifications
+                SET last_payload = $3,
+                    previous_payloads = array_append(previous_payloads, last_payload),
+                    last_received_at = now(),
+                    status = 'accepted',
+                    failure_reason = NULL,
+                    status_updated_at = now()
+                WHERE id = $1 AND client_id = $2
+                RETURNING *",
+            )
+            .bind(id)
+            .bind(client_id)
+            .bind(Json(payload))
+            .fetch_one(&mut *transaction)
+            .await?,
+        ),
+    };
+
+    Ok(claim)
 }
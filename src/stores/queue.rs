@@ -0,0 +1,161 @@
+use {
+    crate::{handlers::push_message::PushMessageBody, stores},
+    async_trait::async_trait,
+    chrono::{DateTime, Utc},
+    sqlx::types::Json,
+    std::time::Duration,
+    tracing::instrument,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct QueuedDelivery {
+    pub id: String,
+    pub tenant_id: String,
+    pub client_id: String,
+    pub notification_id: String,
+
+    pub payload: Json<PushMessageBody>,
+
+    /// Number of times the delivery has been claimed, including the current one
+    pub attempts: i32,
+    pub last_error: Option<String>,
+
+    pub available_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait QueueStore {
+    /// Deliveries are due straight away unless `not_before` is given
+    async fn enqueue(
+        &self,
+        tenant_id: &str,
+        client_id: &str,
+        notification_id: &str,
+        payload: &PushMessageBody,
+        not_before: Option<DateTime<Utc>>,
+    ) -> stores::Result<QueuedDelivery>;
+    /// Claim the next due delivery for `lease`, after which it becomes
+    /// available to other workers again in case this one crashed
+    async fn claim_delivery(&self, lease: Duration) -> stores::Result<Option<QueuedDelivery>>;
+    async fn complete_delivery(&self, id: &str) -> stores::Result<()>;
+    async fn reschedule_delivery(
+        &self,
+        id: &str,
+        delay: Duration,
+        error: &str,
+    ) -> stores::Result<()>;
+    async fn fail_delivery(&self, id: &str, error: &str) -> stores::Result<()>;
+}
+
+#[async_trait]
+impl QueueStore for sqlx::PgPool {
+    #[instrument(skip(self, payload))]
+    async fn enqueue(
+        &self,
+        tenant_id: &str,
+        client_id: &str,
+        notification_id: &str,
+        payload: &PushMessageBody,
+        not_before: Option<DateTime<Utc>>,
+    ) -> stores::Result<QueuedDelivery> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, QueuedDelivery>(
+            "
+            INSERT INTO public.delivery_queue (tenant_id, client_id, notification_id, payload,
+                                               available_at)
+            VALUES ($1, $2, $3, $4, COALESCE($5, now()))
+            RETURNING *",
+        )
+        .bind(tenant_id)
+        .bind(client_id)
+        .bind(notification_id)
+        .bind(Json(payload))
+        .bind(not_before)
+        .fetch_one(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_delivery(&self, lease: Duration) -> stores::Result<Option<QueuedDelivery>> {
+        // `SKIP LOCKED` lets concurrent workers (across instances) claim different
+        // rows without blocking each other
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, QueuedDelivery>(
+            "
+            UPDATE public.delivery_queue
+            SET locked_until = now() + make_interval(secs => $1),
+                attempts = attempts + 1
+            WHERE id = (
+                SELECT id
+                FROM public.delivery_queue
+                WHERE failed_at IS NULL
+                  AND available_at <= now()
+                  AND (locked_until IS NULL OR locked_until < now())
+                ORDER BY available_at
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            RETURNING *",
+        )
+        .bind(lease.as_secs_f64())
+        .fetch_optional(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn complete_delivery(&self, id: &str) -> stores::Result<()> {
+        sqlx::query("DELETE FROM public.delivery_queue WHERE id = $1")
+            .bind(id)
+            .execute(self)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn reschedule_delivery(
+        &self,
+        id: &str,
+        delay: Duration,
+        error: &str,
+    ) -> stores::Result<()> {
+        sqlx::query(
+            "
+            UPDATE public.delivery_queue
+            SET available_at = now() + make_interval(secs => $2),
+                locked_until = NULL,
+                last_error = $3
+            WHERE id = $1",
+        )
+        .bind(id)
+        .bind(delay.as_secs_f64())
+        .bind(error)
+        .execute(self)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn fail_delivery(&self, id: &str, error: &str) -> stores::Result<()> {
+        sqlx::query(
+            "
+            UPDATE public.delivery_queue
+            SET failed_at = now(),
+                locked_until = NULL,
+                last_error = $2
+            WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(self)
+        .await?;
+
+        Ok(())
+    }
+}
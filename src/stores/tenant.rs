@@ -1,27 +1,35 @@
 #[cfg(not(feature = "multitenant"))]
-use {crate::config::Config, std::sync::Arc};
+use {
+    crate::{config::Config, providers::webpush::VapidKey},
+    std::sync::Arc,
+};
 use {
     crate::{
         error::{
             self,
-            Error::{self, InvalidTenantId, ProviderNotAvailable},
+            Error::{
+                self, ApnsTopicNotConfigured, InvalidTenantId, ProviderNotAvailable,
+                ProviderSuspended, TenantSuspended,
+            },
             Result,
         },
         providers::{
             apns::ApnsProvider,
+            cache::{ProviderCache, ProviderCacheKey},
             fcm::FcmProvider,
             fcm_v1::FcmV1Provider,
-            Provider::{self, Apns, Fcm, FcmV1},
-            ProviderKind,
+            webpush::WebPushProvider,
+            Provider::{self, Apns, Fcm, FcmV1, WebPush},
+            ProviderKind, PROVIDER_APNS, PROVIDER_FCM, PROVIDER_WEBPUSH,
         },
     },
     async_trait::async_trait,
     base64::Engine as _,
     chrono::{DateTime, Utc},
-    moka::future::Cache,
     reqwest::Client,
     serde::{Deserialize, Serialize},
     sqlx::{Executor, PgPool},
+    std::time::Duration,
     tracing::{debug, instrument},
 };
 
@@ -33,6 +41,15 @@ const APNS_TYPE_TOKEN: &str = "token";
 
 pub const DEFAULT_TENANT_ID: &str = "0000-0000-0000-0000";
 
+/// Upper bound of the coalescing window, longer bursts are separate bursts
+pub const MAX_COALESCE_WINDOW_SECS: u32 = 3_600;
+
+/// Maximum number of additional APNs topics per tenant
+pub const MAX_APNS_TOPICS: usize = 32;
+
+/// Longest accepted locale, this is the buffer size recommended by BCP 47
+pub const MAX_LOCALE_LENGTH: usize = 35;
+
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "apns_type")]
 #[sqlx(rename_all = "lowercase")]
@@ -41,6 +58,32 @@ pub enum ApnsType {
     Token,
 }
 
+/// Whether client registrations and deletions must carry an `Authorization`
+/// header, requests with one are verified in every mode
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "client_auth_mode")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuthMode {
+    /// Unauthenticated requests are allowed
+    #[default]
+    Off,
+    /// Unauthenticated requests are allowed and logged
+    Warn,
+    /// Unauthenticated requests are rejected
+    Enforce,
+}
+
+impl ClientAuthMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Warn => "warn",
+            Self::Enforce => "enforce",
+        }
+    }
+}
+
 impl ApnsType {
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -89,6 +132,9 @@ pub struct Tenant {
 
     pub apns_type: Option<ApnsType>,
     pub apns_topic: Option<String>,
+    /// Further bundle ids sharing the token credentials, e.g. a beta or
+    /// white-label build of the app
+    pub apns_topics: Vec<String>,
 
     // Certificate Based
     pub apns_certificate: Option<String>,
@@ -99,14 +145,77 @@ pub struct Tenant {
     pub apns_key_id: Option<String>,
     pub apns_team_id: Option<String>,
 
+    // Web Push (VAPID)
+    pub webpush_vapid_public_key: Option<String>,
+    pub webpush_vapid_private_key: Option<String>,
+    pub webpush_vapid_subject: Option<String>,
+
     // Suspension
     pub suspended: bool,
     pub suspended_reason: Option<String>,
 
+    // Provider suspensions, set when a provider rejects the credentials
+    pub apns_suspended_at: Option<DateTime<Utc>>,
+    pub apns_suspended_reason: Option<String>,
+    pub fcm_suspended_at: Option<DateTime<Utc>>,
+    pub fcm_suspended_reason: Option<String>,
+    pub webpush_suspended_at: Option<DateTime<Utc>>,
+    pub webpush_suspended_reason: Option<String>,
+
+    // Coalescing, disabled when not set
+    pub coalesce_window_secs: Option<i32>,
+
+    /// Retry tokens rejected by one APNs environment against the other one
+    pub apns_environment_fallback: bool,
+
+    /// Overrides the server's client authentication mode when set
+    pub client_auth_mode: Option<ClientAuthMode>,
+
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Set when a provider rejects the tenant's credentials, cleared when they're
+/// uploaded again
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProviderSuspension {
+    pub reason: Option<String>,
+    pub suspended_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "suspension_action")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SuspensionAction {
+    Suspended,
+    Unsuspended,
+}
+
+/// A change of the tenant's or one of its providers' suspension
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct SuspensionEvent {
+    pub id: i64,
+    pub action: SuspensionAction,
+    /// Not set for suspensions of the whole tenant
+    pub provider: Option<String>,
+    pub reason: Option<String>,
+    /// Subject of the admin token, not set for suspensions caused by the
+    /// providers
+    pub actor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct TenantListFilter {
+    /// Tenants suspended, either entirely or for one of their providers, or
+    /// not suspended at all
+    pub suspended: Option<bool>,
+    /// Tenants with credentials for the provider
+    pub provider: Option<ProviderKind>,
+    pub updated_since: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct TenantUpdateParams {
     pub id: String,
@@ -122,6 +231,13 @@ pub struct TenantFcmV1UpdateParams {
     pub fcm_v1_credentials: String,
 }
 
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TenantWebPushUpdateParams {
+    pub vapid_public_key: String,
+    pub vapid_private_key: String,
+    pub vapid_subject: String,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub enum TenantApnsUpdateAuth {
     Certificate {
@@ -140,6 +256,15 @@ pub struct TenantApnsUpdateParams {
     pub apns_topic: String,
 }
 
+/// `None` leaves a setting unchanged, nullable settings are cleared with
+/// `Some(None)`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TenantSettingsUpdateParams {
+    pub coalesce_window_secs: Option<Option<u32>>,
+    pub apns_environment_fallback: Option<bool>,
+    pub client_auth_mode: Option<Option<ClientAuthMode>>,
+}
+
 impl Tenant {
     pub fn providers(&self) -> Vec<ProviderKind> {
         let mut supported = vec![];
@@ -153,6 +278,10 @@ impl Tenant {
             supported.push(ProviderKind::Fcm);
         }
 
+        if self.webpush_vapid_private_key.is_some() && self.webpush_vapid_subject.is_some() {
+            supported.push(ProviderKind::WebPush);
+        }
+
         // Only available in debug/testing
         #[cfg(any(debug_assertions, test))]
         supported.push(ProviderKind::Noop);
@@ -160,6 +289,77 @@ impl Tenant {
         supported
     }
 
+    /// Whether the provider shows the client's unread badge, FCM clients
+    /// only get it through FCM v1, which forwards it to APNs and Android
+    pub fn shows_badge(&self, provider: &ProviderKind) -> bool {
+        match provider {
+            ProviderKind::Apns | ProviderKind::ApnsSandbox => true,
+            ProviderKind::Fcm => self.fcm_v1_credentials.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Suspension of the credentials used for the provider, both APNs
+    /// environments share theirs
+    pub fn provider_suspension(&self, provider: &ProviderKind) -> Option<ProviderSuspension> {
+        let (suspended_at, reason) = match provider {
+            ProviderKind::Apns | ProviderKind::ApnsSandbox => {
+                (self.apns_suspended_at, &self.apns_suspended_reason)
+            }
+            ProviderKind::Fcm => (self.fcm_suspended_at, &self.fcm_suspended_reason),
+            ProviderKind::WebPush => (self.webpush_suspended_at, &self.webpush_suspended_reason),
+            #[cfg(any(debug_assertions, test))]
+            ProviderKind::Noop => return None,
+        };
+
+        suspended_at.map(|suspended_at| ProviderSuspension {
+            reason: reason.clone(),
+            suspended_at,
+        })
+    }
+
+    /// Messages can't be sent with the provider while either the whole tenant
+    /// or the provider is suspended
+    pub fn check_suspended(&self, provider: &ProviderKind) -> Result<()> {
+        if self.suspended {
+            return Err(TenantSuspended);
+        }
+        if self.provider_suspension(provider).is_some() {
+            return Err(ProviderSuspended(provider.into()));
+        }
+
+        Ok(())
+    }
+
+    /// The APNs topic to send a client's messages with, clients without a
+    /// bundle id use the tenant's primary topic. Additional topics share the
+    /// token credentials, so they are unavailable with certificate auth.
+    pub fn apns_topic_for<'a>(&'a self, bundle_id: Option<&'a str>) -> Result<&'a str> {
+        let primary = self
+            .apns_topic
+            .as_deref()
+            .ok_or(ProviderNotAvailable(ProviderKind::Apns.into()))?;
+        match bundle_id {
+            None => Ok(primary),
+            Some(topic) if topic == primary => Ok(primary),
+            Some(topic)
+                if self.get_apns_type() == Some(ApnsType::Token)
+                    && self.apns_topics.iter().any(|t| t == topic) =>
+            {
+                Ok(topic)
+            }
+            Some(topic) => Err(ApnsTopicNotConfigured(topic.to_string())),
+        }
+    }
+
+    /// Window in which messages for the same client and topic are coalesced
+    pub fn coalesce_window(&self) -> Option<Duration> {
+        self.coalesce_window_secs
+            .and_then(|secs| u64::try_from(secs).ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
     pub fn get_apns_type(&self) -> Option<ApnsType> {
         if let Some(apns_type) = &self.apns_type {
             // Check if APNS config is correct
@@ -191,13 +391,82 @@ impl Tenant {
     pub async fn provider(
         &self,
         provider: &ProviderKind,
+        apns_topic: Option<&str>,
         http_client: Client,
-        provider_cache: &Cache<String, Provider>,
+        provider_cache: &ProviderCache,
     ) -> Result<Provider> {
         if !self.providers().contains(provider) {
             return Err(ProviderNotAvailable(provider.into()));
         }
 
+        // APNs clients are bound to a single topic
+        let apns_topic = match provider {
+            ProviderKind::Apns | ProviderKind::ApnsSandbox => {
+                Some(self.apns_topic_for(apns_topic)?)
+            }
+            _ => None,
+        };
+
+        // Noop providers hold the sent notifications and must not be shared
+        #[cfg(any(debug_assertions, test))]
+        if provider == &ProviderKind::Noop {
+            return self.build_provider(provider, apns_topic, http_client).await;
+        }
+
+        let cache_key = self.provider_cache_key(provider, apns_topic);
+        if let Some(cached) = provider_cache.get(&cache_key).await {
+            debug!("cached provider is matched");
+            return Ok(cached);
+        }
+
+        let built = self
+            .build_provider(provider, apns_topic, http_client)
+            .await?;
+        provider_cache.insert(cache_key, built.clone()).await;
+
+        Ok(built)
+    }
+
+    fn provider_cache_key(
+        &self,
+        provider: &ProviderKind,
+        apns_topic: Option<&str>,
+    ) -> ProviderCacheKey {
+        let credentials = match provider {
+            ProviderKind::Apns | ProviderKind::ApnsSandbox => vec![
+                self.apns_type.as_ref().map(ApnsType::as_str),
+                apns_topic,
+                self.apns_certificate.as_deref(),
+                self.apns_certificate_password.as_deref(),
+                self.apns_pkcs8_pem.as_deref(),
+                self.apns_key_id.as_deref(),
+                self.apns_team_id.as_deref(),
+            ],
+            ProviderKind::Fcm => vec![
+                self.fcm_v1_credentials.as_deref(),
+                self.fcm_api_key.as_deref(),
+            ],
+            ProviderKind::WebPush => vec![
+                self.webpush_vapid_private_key.as_deref(),
+                self.webpush_vapid_subject.as_deref(),
+            ],
+            #[cfg(any(debug_assertions, test))]
+            ProviderKind::Noop => vec![],
+        };
+        let credentials = credentials
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect::<Vec<_>>();
+
+        ProviderCacheKey::new(&self.id, *provider, &credentials)
+    }
+
+    async fn build_provider(
+        &self,
+        provider: &ProviderKind,
+        apns_topic: Option<&str>,
+        http_client: Client,
+    ) -> Result<Provider> {
         match provider {
             ProviderKind::ApnsSandbox | ProviderKind::Apns => {
                 let endpoint = match provider {
@@ -208,7 +477,7 @@ impl Tenant {
                     Some(ApnsType::Certificate) => match (
                         &self.apns_certificate,
                         &self.apns_certificate_password,
-                        &self.apns_topic,
+                        apns_topic,
                     ) {
                         (Some(certificate), Some(password), Some(topic)) => {
                             debug!("apns certificate (p12) provider is matched");
@@ -218,7 +487,7 @@ impl Tenant {
                                 &mut &mut std::io::Cursor::new(decoded),
                                 password.clone(),
                                 endpoint,
-                                topic.clone(),
+                                topic.to_string(),
                             )?;
 
                             Ok(Apns(apns_client))
@@ -226,7 +495,7 @@ impl Tenant {
                         _ => Err(ProviderNotAvailable(provider.into())),
                     },
                     Some(ApnsType::Token) => match (
-                        &self.apns_topic,
+                        apns_topic,
                         &self.apns_pkcs8_pem,
                         &self.apns_key_id,
                         &self.apns_team_id,
@@ -240,7 +509,7 @@ impl Tenant {
                                 key_id.clone(),
                                 team_id.clone(),
                                 endpoint,
-                                topic.clone(),
+                                topic.to_string(),
                             )?;
 
                             Ok(Apns(apns_client))
@@ -253,9 +522,6 @@ impl Tenant {
             ProviderKind::Fcm => match self.fcm_v1_credentials.clone() {
                 Some(fcm_v1_credentials) => {
                     debug!("fcm v1 provider is matched");
-                    if let Some(provider) = provider_cache.get(&fcm_v1_credentials).await {
-                        return Ok(provider);
-                    }
                     #[allow(clippy::match_single_binding)]
                     let fcm = FcmV1(
                         FcmV1Provider::new(
@@ -270,9 +536,6 @@ impl Tenant {
                             _ => Error::BadFcmV1Credentials,
                         })?,
                     );
-                    provider_cache
-                        .insert(fcm_v1_credentials.clone(), fcm.clone())
-                        .await;
                     Ok(fcm)
                 }
                 None => match self.fcm_api_key.clone() {
@@ -284,6 +547,16 @@ impl Tenant {
                     None => Err(ProviderNotAvailable(provider.into())),
                 },
             },
+            ProviderKind::WebPush => {
+                match (&self.webpush_vapid_private_key, &self.webpush_vapid_subject) {
+                    (Some(private_key), Some(subject)) => {
+                        debug!("webpush provider is matched");
+                        let webpush = WebPushProvider::new(private_key, subject.clone())?;
+                        Ok(WebPush(webpush))
+                    }
+                    _ => Err(ProviderNotAvailable(provider.into())),
+                }
+            }
             #[cfg(any(debug_assertions, test))]
             ProviderKind::Noop => {
                 debug!("noop provider is matched");
@@ -293,6 +566,91 @@ impl Tenant {
     }
 }
 
+/// Text shown for messages the server can't read, i.e. `always_raw` and
+/// encrypted legacy messages, instead of the generic placeholder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct NotificationTemplate {
+    /// Untagged templates apply to messages without a more specific template,
+    /// which includes all legacy messages
+    pub tag: Option<i64>,
+    /// Normalized locale, empty for the template used when none of the
+    /// client's locales match
+    pub locale: String,
+    pub title: String,
+    pub body: Option<String>,
+}
+
+/// Locales are matched case-insensitively and with either separator
+pub fn normalize_locale(locale: &str) -> String {
+    locale.replace('_', "-").to_ascii_lowercase()
+}
+
+pub fn is_valid_locale(locale: &str) -> bool {
+    locale.len() <= MAX_LOCALE_LENGTH
+        && locale
+            .split(['-', '_'])
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Name the provider's suspensions are recorded under and the columns holding
+/// the suspension of its credentials
+fn suspension_columns(
+    provider: &ProviderKind,
+) -> Result<(&'static str, &'static str, &'static str)> {
+    match provider {
+        ProviderKind::Apns | ProviderKind::ApnsSandbox => {
+            Ok((PROVIDER_APNS, "apns_suspended_at", "apns_suspended_reason"))
+        }
+        ProviderKind::Fcm => Ok((PROVIDER_FCM, "fcm_suspended_at", "fcm_suspended_reason")),
+        ProviderKind::WebPush => Ok((
+            PROVIDER_WEBPUSH,
+            "webpush_suspended_at",
+            "webpush_suspended_reason",
+        )),
+        #[cfg(any(debug_assertions, test))]
+        ProviderKind::Noop => Err(ProviderNotAvailable(provider.into())),
+    }
+}
+
+/// Condition matching tenants with credentials for the provider, mirroring
+/// `Tenant::providers`
+fn provider_configured_condition(provider: &ProviderKind) -> Result<&'static str> {
+    match provider {
+        ProviderKind::Apns | ProviderKind::ApnsSandbox => Ok("apns_type IS NOT NULL"),
+        ProviderKind::Fcm => Ok("(fcm_api_key IS NOT NULL OR fcm_v1_credentials IS NOT NULL)"),
+        ProviderKind::WebPush => {
+            Ok("(webpush_vapid_private_key IS NOT NULL AND webpush_vapid_subject IS NOT NULL)")
+        }
+        #[cfg(any(debug_assertions, test))]
+        ProviderKind::Noop => Err(ProviderNotAvailable(provider.into())),
+    }
+}
+
+async fn insert_suspension_event(
+    executor: impl sqlx::PgExecutor<'_>,
+    id: &str,
+    action: SuspensionAction,
+    provider: Option<&str>,
+    reason: Option<&str>,
+    actor: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO public.tenant_suspension_events (tenant_id, action, provider, reason, actor)
+        VALUES ($1, $2, $3, $4, $5)
+    ",
+    )
+    .bind(id)
+    .bind(action)
+    .bind(provider)
+    .bind(reason)
+    .bind(actor)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
 #[async_trait]
 pub trait TenantStore {
     async fn get_tenant(&self, id: &str) -> Result<Tenant>;
@@ -313,8 +671,53 @@ pub trait TenantStore {
         params: TenantApnsUpdateAuth,
     ) -> Result<Tenant>;
     async fn update_tenant_delete_apns(&self, id: &str) -> Result<Tenant>;
-    async fn suspend_tenant(&self, id: &str, reason: &str) -> Result<()>;
-    async fn unsuspend_tenant(&self, id: &str) -> Result<()>;
+    /// Replace the tenant's additional APNs topics
+    async fn update_tenant_apns_topics(&self, id: &str, topics: &[String]) -> Result<Tenant>;
+    async fn update_tenant_webpush(
+        &self,
+        id: &str,
+        params: TenantWebPushUpdateParams,
+    ) -> Result<Tenant>;
+    async fn update_tenant_delete_webpush(&self, id: &str) -> Result<Tenant>;
+    async fn update_tenant_settings(
+        &self,
+        id: &str,
+        params: TenantSettingsUpdateParams,
+    ) -> Result<Tenant>;
+    /// Suspend the credentials used for the provider, keeping the time of the
+    /// first suspension
+    async fn suspend_provider(&self, id: &str, provider: &ProviderKind, reason: &str)
+        -> Result<()>;
+    async fn unsuspend_provider(&self, id: &str, provider: &ProviderKind) -> Result<()>;
+    /// Tenants matching the filter, ordered by id
+    async fn list_tenants(
+        &self,
+        filter: &TenantListFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Tenant>>;
+    /// Suspend the whole tenant on behalf of an operator
+    async fn suspend_tenant(&self, id: &str, reason: &str, actor: &str) -> Result<Tenant>;
+    async fn unsuspend_tenant(&self, id: &str, actor: &str) -> Result<Tenant>;
+    /// Suspensions of the tenant and its providers, newest first
+    async fn get_suspension_history(
+        &self,
+        id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SuspensionEvent>>;
+    async fn get_templates(&self, id: &str) -> Result<Vec<NotificationTemplate>>;
+    /// Replace all of the tenant's templates
+    async fn set_templates(&self, id: &str, templates: &[NotificationTemplate]) -> Result<()>;
+    /// Find the template for a message, a template for the message's tag is
+    /// preferred over an untagged one, then the client's full locale over its
+    /// language and over the fallback template
+    async fn get_template(
+        &self,
+        id: &str,
+        tag: Option<u32>,
+        locale: Option<&str>,
+    ) -> Result<Option<NotificationTemplate>>;
 }
 
 #[async_trait]
@@ -486,6 +889,7 @@ impl TenantStore for PgPool {
             UPDATE public.tenants
             SET updated_at = NOW(),
                 apns_topic = NULL,
+                apns_topics = '{}',
                 apns_type = NULL,
                 apns_certificate = NULL,
                 apns_certificate_password = NULL,
@@ -504,33 +908,388 @@ impl TenantStore for PgPool {
     }
 
     #[instrument(skip(self))]
-    async fn suspend_tenant(&self, id: &str, reason: &str) -> Result<()> {
-        let mut query_builder = sqlx::QueryBuilder::new(
-            "UPDATE public.tenants SET suspended = true, updated_at = NOW(), suspended_reason =",
+    async fn update_tenant_apns_topics(&self, id: &str, topics: &[String]) -> Result<Tenant> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, Tenant>(
+            "UPDATE public.tenants SET apns_topics = $2, updated_at = NOW() WHERE id = $1 \
+             RETURNING *;",
+        )
+        .bind(id)
+        .bind(topics)
+        .fetch_one(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self, params))]
+    async fn update_tenant_webpush(
+        &self,
+        id: &str,
+        params: TenantWebPushUpdateParams,
+    ) -> Result<Tenant> {
+        let query = "
+            UPDATE public.tenants
+            SET updated_at = NOW(),
+                webpush_vapid_public_key = $2,
+                webpush_vapid_private_key = $3,
+                webpush_vapid_subject = $4
+            WHERE id = $1
+            RETURNING *
+        ";
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, Tenant>(query)
+            .bind(id)
+            .bind(params.vapid_public_key)
+            .bind(params.vapid_private_key)
+            .bind(params.vapid_subject)
+            .fetch_one(self)
+            .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_tenant_delete_webpush(&self, id: &str) -> Result<Tenant> {
+        let query = "
+            UPDATE public.tenants
+            SET updated_at = NOW(),
+                webpush_vapid_public_key = NULL,
+                webpush_vapid_private_key = NULL,
+                webpush_vapid_subject = NULL
+            WHERE id = $1
+            RETURNING *
+        ";
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, Tenant>(query)
+            .bind(id)
+            .fetch_one(self)
+            .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_tenant_settings(
+        &self,
+        id: &str,
+        params: TenantSettingsUpdateParams,
+    ) -> Result<Tenant> {
+        let query = "
+            UPDATE public.tenants
+            SET updated_at = NOW(),
+                coalesce_window_secs = CASE WHEN $2 THEN $3 ELSE coalesce_window_secs END,
+                apns_environment_fallback = COALESCE($4, apns_environment_fallback),
+                client_auth_mode = CASE WHEN $5 THEN $6 ELSE client_auth_mode END
+            WHERE id = $1
+            RETURNING *
+        ";
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, Tenant>(query)
+            .bind(id)
+            .bind(params.coalesce_window_secs.is_some())
+            .bind(
+                params
+                    .coalesce_window_secs
+                    .flatten()
+                    .map(|secs| secs as i32),
+            )
+            .bind(params.apns_environment_fallback)
+            .bind(params.client_auth_mode.is_some())
+            .bind(params.client_auth_mode.flatten())
+            .fetch_one(self)
+            .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn suspend_provider(
+        &self,
+        id: &str,
+        provider: &ProviderKind,
+        reason: &str,
+    ) -> Result<()> {
+        let (provider, suspended_at, suspended_reason) = suspension_columns(provider)?;
+        let mut transaction = self.begin().await?;
+
+        let previous: Option<(Option<DateTime<Utc>>,)> = sqlx::query_as(&format!(
+            "SELECT {suspended_at} FROM public.tenants WHERE id = $1 FOR UPDATE"
+        ))
+        .bind(id)
+        .fetch_optional(&mut transaction)
+        .await?;
+
+        let query = format!(
+            "UPDATE public.tenants SET {suspended_at} = COALESCE({suspended_at}, NOW()), \
+             {suspended_reason} = $2, updated_at = NOW() WHERE id = $1"
         );
-        query_builder.push_bind(reason);
-        query_builder.push(" WHERE id = ");
-        query_builder.push_bind(id);
-        let query = query_builder.build();
+        sqlx::query(&query)
+            .bind(id)
+            .bind(reason)
+            .execute(&mut transaction)
+            .await?;
 
-        self.execute(query).await?;
+        // Only the first of the failures suspending the provider is recorded
+        if let Some((None,)) = previous {
+            insert_suspension_event(
+                &mut transaction,
+                id,
+                SuspensionAction::Suspended,
+                Some(provider),
+                Some(reason),
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
 
         Ok(())
     }
 
     #[instrument(skip(self))]
-    async fn unsuspend_tenant(&self, id: &str) -> Result<()> {
-        let mut query_builder = sqlx::QueryBuilder::new(
-            "UPDATE public.tenants SET suspended = false, updated_at = NOW(), suspended_reason = \
-             null WHERE id = ",
+    async fn unsuspend_provider(&self, id: &str, provider: &ProviderKind) -> Result<()> {
+        let (provider, suspended_at, suspended_reason) = suspension_columns(provider)?;
+        let mut transaction = self.begin().await?;
+
+        let previous: Option<(Option<DateTime<Utc>>,)> = sqlx::query_as(&format!(
+            "SELECT {suspended_at} FROM public.tenants WHERE id = $1 FOR UPDATE"
+        ))
+        .bind(id)
+        .fetch_optional(&mut transaction)
+        .await?;
+
+        let query = format!(
+            "UPDATE public.tenants SET {suspended_at} = NULL, {suspended_reason} = NULL, \
+             updated_at = NOW() WHERE id = $1"
         );
-        query_builder.push_bind(id);
-        let query = query_builder.build();
+        sqlx::query(&query)
+            .bind(id)
+            .execute(&mut transaction)
+            .await?;
 
-        self.execute(query).await?;
+        if let Some((Some(_),)) = previous {
+            insert_suspension_event(
+                &mut transaction,
+                id,
+                SuspensionAction::Unsuspended,
+                Some(provider),
+                None,
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
 
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    async fn list_tenants(
+        &self,
+        filter: &TenantListFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Tenant>> {
+        let mut query_builder = sqlx::QueryBuilder::new("SELECT * FROM public.tenants WHERE TRUE");
+        if let Some(suspended) = filter.suspended {
+            query_builder
+                .push(
+                    " AND (suspended OR apns_suspended_at IS NOT NULL \
+                     OR fcm_suspended_at IS NOT NULL OR webpush_suspended_at IS NOT NULL) = ",
+                )
+                .push_bind(suspended);
+        }
+        if let Some(provider) = &filter.provider {
+            query_builder
+                .push(" AND ")
+                .push(provider_configured_condition(provider)?);
+        }
+        if let Some(updated_since) = filter.updated_since {
+            query_builder
+                .push(" AND updated_at >= ")
+                .push_bind(updated_since);
+        }
+        query_builder
+            .push(" ORDER BY id LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let res = query_builder
+            .build_query_as::<Tenant>()
+            .fetch_all(self)
+            .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn suspend_tenant(&self, id: &str, reason: &str, actor: &str) -> Result<Tenant> {
+        let mut transaction = self.begin().await?;
+
+        let tenant = sqlx::query_as::<sqlx::postgres::Postgres, Tenant>(
+            "
+            UPDATE public.tenants
+            SET suspended = true, suspended_reason = $2, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+        ",
+        )
+        .bind(id)
+        .bind(reason)
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or_else(|| InvalidTenantId(id.into()))?;
+
+        insert_suspension_event(
+            &mut transaction,
+            id,
+            SuspensionAction::Suspended,
+            None,
+            Some(reason),
+            Some(actor),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(tenant)
+    }
+
+    #[instrument(skip(self))]
+    async fn unsuspend_tenant(&self, id: &str, actor: &str) -> Result<Tenant> {
+        let mut transaction = self.begin().await?;
+
+        let tenant = sqlx::query_as::<sqlx::postgres::Postgres, Tenant>(
+            "
+            UPDATE public.tenants
+            SET suspended = false, suspended_reason = NULL, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+        ",
+        )
+        .bind(id)
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or_else(|| InvalidTenantId(id.into()))?;
+
+        insert_suspension_event(
+            &mut transaction,
+            id,
+            SuspensionAction::Unsuspended,
+            None,
+            None,
+            Some(actor),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(tenant)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_suspension_history(
+        &self,
+        id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SuspensionEvent>> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, SuspensionEvent>(
+            "
+            SELECT id, action, provider, reason, actor, created_at
+            FROM public.tenant_suspension_events
+            WHERE tenant_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            OFFSET $3
+        ",
+        )
+        .bind(id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_templates(&self, id: &str) -> Result<Vec<NotificationTemplate>> {
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, NotificationTemplate>(
+            "
+            SELECT tag, locale, title, body
+            FROM public.notification_templates
+            WHERE tenant_id = $1
+            ORDER BY tag NULLS FIRST, locale
+        ",
+        )
+        .bind(id)
+        .fetch_all(self)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self, templates))]
+    async fn set_templates(&self, id: &str, templates: &[NotificationTemplate]) -> Result<()> {
+        let mut transaction = self.begin().await?;
+
+        sqlx::query("DELETE FROM public.notification_templates WHERE tenant_id = $1")
+            .bind(id)
+            .execute(&mut transaction)
+            .await?;
+
+        for template in templates {
+            sqlx::query(
+                "
+                INSERT INTO public.notification_templates (tenant_id, tag, locale, title, body)
+                VALUES ($1, $2, $3, $4, $5)
+            ",
+            )
+            .bind(id)
+            .bind(template.tag)
+            .bind(&template.locale)
+            .bind(&template.title)
+            .bind(template.body.as_deref())
+            .execute(&mut transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_template(
+        &self,
+        id: &str,
+        tag: Option<u32>,
+        locale: Option<&str>,
+    ) -> Result<Option<NotificationTemplate>> {
+        let locale = locale.map(normalize_locale).unwrap_or_default();
+        let language = locale.split('-').next().unwrap_or_default().to_string();
+
+        let res = sqlx::query_as::<sqlx::postgres::Postgres, NotificationTemplate>(
+            "
+            SELECT tag, locale, title, body
+            FROM public.notification_templates
+            WHERE tenant_id = $1
+                  AND (tag = $2 OR tag IS NULL)
+                  AND locale IN ($3, $4, '')
+            ORDER BY tag IS NULL, length(locale) DESC
+            LIMIT 1
+        ",
+        )
+        .bind(id)
+        .bind(tag.map(i64::from))
+        .bind(locale)
+        .bind(language)
+        .fetch_optional(self)
+        .await?;
+
+        Ok(res)
+    }
 }
 
 #[cfg(not(feature = "multitenant"))]
@@ -539,19 +1298,45 @@ pub struct DefaultTenantStore(Tenant);
 #[cfg(not(feature = "multitenant"))]
 impl DefaultTenantStore {
     pub fn new(config: Arc<Config>) -> Result<DefaultTenantStore> {
+        let webpush_vapid_public_key = match (
+            &config.webpush_vapid_private_key,
+            &config.webpush_vapid_subject,
+        ) {
+            (Some(private_key), Some(subject)) => Some(
+                VapidKey::new(private_key, subject.clone())?
+                    .public_key()
+                    .to_string(),
+            ),
+            _ => None,
+        };
+
         Ok(DefaultTenantStore(Tenant {
             id: DEFAULT_TENANT_ID.to_string(),
             fcm_api_key: config.fcm_api_key.clone(),
             fcm_v1_credentials: config.fcm_v1_credentials.clone(),
             apns_type: config.apns_type,
             apns_topic: config.apns_topic.clone(),
+            apns_topics: config.apns_topics.clone(),
             apns_certificate: config.apns_certificate.clone(),
             apns_certificate_password: config.apns_certificate_password.clone(),
             apns_pkcs8_pem: config.apns_pkcs8_pem.clone(),
             apns_key_id: config.apns_key_id.clone(),
             apns_team_id: config.apns_team_id.clone(),
+            webpush_vapid_public_key,
+            webpush_vapid_private_key: config.webpush_vapid_private_key.clone(),
+            webpush_vapid_subject: config.webpush_vapid_subject.clone(),
             suspended: false,
             suspended_reason: None,
+            apns_suspended_at: None,
+            apns_suspended_reason: None,
+            fcm_suspended_at: None,
+            fcm_suspended_reason: None,
+            webpush_suspended_at: None,
+            webpush_suspended_reason: None,
+            coalesce_window_secs: config.coalesce_window_secs.map(|secs| secs as i32),
+            apns_environment_fallback: config.apns_environment_fallback,
+            // Single tenant deployments use `CLIENT_AUTH_MODE`
+            client_auth_mode: None,
             created_at: Default::default(),
             updated_at: Default::default(),
         }))
@@ -613,11 +1398,85 @@ impl TenantStore for DefaultTenantStore {
         panic!("Shouldn't have run in single tenant mode")
     }
 
-    async fn suspend_tenant(&self, _id: &str, _reason: &str) -> Result<()> {
+    async fn update_tenant_apns_topics(&self, _id: &str, _topics: &[String]) -> Result<Tenant> {
         panic!("Shouldn't have run in single tenant mode")
     }
 
-    async fn unsuspend_tenant(&self, _id: &str) -> Result<()> {
+    async fn update_tenant_webpush(
+        &self,
+        _id: &str,
+        _params: TenantWebPushUpdateParams,
+    ) -> Result<Tenant> {
         panic!("Shouldn't have run in single tenant mode")
     }
+
+    async fn update_tenant_delete_webpush(&self, _id: &str) -> Result<Tenant> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn update_tenant_settings(
+        &self,
+        _id: &str,
+        _params: TenantSettingsUpdateParams,
+    ) -> Result<Tenant> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn suspend_provider(
+        &self,
+        _id: &str,
+        _provider: &ProviderKind,
+        _reason: &str,
+    ) -> Result<()> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn unsuspend_provider(&self, _id: &str, _provider: &ProviderKind) -> Result<()> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn list_tenants(
+        &self,
+        _filter: &TenantListFilter,
+        _limit: i64,
+        _offset: i64,
+    ) -> Result<Vec<Tenant>> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn suspend_tenant(&self, _id: &str, _reason: &str, _actor: &str) -> Result<Tenant> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn unsuspend_tenant(&self, _id: &str, _actor: &str) -> Result<Tenant> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn get_suspension_history(
+        &self,
+        _id: &str,
+        _limit: i64,
+        _offset: i64,
+    ) -> Result<Vec<SuspensionEvent>> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn get_templates(&self, _id: &str) -> Result<Vec<NotificationTemplate>> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    async fn set_templates(&self, _id: &str, _templates: &[NotificationTemplate]) -> Result<()> {
+        panic!("Shouldn't have run in single tenant mode")
+    }
+
+    // Templates are a multi-tenant feature, single tenant messages always use
+    // the generic placeholder
+    async fn get_template(
+        &self,
+        _id: &str,
+        _tag: Option<u32>,
+        _locale: Option<&str>,
+    ) -> Result<Option<NotificationTemplate>> {
+        Ok(None)
+    }
 }
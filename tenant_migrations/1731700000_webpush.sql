@@ -0,0 +1,4 @@
+ALTER TABLE public.tenants
+  ADD COLUMN webpush_vapid_public_key TEXT NULL DEFAULT NULL,
+  ADD COLUMN webpush_vapid_private_key TEXT NULL DEFAULT NULL,
+  ADD COLUMN webpush_vapid_subject TEXT NULL DEFAULT NULL;
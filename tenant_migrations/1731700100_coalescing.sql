@@ -0,0 +1,2 @@
+ALTER TABLE public.tenants
+  ADD COLUMN coalesce_window_secs INTEGER NULL DEFAULT NULL;
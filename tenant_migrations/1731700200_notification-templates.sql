@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS public.notification_templates
+(
+    tenant_id  VARCHAR(255) NOT NULL REFERENCES public.tenants (id) ON DELETE CASCADE,
+    -- Untagged templates apply to messages without a more specific template
+    tag        BIGINT NULL,
+    -- Empty for the fallback template used when no locale matches
+    locale     TEXT NOT NULL DEFAULT '',
+    title      TEXT NOT NULL,
+    body       TEXT NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS notification_templates_unique_idx
+    ON public.notification_templates (tenant_id, COALESCE(tag, -1), locale);
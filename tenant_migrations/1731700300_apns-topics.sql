@@ -0,0 +1,2 @@
+ALTER TABLE public.tenants
+  ADD COLUMN apns_topics TEXT[] NOT NULL DEFAULT '{}';
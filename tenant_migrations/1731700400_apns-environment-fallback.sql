@@ -0,0 +1,2 @@
+ALTER TABLE public.tenants
+  ADD COLUMN apns_environment_fallback BOOLEAN NOT NULL DEFAULT FALSE;
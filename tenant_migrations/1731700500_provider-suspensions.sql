@@ -0,0 +1,30 @@
+ALTER TABLE public.tenants
+  ADD COLUMN apns_suspended_at       TIMESTAMPTZ NULL,
+  ADD COLUMN apns_suspended_reason   TEXT        NULL,
+  ADD COLUMN fcm_suspended_at        TIMESTAMPTZ NULL,
+  ADD COLUMN fcm_suspended_reason    TEXT        NULL,
+  ADD COLUMN webpush_suspended_at    TIMESTAMPTZ NULL,
+  ADD COLUMN webpush_suspended_reason TEXT       NULL;
+
+-- Credential errors used to suspend the whole tenant, move those suspensions
+-- to the provider named in the reason
+UPDATE public.tenants
+SET apns_suspended_at = updated_at,
+    apns_suspended_reason = suspended_reason,
+    suspended = false,
+    suspended_reason = NULL
+WHERE suspended AND suspended_reason ILIKE '%apns%';
+
+UPDATE public.tenants
+SET fcm_suspended_at = updated_at,
+    fcm_suspended_reason = suspended_reason,
+    suspended = false,
+    suspended_reason = NULL
+WHERE suspended AND suspended_reason ILIKE '%fcm%';
+
+UPDATE public.tenants
+SET webpush_suspended_at = updated_at,
+    webpush_suspended_reason = suspended_reason,
+    suspended = false,
+    suspended_reason = NULL
+WHERE suspended AND suspended_reason ILIKE '%web push%';
@@ -0,0 +1,5 @@
+CREATE TYPE public.client_auth_mode AS ENUM ('off', 'warn', 'enforce');
+
+-- Falls back to the server's `CLIENT_AUTH_MODE` when not set
+ALTER TABLE public.tenants
+  ADD COLUMN client_auth_mode public.client_auth_mode NULL;
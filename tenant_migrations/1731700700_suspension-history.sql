@@ -0,0 +1,18 @@
+CREATE TYPE public.suspension_action AS ENUM ('suspended', 'unsuspended');
+
+CREATE TABLE IF NOT EXISTS public.tenant_suspension_events
+(
+    id         BIGSERIAL PRIMARY KEY,
+    tenant_id  VARCHAR(255) NOT NULL REFERENCES public.tenants (id) ON DELETE CASCADE,
+    action     public.suspension_action NOT NULL,
+    -- Empty for suspensions of the whole tenant
+    provider   VARCHAR(255) NULL,
+    reason     TEXT NULL,
+    -- Subject of the admin token, empty for suspensions caused by the providers
+    actor      VARCHAR(255) NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS tenant_suspension_events_tenant_idx
+    ON public.tenant_suspension_events (tenant_id, created_at DESC);
@@ -1,9 +1,11 @@
 #[cfg(feature = "functional_tests")]
-use echo_server::state::{ClientStoreArc, NotificationStoreArc, TenantStoreArc};
+use echo_server::state::{
+    ClientStoreArc, DeadLetterStoreArc, NotificationStoreArc, QueueStoreArc, TenantStoreArc,
+};
 use {
     self::server::EchoServer,
     async_trait::async_trait,
-    echo_server::config::Config,
+    echo_server::{config::Config, stores::tenant::ClientAuthMode},
     sqlx::{Pool, Postgres},
     std::{env, sync::Arc},
     test_context::{AsyncTestContext, TestContext},
@@ -37,6 +39,10 @@ pub struct StoreContext {
     pub notifications: NotificationStoreArc,
     #[cfg(feature = "functional_tests")]
     pub tenants: TenantStoreArc,
+    #[cfg(feature = "functional_tests")]
+    pub queue: QueueStoreArc,
+    #[cfg(feature = "functional_tests")]
+    pub dead_letters: DeadLetterStoreArc,
 }
 
 impl TestContext for ConfigContext {
@@ -49,17 +55,32 @@ impl TestContext for ConfigContext {
             log_level_otel: "info,echo-server=trace".into(),
             disable_header: true,
             validate_signatures: false,
+            signature_max_clock_skew_secs: 300,
+            max_request_body_bytes: 4 * 1024 * 1024,
+            client_auth_mode: ClientAuthMode::Off,
             relay_public_key: env::var("RELAY_PUBLIC_KEY").unwrap_or(
                 // Default relay public key if env not set
                 // TODO I don't think this is used in the tests, so this should be refactored/removed
                 "ff469faa970df23c23a6542765ce8dba2a907538522833b2327a153e365d138e".to_string(),
             ),
+            relay_public_keys_source: None,
+            relay_public_keys_refresh_secs: 300,
             database_url: env::var("DATABASE_URL")
                 .expect("DATABASE_URL environment variable is not set"),
             tenant_database_url: env::var("TENANT_DATABASE_URL")
                 .expect("TENANT_DATABASE_URL environment variable is not set"),
             #[cfg(feature = "multitenant")]
             jwt_secret: "n/a".to_string(),
+            #[cfg(feature = "multitenant")]
+            jwt_jwks_source: None,
+            #[cfg(feature = "multitenant")]
+            jwt_jwks_refresh_secs: 300,
+            #[cfg(feature = "multitenant")]
+            jwt_audience: None,
+            #[cfg(feature = "multitenant")]
+            jwt_issuer: None,
+            #[cfg(feature = "multitenant")]
+            jwt_require_scopes: false,
             otel_exporter_otlp_endpoint: None,
             telemetry_prometheus_port: Some(self::server::get_random_port()),
             #[cfg(not(feature = "multitenant"))]
@@ -77,9 +98,19 @@ impl TestContext for ConfigContext {
             #[cfg(not(feature = "multitenant"))]
             apns_topic: None,
             #[cfg(not(feature = "multitenant"))]
+            apns_topics: vec![],
+            #[cfg(not(feature = "multitenant"))]
             fcm_api_key: None,
             #[cfg(not(feature = "multitenant"))]
             fcm_v1_credentials: None,
+            #[cfg(not(feature = "multitenant"))]
+            webpush_vapid_private_key: None,
+            #[cfg(not(feature = "multitenant"))]
+            webpush_vapid_subject: None,
+            #[cfg(not(feature = "multitenant"))]
+            coalesce_window_secs: None,
+            #[cfg(not(feature = "multitenant"))]
+            apns_environment_fallback: false,
             #[cfg(any(feature = "analytics", feature = "geoblock"))]
             s3_endpoint: None,
             #[cfg(any(feature = "analytics", feature = "geoblock"))]
@@ -90,6 +121,23 @@ impl TestContext for ConfigContext {
             analytics_export_bucket: "example-bucket".to_string(),
             is_test: true,
             cors_allowed_origins: vec!["*".to_string()],
+            retry_max_attempts: 3,
+            retry_initial_backoff_ms: 10,
+            retry_max_backoff_ms: 100,
+            retry_max_elapsed_ms: 1_000,
+            queue_enabled: false,
+            queue_workers: 1,
+            queue_poll_interval_ms: 50,
+            queue_lease_secs: 60,
+            queue_max_attempts: 3,
+            notification_in_flight_timeout_secs: 600,
+            notification_retention_secs: 604_800,
+            notification_pruning_interval_secs: 3_600,
+            notification_pruning_batch_size: 10_000,
+            client_strike_limit: 3,
+            client_suspension_grace_secs: 604_800,
+            client_pruning_interval_secs: 3_600,
+            client_pruning_batch_size: 1_000,
             #[cfg(feature = "geoblock")]
             blocked_countries: vec![],
         };
@@ -136,6 +184,10 @@ impl AsyncTestContext for StoreContext {
             notifications: db_arc.clone(),
             #[cfg(feature = "functional_tests")]
             tenants: tenant_db_arc.clone(),
+            #[cfg(feature = "functional_tests")]
+            queue: db_arc.clone(),
+            #[cfg(feature = "functional_tests")]
+            dead_letters: db_arc.clone(),
         }
     }
 
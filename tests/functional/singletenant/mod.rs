@@ -3,6 +3,7 @@ use {crate::context::EchoServerContext, test_context::test_context};
 
 mod push;
 mod registration;
+mod settings;
 
 #[test_context(EchoServerContext)]
 #[tokio::test]
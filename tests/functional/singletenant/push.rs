@@ -44,6 +44,8 @@ async fn create_client(ctx: &mut EchoServerContext, always_raw: bool) -> (Client
         push_type: "noop".to_string(),
         token: token.clone(),
         always_raw: Some(always_raw),
+        apns_topic: None,
+        settings: None,
     };
 
     // Register client
@@ -84,6 +86,7 @@ async fn test_push(ctx: &mut EchoServerContext) {
             id: push_message_id,
             payload: push_message_payload,
         }),
+        options: None,
     };
 
     // Push
@@ -146,6 +149,7 @@ async fn test_push_multiple_clients(ctx: &mut EchoServerContext) {
             id: push_message_id.clone(),
             payload: push_message_payload,
         }),
+        options: None,
     };
 
     // Push client 1
@@ -206,6 +210,7 @@ async fn test_push_always_raw(ctx: &mut EchoServerContext) {
             id: push_message_id,
             payload: push_message_payload,
         }),
+        options: None,
     };
     let response = client
         .post(format!(
@@ -227,6 +232,7 @@ async fn test_push_always_raw(ctx: &mut EchoServerContext) {
             message: blob,
         }),
         legacy: None,
+        options: None,
     };
     let response = client
         .post(format!(
@@ -0,0 +1,99 @@
+use {
+    crate::context::EchoServerContext,
+    echo_server::handlers::register_client::RegisterBody,
+    ed25519_dalek::SigningKey,
+    relay_rpc::domain::{ClientId, DecodedClientId},
+    test_context::test_context,
+};
+
+/// Register a client, returning its id and token
+async fn register_client(ctx: &EchoServerContext) -> (ClientId, String) {
+    let keypair = SigningKey::generate(&mut rand::thread_rng());
+    let client_id = ClientId::from(DecodedClientId::from_key(&keypair.verifying_key()));
+
+    let jwt = relay_rpc::auth::AuthToken::new(client_id.value().to_string())
+        .aud(format!(
+            "http://127.0.0.1:{}",
+            ctx.server.public_addr.port()
+        ))
+        .as_jwt(&keypair)
+        .unwrap()
+        .to_string();
+
+    let payload = RegisterBody {
+        client_id: client_id.clone(),
+        push_type: "noop".to_string(),
+        token: "test".to_string(),
+        always_raw: Some(false),
+        apns_topic: None,
+        settings: None,
+    };
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/clients", ctx.server.public_addr))
+        .header("Authorization", jwt.clone())
+        .json(&payload)
+        .send()
+        .await
+        .expect("Call failed");
+    assert!(response.status().is_success(), "Failed to register client");
+
+    (client_id, jwt)
+}
+
+#[test_context(EchoServerContext)]
+#[tokio::test]
+async fn test_settings_require_authentication(ctx: &mut EchoServerContext) {
+    let (client_id, jwt) = register_client(ctx).await;
+    let url = format!(
+        "http://{}/clients/{}/settings",
+        ctx.server.public_addr, client_id
+    );
+    let client = reqwest::Client::new();
+
+    let response = client.get(&url).send().await.expect("Call failed");
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .put(&url)
+        .json(&serde_json::json!({ "denied_tags": [1] }))
+        .send()
+        .await
+        .expect("Call failed");
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .get(&url)
+        .header("Authorization", jwt)
+        .send()
+        .await
+        .expect("Call failed");
+    assert!(response.status().is_success());
+}
+
+#[test_context(EchoServerContext)]
+#[tokio::test]
+async fn test_badge_requires_authentication(ctx: &mut EchoServerContext) {
+    let (client_id, jwt) = register_client(ctx).await;
+    let url = format!(
+        "http://{}/clients/{}/badge",
+        ctx.server.public_addr, client_id
+    );
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(&url)
+        .json(&serde_json::json!({ "count": 0 }))
+        .send()
+        .await
+        .expect("Call failed");
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .put(&url)
+        .header("Authorization", jwt)
+        .json(&serde_json::json!({ "count": 0 }))
+        .send()
+        .await
+        .expect("Call failed");
+    assert!(response.status().is_success());
+}
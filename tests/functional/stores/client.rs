@@ -3,9 +3,13 @@ use {
         context::StoreContext,
         functional::stores::{gen_id, TENANT_ID},
     },
+    chrono::NaiveTime,
     echo_server::{
-        handlers::push_message::PushMessageBody, providers::ProviderKind, stores::client::Client,
+        handlers::push_message::PushMessageBody,
+        providers::ProviderKind,
+        stores::client::{Client, ClientSettings, ClientStatus, QuietHoursMode},
     },
+    std::time::Duration,
     test_context::test_context,
 };
 
@@ -23,6 +27,10 @@ async fn client_creation(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Noop,
                 token,
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -52,6 +60,10 @@ async fn client_creation_concurrent(ctx: &mut StoreContext) {
                         push_type: ProviderKind::Noop,
                         token,
                         always_raw: false,
+                        apns_topic: None,
+                        status: ClientStatus::Active,
+                        strikes: 0,
+                        settings: ClientSettings::default(),
                     },
                     None,
                 )
@@ -81,6 +93,10 @@ async fn client_creation_fcm(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Fcm,
                 token,
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -104,6 +120,10 @@ async fn client_creation_apns(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Apns,
                 token,
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -129,6 +149,10 @@ async fn client_upsert_token(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Fcm,
                 token: token.clone(),
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -149,6 +173,7 @@ async fn client_upsert_token(ctx: &mut StoreContext) {
             &PushMessageBody {
                 raw: None,
                 legacy: None,
+                options: None,
             },
         )
         .await
@@ -171,6 +196,10 @@ async fn client_upsert_token(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Apns,
                 token: updated_token.clone(),
                 always_raw: true,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -203,6 +232,10 @@ async fn client_upsert_id(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Fcm,
                 token: token.clone(),
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -223,6 +256,7 @@ async fn client_upsert_id(ctx: &mut StoreContext) {
             &PushMessageBody {
                 raw: None,
                 legacy: None,
+                options: None,
             },
         )
         .await
@@ -245,6 +279,10 @@ async fn client_upsert_id(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Fcm,
                 token: token.clone(),
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -280,6 +318,10 @@ async fn client_create_same_id_and_token(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Fcm,
                 token: token.clone(),
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -300,6 +342,7 @@ async fn client_create_same_id_and_token(ctx: &mut StoreContext) {
             &PushMessageBody {
                 raw: None,
                 legacy: None,
+                options: None,
             },
         )
         .await
@@ -322,6 +365,10 @@ async fn client_create_same_id_and_token(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Noop,
                 token: token.clone(),
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -353,6 +400,10 @@ async fn client_deletion(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Noop,
                 token,
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -376,6 +427,10 @@ async fn client_fetch(ctx: &mut StoreContext) {
                 push_type: ProviderKind::Noop,
                 token: token.clone(),
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -390,3 +445,439 @@ async fn client_fetch(ctx: &mut StoreContext) {
     // Cleaning up records
     ctx.clients.delete_client(TENANT_ID, &id).await.unwrap();
 }
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_bulk_lookup(ctx: &mut StoreContext) {
+    let ids = (0..2)
+        .map(|_| format!("id-{}", gen_id()))
+        .collect::<Vec<_>>();
+    for id in &ids {
+        ctx.clients
+            .create_client(
+                TENANT_ID,
+                id,
+                Client {
+                    tenant_id: TENANT_ID.to_string(),
+                    push_type: ProviderKind::Noop,
+                    token: format!("token-{}", gen_id()),
+                    always_raw: false,
+                    apns_topic: None,
+                    status: ClientStatus::Active,
+                    strikes: 0,
+                    settings: ClientSettings::default(),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    let unknown_id = format!("id-{}", gen_id());
+    let clients = ctx
+        .clients
+        .get_clients(
+            TENANT_ID,
+            &[ids[0].clone(), ids[1].clone(), unknown_id.clone()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(clients.len(), 2);
+    assert!(clients.contains_key(&ids[0]));
+    assert!(clients.contains_key(&ids[1]));
+    assert!(!clients.contains_key(&unknown_id));
+
+    // Cleaning up records
+    for id in &ids {
+        ctx.clients.delete_client(TENANT_ID, id).await.unwrap();
+    }
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_settings_update(ctx: &mut StoreContext) {
+    let id = format!("id-{}", gen_id());
+    let token = format!("token-{}", gen_id());
+    let client = Client {
+        tenant_id: TENANT_ID.to_string(),
+        push_type: ProviderKind::Noop,
+        token,
+        always_raw: false,
+        apns_topic: None,
+        status: ClientStatus::Active,
+        strikes: 0,
+        settings: ClientSettings::default(),
+    };
+    ctx.clients
+        .create_client(TENANT_ID, &id, client.clone(), None)
+        .await
+        .unwrap();
+    let stored = ctx.clients.get_client(TENANT_ID, &id).await.unwrap();
+    assert_eq!(stored.settings, ClientSettings::default());
+
+    let settings = ClientSettings {
+        allowed_tags: Some(vec![4000, u32::MAX.into()]),
+        denied_tags: vec![4001],
+        muted_topics: vec!["muted-topic".to_string()],
+        timezone: Some("Europe/Berlin".to_string()),
+        quiet_hours_start: NaiveTime::from_hms_opt(22, 0, 0),
+        quiet_hours_end: NaiveTime::from_hms_opt(7, 0, 0),
+        quiet_hours_mode: QuietHoursMode::Defer,
+        locale: Some("pt-br".to_string()),
+    };
+    ctx.clients
+        .update_client_settings(TENANT_ID, &id, &settings)
+        .await
+        .unwrap();
+    let stored = ctx.clients.get_client(TENANT_ID, &id).await.unwrap();
+    assert_eq!(stored.settings, settings);
+
+    // Registering again must keep the filters
+    ctx.clients
+        .create_client(TENANT_ID, &id, client, None)
+        .await
+        .unwrap();
+    let clients = ctx
+        .clients
+        .get_clients(TENANT_ID, &[id.clone()])
+        .await
+        .unwrap();
+    assert_eq!(clients[&id].settings, settings);
+
+    // Cleaning up records
+    ctx.clients.delete_client(TENANT_ID, &id).await.unwrap();
+
+    let res = ctx
+        .clients
+        .update_client_settings(TENANT_ID, &id, &settings)
+        .await;
+    assert!(res.is_err());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_badge_count(ctx: &mut StoreContext) {
+    let id = format!("id-{}", gen_id());
+    let token = format!("token-{}", gen_id());
+    ctx.clients
+        .create_client(
+            TENANT_ID,
+            &id,
+            Client {
+                tenant_id: TENANT_ID.to_string(),
+                push_type: ProviderKind::Noop,
+                token,
+                always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let count = ctx
+        .clients
+        .add_badge_count(TENANT_ID, &id, 1)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    let count = ctx
+        .clients
+        .add_badge_count(TENANT_ID, &id, 1)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+
+    // The count never goes below zero
+    ctx.clients
+        .set_badge_count(TENANT_ID, &id, 0)
+        .await
+        .unwrap();
+    let count = ctx
+        .clients
+        .add_badge_count(TENANT_ID, &id, -1)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    ctx.clients
+        .set_badge_count(TENANT_ID, &id, 5)
+        .await
+        .unwrap();
+    let count = ctx
+        .clients
+        .add_badge_count(TENANT_ID, &id, 1)
+        .await
+        .unwrap();
+    assert_eq!(count, 6);
+
+    // Cleaning up records
+    ctx.clients.delete_client(TENANT_ID, &id).await.unwrap();
+
+    assert!(ctx
+        .clients
+        .set_badge_count(TENANT_ID, &id, 0)
+        .await
+        .is_err());
+    assert!(ctx
+        .clients
+        .add_badge_count(TENANT_ID, &id, 1)
+        .await
+        .is_err());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_push_type_update(ctx: &mut StoreContext) {
+    let id = format!("id-{}", gen_id());
+    let token = format!("token-{}", gen_id());
+    ctx.clients
+        .create_client(
+            TENANT_ID,
+            &id,
+            Client {
+                tenant_id: TENANT_ID.to_string(),
+                push_type: ProviderKind::Apns,
+                token,
+                always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    ctx.clients
+        .update_client_push_type(TENANT_ID, &id, ProviderKind::ApnsSandbox)
+        .await
+        .unwrap();
+    let client = ctx.clients.get_client(TENANT_ID, &id).await.unwrap();
+    assert_eq!(client.push_type, ProviderKind::ApnsSandbox);
+
+    // Cleaning up records
+    ctx.clients.delete_client(TENANT_ID, &id).await.unwrap();
+
+    assert!(ctx
+        .clients
+        .update_client_push_type(TENANT_ID, &id, ProviderKind::Apns)
+        .await
+        .is_err());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_suspension(ctx: &mut StoreContext) {
+    // A tenant of its own so the suspended clients can be counted
+    let tenant_id = format!("tenant-{}", gen_id());
+    let id = format!("id-{}", gen_id());
+    let token = format!("token-{}", gen_id());
+    let client = Client {
+        tenant_id: tenant_id.clone(),
+        push_type: ProviderKind::Noop,
+        token,
+        always_raw: false,
+        apns_topic: None,
+        status: ClientStatus::Active,
+        strikes: 0,
+        settings: ClientSettings::default(),
+    };
+    ctx.clients
+        .create_client(&tenant_id, &id, client.clone(), None)
+        .await
+        .unwrap();
+
+    let status = ctx
+        .clients
+        .record_client_strike(&tenant_id, &id, 2)
+        .await
+        .unwrap();
+    assert_eq!(status, ClientStatus::Active);
+    let stored = ctx.clients.get_client(&tenant_id, &id).await.unwrap();
+    assert_eq!(stored.strikes, 1);
+
+    // Strikes only count consecutive failures
+    ctx.clients
+        .reset_client_strikes(&tenant_id, &id)
+        .await
+        .unwrap();
+    let stored = ctx.clients.get_client(&tenant_id, &id).await.unwrap();
+    assert_eq!(stored.strikes, 0);
+
+    for expected in [ClientStatus::Active, ClientStatus::Suspended] {
+        let status = ctx
+            .clients
+            .record_client_strike(&tenant_id, &id, 2)
+            .await
+            .unwrap();
+        assert_eq!(status, expected);
+    }
+    let stored = ctx.clients.get_client(&tenant_id, &id).await.unwrap();
+    assert_eq!(stored.status, ClientStatus::Suspended);
+    assert_eq!(
+        ctx.clients
+            .count_suspended_clients(&tenant_id)
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Registering again reactivates the client
+    ctx.clients
+        .create_client(&tenant_id, &id, client, None)
+        .await
+        .unwrap();
+    let stored = ctx.clients.get_client(&tenant_id, &id).await.unwrap();
+    assert_eq!(stored.status, ClientStatus::Active);
+    assert_eq!(stored.strikes, 0);
+    assert_eq!(
+        ctx.clients
+            .count_suspended_clients(&tenant_id)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // Suspended clients are deleted once the grace period has passed
+    let status = ctx
+        .clients
+        .record_client_strike(&tenant_id, &id, 1)
+        .await
+        .unwrap();
+    assert_eq!(status, ClientStatus::Suspended);
+    assert!(ctx
+        .clients
+        .delete_suspended_clients(Duration::from_secs(3_600), 100)
+        .await
+        .is_ok());
+    assert!(ctx.clients.get_client(&tenant_id, &id).await.is_ok());
+
+    ctx.clients
+        .delete_suspended_clients(Duration::ZERO, 100)
+        .await
+        .unwrap();
+    assert!(ctx.clients.get_client(&tenant_id, &id).await.is_err());
+    assert!(ctx
+        .clients
+        .record_client_strike(&tenant_id, &id, 1)
+        .await
+        .is_err());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_upsert_id_with_queued_delivery(ctx: &mut StoreContext) {
+    let client_id = format!("id-{}", gen_id());
+    let token = format!("token-{}", gen_id());
+    let client = Client {
+        tenant_id: TENANT_ID.to_string(),
+        push_type: ProviderKind::Fcm,
+        token: token.clone(),
+        always_raw: false,
+        apns_topic: None,
+        status: ClientStatus::Active,
+        strikes: 0,
+        settings: ClientSettings::default(),
+    };
+    ctx.clients
+        .create_client(TENANT_ID, &client_id, client.clone(), None)
+        .await
+        .unwrap();
+
+    ctx.queue
+        .enqueue(
+            TENANT_ID,
+            &client_id,
+            &gen_id(),
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Registering the token under a new id drops the old client's deliveries
+    let updated_id = gen_id();
+    ctx.clients
+        .create_client(TENANT_ID, &updated_id, client, None)
+        .await
+        .unwrap();
+    let queued: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM public.delivery_queue WHERE client_id = $1")
+            .bind(&client_id)
+            .fetch_one(ctx.pool.as_ref())
+            .await
+            .unwrap();
+    assert_eq!(queued, 0);
+
+    // Cleaning up records
+    ctx.clients
+        .delete_client(TENANT_ID, &updated_id)
+        .await
+        .unwrap();
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn client_upsert_id_with_dead_letter(ctx: &mut StoreContext) {
+    let client_id = format!("id-{}", gen_id());
+    let token = format!("token-{}", gen_id());
+    let client = Client {
+        tenant_id: TENANT_ID.to_string(),
+        push_type: ProviderKind::Fcm,
+        token: token.clone(),
+        always_raw: false,
+        apns_topic: None,
+        status: ClientStatus::Active,
+        strikes: 0,
+        settings: ClientSettings::default(),
+    };
+    ctx.clients
+        .create_client(TENANT_ID, &client_id, client.clone(), None)
+        .await
+        .unwrap();
+
+    ctx.dead_letters
+        .create_dead_letter(
+            TENANT_ID,
+            &client_id,
+            &gen_id(),
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+            "provider unavailable",
+            1,
+        )
+        .await
+        .unwrap();
+
+    // Registering the token under a new id drops the old client's dead letters
+    let updated_id = gen_id();
+    ctx.clients
+        .create_client(TENANT_ID, &updated_id, client, None)
+        .await
+        .unwrap();
+    let dead_letters: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM public.dead_letters WHERE client_id = $1")
+            .bind(&client_id)
+            .fetch_one(ctx.pool.as_ref())
+            .await
+            .unwrap();
+    assert_eq!(dead_letters, 0);
+
+    // Cleaning up records
+    ctx.clients
+        .delete_client(TENANT_ID, &updated_id)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,99 @@
+use {
+    crate::{
+        context::StoreContext,
+        functional::stores::{gen_id, notification::create_client, TENANT_ID},
+    },
+    echo_server::{handlers::push_message::PushMessageBody, stores::StoreError},
+    test_context::test_context,
+};
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn dead_letter_lifecycle(ctx: &mut StoreContext) {
+    let client_id = create_client(&ctx.clients).await;
+    let notification_id = gen_id();
+
+    let dead_letter = ctx
+        .dead_letters
+        .create_dead_letter(
+            TENANT_ID,
+            &client_id,
+            &notification_id,
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+            "tenant suspended due to invalid configuration",
+            1,
+        )
+        .await
+        .expect("failed to create dead letter");
+
+    let fetched = ctx
+        .dead_letters
+        .get_dead_letter(TENANT_ID, &dead_letter.id)
+        .await
+        .expect("failed to get dead letter");
+    assert_eq!(fetched.notification_id, notification_id);
+
+    let updated = ctx
+        .dead_letters
+        .update_dead_letter_error(TENANT_ID, &dead_letter.id, "still failing")
+        .await
+        .expect("failed to update dead letter");
+    assert_eq!(updated.attempts, 2);
+    assert_eq!(updated.error, "still failing");
+
+    let listed = ctx
+        .dead_letters
+        .get_dead_letters(TENANT_ID, 500, 0)
+        .await
+        .expect("failed to list dead letters");
+    assert!(listed.iter().any(|d| d.id == dead_letter.id));
+
+    ctx.dead_letters
+        .delete_dead_letter(TENANT_ID, &dead_letter.id)
+        .await
+        .expect("failed to delete dead letter");
+
+    let res = ctx
+        .dead_letters
+        .get_dead_letter(TENANT_ID, &dead_letter.id)
+        .await;
+    assert!(matches!(res, Err(StoreError::NotFound(_, _))));
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn dead_letters_deleted_with_client(ctx: &mut StoreContext) {
+    let client_id = create_client(&ctx.clients).await;
+
+    let dead_letter = ctx
+        .dead_letters
+        .create_dead_letter(
+            TENANT_ID,
+            &client_id,
+            &gen_id(),
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+            "provider unavailable",
+            1,
+        )
+        .await
+        .expect("failed to create dead letter");
+
+    ctx.clients
+        .delete_client(TENANT_ID, &client_id)
+        .await
+        .expect("failed to delete client");
+
+    let res = ctx
+        .dead_letters
+        .get_dead_letter(TENANT_ID, &dead_letter.id)
+        .await;
+    assert!(matches!(res, Err(StoreError::NotFound(_, _))));
+}
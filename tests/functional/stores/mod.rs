@@ -1,7 +1,9 @@
 use uuid::Uuid;
 
 mod client;
+mod dead_letter;
 mod notification;
+mod queue;
 /// Tests against the stores
 mod tenant;
 
@@ -3,10 +3,17 @@ use {
         context::StoreContext,
         functional::stores::{gen_id, TENANT_ID},
     },
+    chrono::Utc,
     echo_server::{
-        handlers::push_message::PushMessageBody, providers::ProviderKind, state::ClientStoreArc,
-        stores::client::Client,
+        handlers::push_message::PushMessageBody,
+        providers::ProviderKind,
+        state::ClientStoreArc,
+        stores::{
+            client::{Client, ClientSettings, ClientStatus},
+            notification::{NotificationClaim, NotificationStatus},
+        },
     },
+    std::time::Duration,
     test_context::test_context,
 };
 
@@ -23,6 +30,10 @@ pub async fn create_client(client_store: &ClientStoreArc) -> String {
                 push_type: ProviderKind::Noop,
                 token,
                 always_raw: false,
+                apns_topic: None,
+                status: ClientStatus::Active,
+                strikes: 0,
+                settings: ClientSettings::default(),
             },
             None,
         )
@@ -46,6 +57,7 @@ async fn notification(ctx: &mut StoreContext) {
             &PushMessageBody {
                 raw: None,
                 legacy: None,
+                options: None,
             },
         )
         .await;
@@ -60,6 +72,7 @@ async fn notification_multiple_clients_same_payload(ctx: &mut StoreContext) {
     let payload = PushMessageBody {
         raw: None,
         legacy: None,
+        options: None,
     };
 
     let client_id1 = create_client(&ctx.clients).await;
@@ -90,3 +103,373 @@ async fn notification_multiple_clients_same_payload(ctx: &mut StoreContext) {
         .unwrap();
     assert_eq!(notification2.client_id, client_id2);
 }
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn notification_status(ctx: &mut StoreContext) {
+    let message_id = gen_id();
+    let client_id = create_client(&ctx.clients).await;
+
+    let notification = ctx
+        .notifications
+        .create_or_update_notification(
+            &message_id,
+            TENANT_ID,
+            &client_id,
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(notification.status, NotificationStatus::Accepted);
+
+    ctx.notifications
+        .update_notification_status(
+            &message_id,
+            &client_id,
+            TENANT_ID,
+            NotificationStatus::Sent,
+            None,
+            Some("apns-id"),
+        )
+        .await
+        .unwrap();
+
+    let notification = ctx
+        .notifications
+        .get_notification(&message_id, &client_id, TENANT_ID)
+        .await
+        .unwrap();
+    assert_eq!(notification.status, NotificationStatus::Sent);
+    assert_eq!(notification.provider_message_id.as_deref(), Some("apns-id"));
+    assert_eq!(notification.failure_reason, None);
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn notification_claim_redelivery(ctx: &mut StoreContext) {
+    let message_id = gen_id();
+    let client_id = create_client(&ctx.clients).await;
+    let payload = PushMessageBody {
+        raw: None,
+        legacy: None,
+        options: None,
+    };
+    let timeout = Duration::from_secs(600);
+
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, timeout)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::Claimed(_)));
+
+    // Still being sent, the duplicate is counted without touching the status
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, timeout)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::InFlight(_)));
+    let notification = ctx
+        .notifications
+        .get_notification(&message_id, &client_id, TENANT_ID)
+        .await
+        .unwrap();
+    assert_eq!(notification.status, NotificationStatus::Accepted);
+    assert_eq!(notification.duplicate_count, 1);
+
+    // Abandoned sends are claimed again once the timeout elapsed
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::Claimed(_)));
+
+    // Failed sends are retried
+    ctx.notifications
+        .update_notification_status(
+            &message_id,
+            &client_id,
+            TENANT_ID,
+            NotificationStatus::Failed,
+            Some("provider unavailable"),
+            None,
+        )
+        .await
+        .unwrap();
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, timeout)
+        .await
+        .unwrap();
+    match claim {
+        NotificationClaim::Claimed(notification) => {
+            assert_eq!(notification.status, NotificationStatus::Accepted);
+            assert_eq!(notification.failure_reason, None);
+        }
+        claim => panic!("unexpected claim {claim:?}"),
+    }
+
+    // Delivered ones stay deduplicated
+    ctx.notifications
+        .update_notification_status(
+            &message_id,
+            &client_id,
+            TENANT_ID,
+            NotificationStatus::Sent,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, timeout)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::AlreadyDelivered(_)));
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn notification_pruning(ctx: &mut StoreContext) {
+    let client_id = create_client(&ctx.clients).await;
+    let payload = PushMessageBody {
+        raw: None,
+        legacy: None,
+        options: None,
+    };
+
+    let old_id = gen_id();
+    let dead_letter_id = gen_id();
+    for id in [&old_id, &dead_letter_id] {
+        ctx.notifications
+            .create_or_update_notification(id, TENANT_ID, &client_id, &payload)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE public.notifications SET last_received_at = now() - interval '30 days' WHERE \
+             id = $1",
+        )
+        .bind(id)
+        .execute(ctx.pool.as_ref())
+        .await
+        .unwrap();
+    }
+    ctx.dead_letters
+        .create_dead_letter(
+            TENANT_ID,
+            &client_id,
+            &dead_letter_id,
+            &payload,
+            "provider unavailable",
+            1,
+        )
+        .await
+        .unwrap();
+
+    let recent_id = gen_id();
+    ctx.notifications
+        .create_or_update_notification(&recent_id, TENANT_ID, &client_id, &payload)
+        .await
+        .unwrap();
+
+    // Another test may be pruning concurrently
+    let mut pruned = None;
+    while pruned.is_none() {
+        pruned = ctx
+            .notifications
+            .prune_notifications(Duration::from_secs(7 * 24 * 60 * 60), 1)
+            .await
+            .unwrap();
+    }
+    assert!(pruned.unwrap() >= 1);
+
+    assert!(ctx
+        .notifications
+        .get_notification(&old_id, &client_id, TENANT_ID)
+        .await
+        .is_err());
+    assert!(ctx
+        .notifications
+        .get_notification(&recent_id, &client_id, TENANT_ID)
+        .await
+        .is_ok());
+    // Still needed to replay the dead letter
+    assert!(ctx
+        .notifications
+        .get_notification(&dead_letter_id, &client_id, TENANT_ID)
+        .await
+        .is_ok());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn notification_coalescing(ctx: &mut StoreContext) {
+    let client_id = create_client(&ctx.clients).await;
+    let topic = gen_id();
+    let window = Duration::from_secs(60);
+    let payload = PushMessageBody {
+        raw: None,
+        legacy: None,
+        options: None,
+    };
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let id = gen_id();
+        ctx.notifications
+            .create_or_update_notification(&id, TENANT_ID, &client_id, &payload)
+            .await
+            .unwrap();
+        ids.push(id);
+    }
+
+    // The first message of a burst starts a new collapse key which the
+    // following messages for the same topic share
+    let first = ctx
+        .notifications
+        .coalesce_notification(&ids[0], TENANT_ID, &client_id, &topic, window)
+        .await
+        .unwrap();
+    assert_eq!(first, ids[0]);
+    let second = ctx
+        .notifications
+        .coalesce_notification(&ids[1], TENANT_ID, &client_id, &topic, window)
+        .await
+        .unwrap();
+    assert_eq!(second, first);
+
+    let notification = ctx
+        .notifications
+        .get_notification(&ids[1], &client_id, TENANT_ID)
+        .await
+        .unwrap();
+    assert_eq!(notification.collapse_key, Some(first));
+
+    let res = ctx
+        .notifications
+        .coalesce_notification(&gen_id(), TENANT_ID, &client_id, &topic, window)
+        .await;
+    assert!(res.is_err());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn notification_claim_queued(ctx: &mut StoreContext) {
+    let message_id = gen_id();
+    let client_id = create_client(&ctx.clients).await;
+    let payload = PushMessageBody {
+        raw: None,
+        legacy: None,
+        options: None,
+    };
+
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::Claimed(_)));
+
+    // Deferred deliveries stay in flight past the timeout
+    let delivery = ctx
+        .queue
+        .enqueue(
+            TENANT_ID,
+            &client_id,
+            &message_id,
+            &payload,
+            Some(Utc::now() + chrono::Duration::hours(8)),
+        )
+        .await
+        .unwrap();
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::InFlight(_)));
+
+    // Until the queue gives up on them
+    ctx.queue
+        .fail_delivery(&delivery.id, "provider unavailable")
+        .await
+        .unwrap();
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::Claimed(_)));
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn notification_claim_rescheduled(ctx: &mut StoreContext) {
+    let message_id = gen_id();
+    let client_id = create_client(&ctx.clients).await;
+    let payload = PushMessageBody {
+        raw: None,
+        legacy: None,
+        options: None,
+    };
+
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::Claimed(_)));
+    let delivery = ctx
+        .queue
+        .enqueue(TENANT_ID, &client_id, &message_id, &payload, None)
+        .await
+        .unwrap();
+    ctx.queue
+        .reschedule_delivery(
+            &delivery.id,
+            Duration::from_secs(60),
+            "provider unavailable",
+        )
+        .await
+        .unwrap();
+
+    // A redelivery while the retry is waiting isn't sent again, even if the
+    // notification was marked as failed
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::InFlight(_)));
+    ctx.notifications
+        .update_notification_status(
+            &message_id,
+            &client_id,
+            TENANT_ID,
+            NotificationStatus::Failed,
+            Some("provider unavailable"),
+            None,
+        )
+        .await
+        .unwrap();
+    let claim = ctx
+        .notifications
+        .claim_notification(&message_id, TENANT_ID, &client_id, &payload, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(matches!(claim, NotificationClaim::InFlight(_)));
+
+    // Only one delivery of the notification can be open
+    assert!(ctx
+        .queue
+        .enqueue(TENANT_ID, &client_id, &message_id, &payload, None)
+        .await
+        .is_err());
+}
@@ -0,0 +1,89 @@
+use {
+    crate::{
+        context::StoreContext,
+        functional::stores::{gen_id, notification::create_client, TENANT_ID},
+    },
+    chrono::Utc,
+    echo_server::handlers::push_message::PushMessageBody,
+    std::time::Duration,
+    test_context::test_context,
+};
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn queue_delivery_lifecycle(ctx: &mut StoreContext) {
+    let client_id = create_client(&ctx.clients).await;
+    let notification_id = gen_id();
+
+    let queued = ctx
+        .queue
+        .enqueue(
+            TENANT_ID,
+            &client_id,
+            &notification_id,
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+            None,
+        )
+        .await
+        .expect("failed to enqueue delivery");
+    assert_eq!(queued.attempts, 0);
+
+    // Other deliveries may be due, skip past them until ours is claimed
+    let claimed = loop {
+        let claimed = ctx
+            .queue
+            .claim_delivery(Duration::from_secs(60))
+            .await
+            .expect("failed to claim delivery")
+            .expect("queued delivery was not claimable");
+        if claimed.id == queued.id {
+            break claimed;
+        }
+    };
+    assert_eq!(claimed.attempts, 1);
+    assert_eq!(claimed.client_id, client_id);
+    assert_eq!(claimed.notification_id, notification_id);
+
+    ctx.queue
+        .reschedule_delivery(&claimed.id, Duration::from_secs(3600), "transient")
+        .await
+        .expect("failed to reschedule delivery");
+
+    ctx.queue
+        .complete_delivery(&claimed.id)
+        .await
+        .expect("failed to complete delivery");
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn queue_deferred_delivery(ctx: &mut StoreContext) {
+    let client_id = create_client(&ctx.clients).await;
+    let not_before = Utc::now() + chrono::Duration::hours(1);
+
+    let queued = ctx
+        .queue
+        .enqueue(
+            TENANT_ID,
+            &client_id,
+            &gen_id(),
+            &PushMessageBody {
+                raw: None,
+                legacy: None,
+                options: None,
+            },
+            Some(not_before),
+        )
+        .await
+        .expect("failed to enqueue delivery");
+    assert!((queued.available_at - not_before).num_milliseconds().abs() < 1);
+
+    ctx.queue
+        .complete_delivery(&queued.id)
+        .await
+        .expect("failed to complete delivery");
+}
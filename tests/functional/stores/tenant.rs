@@ -1,9 +1,17 @@
 use {
     crate::context::StoreContext,
-    echo_server::stores::tenant::{
-        TenantApnsUpdateAuth, TenantApnsUpdateParams, TenantFcmUpdateParams,
-        TenantFcmV1UpdateParams, TenantUpdateParams,
+    chrono::Utc,
+    echo_server::{
+        error::Error,
+        providers::ProviderKind,
+        stores::tenant::{
+            ClientAuthMode, NotificationTemplate, SuspensionAction, TenantApnsUpdateAuth,
+            TenantApnsUpdateParams, TenantFcmUpdateParams, TenantFcmV1UpdateParams,
+            TenantListFilter, TenantSettingsUpdateParams, TenantUpdateParams,
+            TenantWebPushUpdateParams,
+        },
     },
+    std::time::Duration,
     test_context::test_context,
     uuid::Uuid,
 };
@@ -154,6 +162,35 @@ async fn tenant_fcm_v1(ctx: &mut StoreContext) {
     assert!(res.is_ok())
 }
 
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_shows_badge(ctx: &mut StoreContext) {
+    let tenant = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+    assert!(tenant.shows_badge(&ProviderKind::Apns));
+    assert!(tenant.shows_badge(&ProviderKind::ApnsSandbox));
+    assert!(!tenant.shows_badge(&ProviderKind::Fcm));
+    assert!(!tenant.shows_badge(&ProviderKind::WebPush));
+
+    // Only FCM v1 forwards the badge
+    let tenant = ctx
+        .tenants
+        .update_tenant_fcm_v1(
+            &tenant.id,
+            TenantFcmV1UpdateParams {
+                fcm_v1_credentials: "test-credentials".to_string(),
+            },
+        )
+        .await
+        .expect("update failed");
+    assert!(tenant.shows_badge(&ProviderKind::Fcm));
+}
+
 #[test_context(StoreContext)]
 #[tokio::test]
 async fn tenant_delete_fcm_v1(ctx: &mut StoreContext) {
@@ -201,6 +238,48 @@ async fn tenant_delete_fcm_v1(ctx: &mut StoreContext) {
     assert_eq!(res.fcm_api_key, Some("test-api-key".to_owned()));
 }
 
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_webpush(ctx: &mut StoreContext) {
+    let tenant = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+
+    let res = ctx
+        .tenants
+        .update_tenant_webpush(
+            &tenant.id,
+            TenantWebPushUpdateParams {
+                vapid_public_key: "test-public-key".to_string(),
+                vapid_private_key: "test-private-key".to_string(),
+                vapid_subject: "mailto:test@example.com".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        res.webpush_vapid_public_key,
+        Some("test-public-key".to_owned())
+    );
+    assert_eq!(
+        res.webpush_vapid_subject,
+        Some("mailto:test@example.com".to_owned())
+    );
+
+    let res = ctx
+        .tenants
+        .update_tenant_delete_webpush(&tenant.id)
+        .await
+        .unwrap();
+    assert_eq!(res.webpush_vapid_public_key, None);
+    assert_eq!(res.webpush_vapid_private_key, None);
+    assert_eq!(res.webpush_vapid_subject, None);
+}
+
 #[test_context(StoreContext)]
 #[tokio::test]
 async fn tenant_apns(ctx: &mut StoreContext) {
@@ -401,3 +480,494 @@ async fn tenant_delete_apns_token_auth(ctx: &mut StoreContext) {
     assert_eq!(res.apns_certificate, None);
     assert_eq!(res.apns_certificate_password, None);
 }
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_settings(ctx: &mut StoreContext) {
+    let id = Uuid::new_v4().to_string();
+
+    let tenant = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams { id: id.clone() })
+        .await
+        .expect("creation failed");
+    assert_eq!(tenant.coalesce_window(), None);
+    assert!(!tenant.apns_environment_fallback);
+    assert_eq!(tenant.client_auth_mode, None);
+
+    let tenant = ctx
+        .tenants
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: Some(Some(30)),
+                apns_environment_fallback: Some(true),
+                client_auth_mode: Some(Some(ClientAuthMode::Enforce)),
+            },
+        )
+        .await
+        .expect("update failed");
+    assert_eq!(tenant.coalesce_window(), Some(Duration::from_secs(30)));
+    assert!(tenant.apns_environment_fallback);
+    assert_eq!(tenant.client_auth_mode, Some(ClientAuthMode::Enforce));
+
+    let tenant = ctx
+        .tenants
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: Some(None),
+                apns_environment_fallback: Some(false),
+                client_auth_mode: Some(None),
+            },
+        )
+        .await
+        .expect("update failed");
+    assert_eq!(tenant.coalesce_window(), None);
+    assert!(!tenant.apns_environment_fallback);
+    assert_eq!(tenant.client_auth_mode, None);
+
+    // Settings left out are unchanged
+    ctx.tenants
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: Some(Some(30)),
+                apns_environment_fallback: Some(true),
+                client_auth_mode: Some(Some(ClientAuthMode::Enforce)),
+            },
+        )
+        .await
+        .expect("update failed");
+    let tenant = ctx
+        .tenants
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: None,
+                apns_environment_fallback: Some(false),
+                client_auth_mode: None,
+            },
+        )
+        .await
+        .expect("update failed");
+    assert_eq!(tenant.coalesce_window(), Some(Duration::from_secs(30)));
+    assert!(!tenant.apns_environment_fallback);
+    assert_eq!(tenant.client_auth_mode, Some(ClientAuthMode::Enforce));
+    let tenant = ctx
+        .tenants
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: None,
+                apns_environment_fallback: None,
+                client_auth_mode: None,
+            },
+        )
+        .await
+        .expect("update failed");
+    assert_eq!(tenant.coalesce_window(), Some(Duration::from_secs(30)));
+    assert!(!tenant.apns_environment_fallback);
+    assert_eq!(tenant.client_auth_mode, Some(ClientAuthMode::Enforce));
+
+    // Updating only the auth mode keeps the other settings
+    let tenant = ctx
+        .tenants
+        .update_tenant_settings(
+            &id,
+            TenantSettingsUpdateParams {
+                coalesce_window_secs: None,
+                apns_environment_fallback: None,
+                client_auth_mode: Some(Some(ClientAuthMode::Warn)),
+            },
+        )
+        .await
+        .expect("update failed");
+    assert_eq!(tenant.coalesce_window(), Some(Duration::from_secs(30)));
+    assert!(!tenant.apns_environment_fallback);
+    assert_eq!(tenant.client_auth_mode, Some(ClientAuthMode::Warn));
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_templates(ctx: &mut StoreContext) {
+    let id = Uuid::new_v4().to_string();
+    ctx.tenants
+        .create_tenant(TenantUpdateParams { id: id.clone() })
+        .await
+        .expect("creation failed");
+
+    let template = |tag: Option<i64>, locale: &str, title: &str| NotificationTemplate {
+        tag,
+        locale: locale.to_string(),
+        title: title.to_string(),
+        body: None,
+    };
+    let templates = vec![
+        template(None, "", "fallback"),
+        template(None, "de", "untagged de"),
+        template(Some(1100), "", "sign"),
+        template(Some(1100), "pt", "sign pt"),
+        template(Some(1100), "pt-br", "sign pt-br"),
+    ];
+    ctx.tenants
+        .set_templates(&id, &templates)
+        .await
+        .expect("update failed");
+    assert_eq!(ctx.tenants.get_templates(&id).await.unwrap(), templates);
+
+    let title = |tag: Option<u32>, locale: Option<&'static str>| {
+        let (tenants, id) = (ctx.tenants.clone(), id.clone());
+        async move {
+            tenants
+                .get_template(&id, tag, locale)
+                .await
+                .unwrap()
+                .map(|template| template.title)
+        }
+    };
+    assert_eq!(
+        title(Some(1100), Some("pt_BR")).await.unwrap(),
+        "sign pt-br"
+    );
+    assert_eq!(title(Some(1100), Some("pt-PT")).await.unwrap(), "sign pt");
+    assert_eq!(title(Some(1100), Some("de")).await.unwrap(), "sign");
+    assert_eq!(title(Some(1100), None).await.unwrap(), "sign");
+    // Untagged templates cover other tags and legacy messages
+    assert_eq!(
+        title(Some(1101), Some("de-AT")).await.unwrap(),
+        "untagged de"
+    );
+    assert_eq!(title(None, Some("fr")).await.unwrap(), "fallback");
+
+    // Setting templates replaces the existing ones
+    ctx.tenants
+        .set_templates(&id, &[template(Some(1100), "", "sign")])
+        .await
+        .expect("update failed");
+    assert_eq!(ctx.tenants.get_templates(&id).await.unwrap().len(), 1);
+    assert!(title(None, None).await.is_none());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_apns_topics(ctx: &mut StoreContext) {
+    let tenant = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+    assert!(tenant.apns_topics.is_empty());
+
+    ctx.tenants
+        .update_tenant_apns(
+            &tenant.id,
+            TenantApnsUpdateParams {
+                apns_topic: "com.walletconect.exampleapp".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    ctx.tenants
+        .update_tenant_apns_auth(
+            &tenant.id,
+            TenantApnsUpdateAuth::Token {
+                apns_pkcs8_pem: "example-pem-string".to_string(),
+                apns_key_id: "123".to_string(),
+                apns_team_id: "456".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let res = ctx
+        .tenants
+        .update_tenant_apns_topics(
+            &tenant.id,
+            &["com.walletconect.exampleapp.beta".to_string()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.apns_topics, vec!["com.walletconect.exampleapp.beta"]);
+
+    assert_eq!(
+        res.apns_topic_for(None).unwrap(),
+        "com.walletconect.exampleapp"
+    );
+    assert_eq!(
+        res.apns_topic_for(Some("com.walletconect.exampleapp.beta"))
+            .unwrap(),
+        "com.walletconect.exampleapp.beta"
+    );
+    assert!(matches!(
+        res.apns_topic_for(Some("com.walletconect.other")),
+        Err(Error::ApnsTopicNotConfigured(_))
+    ));
+
+    let res = ctx
+        .tenants
+        .update_tenant_delete_apns(&tenant.id)
+        .await
+        .unwrap();
+    assert!(res.apns_topics.is_empty());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_provider_suspension(ctx: &mut StoreContext) {
+    let tenant = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+    assert!(tenant.check_suspended(&ProviderKind::Apns).is_ok());
+
+    ctx.tenants
+        .suspend_provider(&tenant.id, &ProviderKind::Apns, "APNs certificate expired")
+        .await
+        .unwrap();
+    let res = ctx.tenants.get_tenant(&tenant.id).await.unwrap();
+    let suspension = res.provider_suspension(&ProviderKind::ApnsSandbox).unwrap();
+    assert_eq!(
+        suspension.reason.as_deref(),
+        Some("APNs certificate expired")
+    );
+
+    // Only the suspended provider is affected
+    assert!(matches!(
+        res.check_suspended(&ProviderKind::Apns),
+        Err(Error::ProviderSuspended(_))
+    ));
+    assert!(res.check_suspended(&ProviderKind::Fcm).is_ok());
+    assert!(!res.suspended);
+
+    // A repeated suspension keeps the original time
+    ctx.tenants
+        .suspend_provider(&tenant.id, &ProviderKind::Apns, "Invalid APNS Credentials")
+        .await
+        .unwrap();
+    let res = ctx.tenants.get_tenant(&tenant.id).await.unwrap();
+    let repeated = res.provider_suspension(&ProviderKind::Apns).unwrap();
+    assert_eq!(repeated.suspended_at, suspension.suspended_at);
+    assert_eq!(repeated.reason.as_deref(), Some("Invalid APNS Credentials"));
+
+    ctx.tenants
+        .unsuspend_provider(&tenant.id, &ProviderKind::Apns)
+        .await
+        .unwrap();
+    let res = ctx.tenants.get_tenant(&tenant.id).await.unwrap();
+    assert!(res.provider_suspension(&ProviderKind::Apns).is_none());
+    assert!(res.check_suspended(&ProviderKind::Apns).is_ok());
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_suspension_history(ctx: &mut StoreContext) {
+    let tenant = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+
+    // Repeated provider failures are recorded once
+    ctx.tenants
+        .suspend_provider(&tenant.id, &ProviderKind::Fcm, "Invalid FCM Credentials")
+        .await
+        .unwrap();
+    ctx.tenants
+        .suspend_provider(&tenant.id, &ProviderKind::Fcm, "Invalid FCM Credentials")
+        .await
+        .unwrap();
+    ctx.tenants
+        .unsuspend_provider(&tenant.id, &ProviderKind::Fcm)
+        .await
+        .unwrap();
+    ctx.tenants
+        .unsuspend_provider(&tenant.id, &ProviderKind::Fcm)
+        .await
+        .unwrap();
+
+    let res = ctx
+        .tenants
+        .suspend_tenant(&tenant.id, "abuse", "operator")
+        .await
+        .unwrap();
+    assert!(res.suspended);
+    assert_eq!(res.suspended_reason.as_deref(), Some("abuse"));
+    assert!(matches!(
+        res.check_suspended(&ProviderKind::Fcm),
+        Err(Error::TenantSuspended)
+    ));
+
+    let res = ctx
+        .tenants
+        .unsuspend_tenant(&tenant.id, "operator")
+        .await
+        .unwrap();
+    assert!(!res.suspended);
+    assert_eq!(res.suspended_reason, None);
+
+    let history = ctx
+        .tenants
+        .get_suspension_history(&tenant.id, 10, 0)
+        .await
+        .unwrap();
+    let events = history
+        .iter()
+        .map(|event| {
+            (
+                event.action,
+                event.provider.as_deref(),
+                event.reason.as_deref(),
+                event.actor.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        vec![
+            (SuspensionAction::Unsuspended, None, None, Some("operator")),
+            (
+                SuspensionAction::Suspended,
+                None,
+                Some("abuse"),
+                Some("operator")
+            ),
+            (SuspensionAction::Unsuspended, Some("fcm"), None, None),
+            (
+                SuspensionAction::Suspended,
+                Some("fcm"),
+                Some("Invalid FCM Credentials"),
+                None
+            ),
+        ]
+    );
+
+    let page = ctx
+        .tenants
+        .get_suspension_history(&tenant.id, 1, 1)
+        .await
+        .unwrap();
+    assert_eq!(page, history[1..2]);
+
+    assert!(matches!(
+        ctx.tenants
+            .suspend_tenant("missing", "abuse", "operator")
+            .await,
+        Err(Error::InvalidTenantId(_))
+    ));
+}
+
+/// Ids of the listed tenants among `ids`, other tests create tenants
+/// concurrently
+async fn list_tenant_ids(
+    ctx: &StoreContext,
+    filter: TenantListFilter,
+    ids: &[&str],
+) -> Vec<String> {
+    ctx.tenants
+        .list_tenants(&filter, 500, 0)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tenant| tenant.id)
+        .filter(|id| ids.contains(&id.as_str()))
+        .collect()
+}
+
+#[test_context(StoreContext)]
+#[tokio::test]
+async fn tenant_listing(ctx: &mut StoreContext) {
+    let since = Utc::now();
+    let plain = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+    let with_fcm = ctx
+        .tenants
+        .create_tenant(TenantUpdateParams {
+            id: Uuid::new_v4().to_string(),
+        })
+        .await
+        .expect("creation failed");
+    ctx.tenants
+        .update_tenant_fcm(
+            &with_fcm.id,
+            TenantFcmUpdateParams {
+                fcm_api_key: "key".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    ctx.tenants
+        .suspend_provider(&with_fcm.id, &ProviderKind::Fcm, "Invalid FCM Credentials")
+        .await
+        .unwrap();
+    let ids = [plain.id.as_str(), with_fcm.id.as_str()];
+
+    let all = list_tenant_ids(
+        ctx,
+        TenantListFilter {
+            updated_since: Some(since),
+            ..Default::default()
+        },
+        &ids,
+    )
+    .await;
+    let mut sorted = ids.map(ToString::to_string).to_vec();
+    sorted.sort();
+    assert_eq!(all, sorted);
+
+    let suspended = list_tenant_ids(
+        ctx,
+        TenantListFilter {
+            suspended: Some(true),
+            ..Default::default()
+        },
+        &ids,
+    )
+    .await;
+    assert_eq!(suspended, vec![with_fcm.id.clone()]);
+
+    let not_suspended = list_tenant_ids(
+        ctx,
+        TenantListFilter {
+            suspended: Some(false),
+            ..Default::default()
+        },
+        &ids,
+    )
+    .await;
+    assert_eq!(not_suspended, vec![plain.id.clone()]);
+
+    let fcm = list_tenant_ids(
+        ctx,
+        TenantListFilter {
+            provider: Some(ProviderKind::Fcm),
+            ..Default::default()
+        },
+        &ids,
+    )
+    .await;
+    assert_eq!(fcm, vec![with_fcm.id.clone()]);
+
+    let later = list_tenant_ids(
+        ctx,
+        TenantListFilter {
+            updated_since: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        },
+        &ids,
+    )
+    .await;
+    assert!(later.is_empty());
+}
@@ -0,0 +1,194 @@
+use {
+    chrono::{NaiveTime, TimeZone, Utc},
+    echo_server::{
+        error::Error,
+        handlers::client_settings::{ClientSettingsBody, QuietHours, MAX_FILTER_ENTRIES},
+        stores::client::{ClientSettings, QuietHoursMode},
+    },
+};
+
+const EXAMPLE_TOPIC: &str = "example-topic";
+
+#[test]
+pub fn default_settings_allow_everything() {
+    let settings = ClientSettings::default();
+
+    assert!(settings.allows(Some(4000), EXAMPLE_TOPIC));
+    assert!(settings.allows(None, EXAMPLE_TOPIC));
+}
+
+#[test]
+pub fn tag_filters() {
+    let settings: ClientSettings = ClientSettingsBody {
+        allowed_tags: Some(vec![4000, 4001]),
+        denied_tags: vec![4001],
+        ..Default::default()
+    }
+    .into();
+
+    assert!(settings.allows(Some(4000), EXAMPLE_TOPIC));
+    // Denied tags win over allowed tags
+    assert!(!settings.allows(Some(4001), EXAMPLE_TOPIC));
+    assert!(!settings.allows(Some(4002), EXAMPLE_TOPIC));
+    // Legacy messages have no tag
+    assert!(settings.allows(None, EXAMPLE_TOPIC));
+}
+
+#[test]
+pub fn muted_topics() {
+    let settings: ClientSettings = ClientSettingsBody {
+        muted_topics: vec![EXAMPLE_TOPIC.to_string()],
+        ..Default::default()
+    }
+    .into();
+
+    assert!(!settings.allows(Some(4000), EXAMPLE_TOPIC));
+    assert!(!settings.allows(None, EXAMPLE_TOPIC));
+    assert!(settings.allows(Some(4000), "other-topic"));
+}
+
+#[test]
+pub fn settings_body_round_trip() {
+    let body = ClientSettingsBody {
+        allowed_tags: Some(vec![u32::MAX]),
+        denied_tags: vec![0],
+        muted_topics: vec![EXAMPLE_TOPIC.to_string()],
+        timezone: Some("Europe/Berlin".to_string()),
+        quiet_hours: Some(QuietHours {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            mode: QuietHoursMode::Defer,
+        }),
+        locale: Some("pt-br".to_string()),
+    };
+
+    let settings: ClientSettings = body.clone().into();
+    assert_eq!(ClientSettingsBody::from(settings), body);
+}
+
+#[test]
+pub fn settings_body_validation() {
+    assert!(ClientSettingsBody::default().validate().is_ok());
+
+    let too_many = ClientSettingsBody {
+        denied_tags: vec![4000; MAX_FILTER_ENTRIES + 1],
+        ..Default::default()
+    };
+    assert!(matches!(
+        too_many.validate(),
+        Err(Error::InvalidClientSettings(_))
+    ));
+
+    let empty_topic = ClientSettingsBody {
+        muted_topics: vec![String::new()],
+        ..Default::default()
+    };
+    assert!(matches!(
+        empty_topic.validate(),
+        Err(Error::InvalidClientSettings(_))
+    ));
+}
+
+fn quiet_hours(timezone: &str, start: (u32, u32), end: (u32, u32)) -> ClientSettings {
+    ClientSettingsBody {
+        timezone: Some(timezone.to_string()),
+        quiet_hours: Some(QuietHours {
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+            mode: QuietHoursMode::Silent,
+        }),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[test]
+pub fn quiet_hours_within_day() {
+    let settings = quiet_hours("UTC", (12, 0), (14, 0));
+
+    let now = Utc.with_ymd_and_hms(2024, 1, 10, 13, 0, 0).unwrap();
+    assert_eq!(
+        settings.quiet_hours_end(now),
+        Some(Utc.with_ymd_and_hms(2024, 1, 10, 14, 0, 0).unwrap())
+    );
+
+    let now = Utc.with_ymd_and_hms(2024, 1, 10, 14, 0, 0).unwrap();
+    assert_eq!(settings.quiet_hours_end(now), None);
+    let now = Utc.with_ymd_and_hms(2024, 1, 10, 11, 59, 0).unwrap();
+    assert_eq!(settings.quiet_hours_end(now), None);
+}
+
+#[test]
+pub fn quiet_hours_past_midnight() {
+    // 22:00 to 07:00 in Berlin, which is UTC+1 in January
+    let settings = quiet_hours("Europe/Berlin", (22, 0), (7, 0));
+
+    let before_midnight = Utc.with_ymd_and_hms(2024, 1, 10, 22, 30, 0).unwrap();
+    assert_eq!(
+        settings.quiet_hours_end(before_midnight),
+        Some(Utc.with_ymd_and_hms(2024, 1, 11, 6, 0, 0).unwrap())
+    );
+
+    let after_midnight = Utc.with_ymd_and_hms(2024, 1, 11, 3, 0, 0).unwrap();
+    assert_eq!(
+        settings.quiet_hours_end(after_midnight),
+        Some(Utc.with_ymd_and_hms(2024, 1, 11, 6, 0, 0).unwrap())
+    );
+
+    let daytime = Utc.with_ymd_and_hms(2024, 1, 11, 12, 0, 0).unwrap();
+    assert_eq!(settings.quiet_hours_end(daytime), None);
+}
+
+#[test]
+pub fn quiet_hours_validation() {
+    let without_timezone = ClientSettingsBody {
+        quiet_hours: Some(QuietHours {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            mode: QuietHoursMode::Silent,
+        }),
+        ..Default::default()
+    };
+    assert!(matches!(
+        without_timezone.validate(),
+        Err(Error::InvalidClientSettings(_))
+    ));
+
+    let unknown_timezone = ClientSettingsBody {
+        timezone: Some("Mars/Olympus_Mons".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        unknown_timezone.validate(),
+        Err(Error::InvalidClientSettings(_))
+    ));
+
+    let body: ClientSettingsBody = serde_json::from_str(
+        r#"{"timezone":"Europe/Berlin","quiet_hours":{"start":"22:00","end":"07:00"}}"#,
+    )
+    .unwrap();
+    assert!(body.validate().is_ok());
+    assert_eq!(body.quiet_hours.unwrap().mode, QuietHoursMode::Silent);
+}
+
+#[test]
+pub fn locale_validation() {
+    let body = ClientSettingsBody {
+        locale: Some("pt_BR".to_string()),
+        ..Default::default()
+    };
+    assert!(body.validate().is_ok());
+    let settings: ClientSettings = body.into();
+    assert_eq!(settings.locale.as_deref(), Some("pt-br"));
+
+    for locale in ["", "-", "en us", "x".repeat(36).as_str()] {
+        let body = ClientSettingsBody {
+            locale: Some(locale.to_string()),
+            ..Default::default()
+        };
+        assert!(
+            matches!(body.validate(), Err(Error::InvalidClientSettings(_))),
+            "{locale:?} must be rejected"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use {
+    base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine},
+    echo_server::jwt_validation::{
+        parse_jwks, Claims, JwksSource, JwtValidationClient, TenantScope,
+    },
+    ed25519_dalek::SigningKey,
+    jsonwebtoken::{encode, Algorithm, EncodingKey, Header},
+    rand::rngs::OsRng,
+    serde::Serialize,
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Serialize)]
+struct TokenClaims<'a> {
+    sub: &'a str,
+    exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<&'a str>,
+}
+
+impl<'a> TokenClaims<'a> {
+    fn new(sub: &'a str) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        TokenClaims {
+            sub,
+            exp: now.as_secs() + 60 * 60,
+            aud: None,
+            iss: None,
+            scope: None,
+        }
+    }
+}
+
+/// An Ed25519 key pair as a jsonwebtoken encoding key and its JWKS document
+fn generate_ed25519_key(kid: &str) -> (EncodingKey, String) {
+    let signing_key = SigningKey::generate(&mut OsRng);
+
+    // PKCS#8 v1 wrapping of the seed
+    let mut der = hex::decode("302e020100300506032b657004220420").unwrap();
+    der.extend_from_slice(&signing_key.to_bytes());
+
+    let jwks = format!(
+        r#"{{"keys": [{{"kty": "OKP", "crv": "Ed25519", "kid": "{kid}", "x": "{}"}}]}}"#,
+        URL_SAFE_NO_PAD.encode(signing_key.verifying_key().to_bytes())
+    );
+
+    (EncodingKey::from_ed_der(&der), jwks)
+}
+
+fn eddsa_header(kid: &str) -> Header {
+    Header {
+        kid: Some(kid.to_string()),
+        ..Header::new(Algorithm::EdDSA)
+    }
+}
+
+fn claims(scope: Option<&str>) -> Claims {
+    Claims {
+        sub: "tenant".to_string(),
+        scope: scope.map(ToString::to_string),
+    }
+}
+
+#[test]
+pub fn scopes() {
+    let read_only = claims(Some("tenant:read"));
+    assert!(read_only.has_scope(TenantScope::Read, true));
+    assert!(!read_only.has_scope(TenantScope::Write, true));
+
+    let write = claims(Some("other tenant:write"));
+    assert!(write.has_scope(TenantScope::Read, true));
+    assert!(write.has_scope(TenantScope::Write, true));
+
+    assert!(!claims(Some("")).has_scope(TenantScope::Read, false));
+
+    // Tokens without scopes predate them
+    let unscoped = claims(None);
+    assert!(unscoped.has_scope(TenantScope::Write, false));
+    assert!(!unscoped.has_scope(TenantScope::Read, true));
+}
+
+#[test]
+pub fn hs256_token() {
+    let client = JwtValidationClient::new("secret".to_string());
+    let token = |secret: &str| {
+        encode(
+            &Header::default(),
+            &TokenClaims::new("tenant"),
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    };
+
+    let token_data = client.is_valid_token(token("secret")).unwrap();
+    assert_eq!(token_data.claims.sub, "tenant");
+    assert_eq!(token_data.claims.scope, None);
+    assert!(client.is_valid_token(token("other")).is_err());
+
+    // Without a secret only JWKS keys are accepted
+    let client = JwtValidationClient::new(String::new());
+    assert!(client.is_valid_token(token("")).is_err());
+}
+
+#[test]
+pub fn eddsa_token_from_jwks() {
+    let (encoding_key, jwks) = generate_ed25519_key("key-1");
+    let client = JwtValidationClient::new("secret".to_string());
+    let token = encode(
+        &eddsa_header("key-1"),
+        &TokenClaims {
+            scope: Some("tenant:read"),
+            ..TokenClaims::new("tenant")
+        },
+        &encoding_key,
+    )
+    .unwrap();
+
+    // Not accepted until the JWKS is loaded
+    assert!(client.is_valid_token(token.clone()).is_err());
+
+    let keys = parse_jwks(&jwks).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].algorithm, Algorithm::EdDSA);
+    assert_eq!(keys[0].key_id.as_deref(), Some("key-1"));
+    client.set_jwks_keys(keys);
+
+    let token_data = client.is_valid_token(token).unwrap();
+    assert_eq!(token_data.claims.scope.as_deref(), Some("tenant:read"));
+
+    // Signed by a key that isn't in the JWKS
+    let (other_key, _) = generate_ed25519_key("key-1");
+    let forged = encode(
+        &eddsa_header("key-1"),
+        &TokenClaims::new("tenant"),
+        &other_key,
+    )
+    .unwrap();
+    assert!(client.is_valid_token(forged).is_err());
+
+    // Unknown key id
+    let unknown = encode(
+        &eddsa_header("key-2"),
+        &TokenClaims::new("tenant"),
+        &encoding_key,
+    )
+    .unwrap();
+    assert!(client.is_valid_token(unknown).is_err());
+}
+
+#[test]
+pub fn audience_and_issuer() {
+    let (encoding_key, jwks) = generate_ed25519_key("key-1");
+    let client = JwtValidationClient::new(String::new())
+        .with_audience(Some("push".to_string()))
+        .with_issuer(Some("cloud".to_string()));
+    client.set_jwks_keys(parse_jwks(&jwks).unwrap());
+    let token = |aud, iss| {
+        encode(
+            &eddsa_header("key-1"),
+            &TokenClaims {
+                aud,
+                iss,
+                ..TokenClaims::new("tenant")
+            },
+            &encoding_key,
+        )
+        .unwrap()
+    };
+
+    assert!(client
+        .is_valid_token(token(Some("push"), Some("cloud")))
+        .is_ok());
+    assert!(client
+        .is_valid_token(token(Some("other"), Some("cloud")))
+        .is_err());
+    assert!(client
+        .is_valid_token(token(Some("push"), Some("other")))
+        .is_err());
+    assert!(client.is_valid_token(token(None, Some("cloud"))).is_err());
+    assert!(client.is_valid_token(token(Some("push"), None)).is_err());
+}
+
+#[test]
+pub fn symmetric_jwks_keys_are_skipped() {
+    let keys = parse_jwks(r#"{"keys": [{"kty": "oct", "k": "c2VjcmV0"}]}"#).unwrap();
+    assert!(keys.is_empty());
+    assert!(parse_jwks("not json").is_err());
+}
+
+#[tokio::test]
+pub async fn fetch_jwks_from_file() {
+    let (_, jwks) = generate_ed25519_key("key-1");
+    let path = std::env::temp_dir().join(format!("jwks-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&path, &jwks).await.unwrap();
+
+    let source = JwksSource::parse(&format!("file://{}", path.display()));
+    assert_eq!(source, JwksSource::File(path.to_string_lossy().to_string()));
+    let keys = source.fetch(&reqwest::Client::new()).await.unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key_id.as_deref(), Some("key-1"));
+}
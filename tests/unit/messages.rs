@@ -1,6 +1,13 @@
 use echo_server::{
-    blob::{DecryptedPayloadBlob, ENCRYPTED_FLAG},
-    providers::MessagePayload,
+    blob::{DecryptedPayloadBlob, ENCRYPTED_FLAG, IMAGE_DATA_KEY, URL_DATA_KEY},
+    error::Error,
+    handlers::{
+        push_batch::BatchPushMessageBody,
+        push_message::{PushMessageBody, REDACTED},
+    },
+    providers::{
+        DeliveryOptions, DeliveryPriority, DeliveryPushType, MessagePayload, MAX_TTL_SECS,
+    },
 };
 
 const EXAMPLE_TOPIC: &str = "example-topic";
@@ -74,3 +81,165 @@ pub fn parse_encoded_blob() {
         }
     )
 }
+
+#[test]
+pub fn blob_rich_data() {
+    use base64::Engine as _;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(
+        serde_json::json!({
+            "title": EXAMPLE_CLEARTEXT_BLOB_TITLE,
+            "body": EXAMPLE_CLEARTEXT_BLOB_BODY,
+            "image": "https://example.com/image.png",
+            "url": "wc://example",
+        })
+        .to_string(),
+    );
+    let blob = DecryptedPayloadBlob::from_base64_encoded(&encoded).expect("Failed to parse blob");
+
+    let data = blob.rich_data();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[IMAGE_DATA_KEY], "https://example.com/image.png");
+    assert_eq!(data[URL_DATA_KEY], "wc://example");
+
+    // Keys are left out rather than sent empty
+    let blob = DecryptedPayloadBlob::from_base64_encoded(EXAMPLE_CLEARTEXT_ENCODED_BLOB)
+        .expect("Failed to parse encoded blob");
+    assert!(blob.rich_data().is_empty());
+}
+
+#[test]
+pub fn parse_delivery_options() {
+    let body: PushMessageBody = serde_json::from_value(serde_json::json!({
+        "topic": EXAMPLE_TOPIC,
+        "tag": 1100,
+        "message": EXAMPLE_ENCRYPTED_BLOB,
+        "options": {
+            "ttl": 3600,
+            "priority": "normal",
+            "collapse_key": "chat",
+            "push_type": "background"
+        }
+    }))
+    .expect("Failed to parse body");
+
+    let options = body.options.expect("Missing options");
+    assert_eq!(
+        options,
+        DeliveryOptions {
+            ttl: Some(3600),
+            priority: Some(DeliveryPriority::Normal),
+            collapse_key: Some("chat".to_string()),
+            push_type: Some(DeliveryPushType::Background),
+            badge: None,
+            alert: None,
+        }
+    );
+    assert!(body.raw.is_some());
+}
+
+#[test]
+pub fn background_delivery_is_normal_priority() {
+    let options = DeliveryOptions {
+        priority: Some(DeliveryPriority::High),
+        push_type: Some(DeliveryPushType::Background),
+        ..Default::default()
+    };
+
+    assert_eq!(options.priority(), Some(DeliveryPriority::Normal));
+}
+
+#[test]
+pub fn delivery_ttl_is_capped() {
+    let options = DeliveryOptions {
+        ttl: Some(u64::MAX),
+        ..Default::default()
+    };
+    assert_eq!(options.ttl(), Some(MAX_TTL_SECS));
+
+    let options = DeliveryOptions {
+        ttl: Some(3600),
+        ..Default::default()
+    };
+    assert_eq!(options.ttl(), Some(3600));
+}
+
+#[test]
+pub fn validate_delivery_options_collapse_key() {
+    let options = DeliveryOptions {
+        collapse_key: Some("a".repeat(65)),
+        ..Default::default()
+    };
+
+    assert!(options.validate().is_err());
+    assert!(DeliveryOptions::default().validate().is_ok());
+}
+
+#[test]
+pub fn redacted_body_strips_message_contents() {
+    let body: PushMessageBody = serde_json::from_value(serde_json::json!({
+        "id": "legacy-id",
+        "payload": {
+            "topic": EXAMPLE_TOPIC,
+            "flags": 0,
+            "blob": EXAMPLE_CLEARTEXT_ENCODED_BLOB
+        },
+        "topic": EXAMPLE_TOPIC,
+        "tag": 4002,
+        "message": "raw-message"
+    }))
+    .expect("Failed to parse body");
+
+    let redacted = body.redacted();
+
+    let raw = redacted.raw.expect("Missing raw message");
+    assert_eq!(&*raw.message, REDACTED);
+    assert_eq!(&*raw.topic, EXAMPLE_TOPIC);
+    assert_eq!(raw.tag, 4002);
+
+    let legacy = redacted.legacy.expect("Missing legacy message");
+    assert_eq!(&*legacy.payload.blob, REDACTED);
+    assert_eq!(&*legacy.id, "legacy-id");
+    assert_eq!(&*legacy.payload.topic, EXAMPLE_TOPIC);
+}
+
+#[test]
+pub fn parse_batch_body() {
+    let body: BatchPushMessageBody = serde_json::from_value(serde_json::json!({
+        "messages": [
+            {
+                "client_id": "did:key:z6Mk-client",
+                "message": {
+                    "topic": EXAMPLE_TOPIC,
+                    "tag": 4002,
+                    "message": "raw-message"
+                }
+            },
+            {
+                "client_id": "client",
+                "message": {
+                    "id": "legacy-id",
+                    "payload": {
+                        "topic": EXAMPLE_TOPIC,
+                        "flags": ENCRYPTED_FLAG,
+                        "blob": EXAMPLE_ENCRYPTED_BLOB
+                    }
+                }
+            }
+        ]
+    }))
+    .expect("Failed to parse batch body");
+
+    assert_eq!(body.messages.len(), 2);
+    assert!(body.messages[0].message.raw.is_some());
+    assert!(body.messages[1].message.legacy.is_some());
+}
+
+#[test]
+pub fn batch_entry_errors_use_response_names() {
+    let response = serde_json::to_value(Error::InvalidBatch("empty".to_string()).as_response())
+        .expect("Failed to serialize response");
+
+    assert_eq!(response["status"], "FAILURE");
+    assert_eq!(response["errors"][0]["name"], "invalid_batch");
+}
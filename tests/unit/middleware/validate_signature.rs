@@ -1,7 +1,8 @@
 use {
-    echo_server::middleware::validate_signature::signature_is_valid,
+    echo_server::middleware::validate_signature::{signature_is_valid, ReplayCache},
     ed25519_dalek::{Signer, SigningKey, VerifyingKey},
     rand::rngs::OsRng,
+    std::time::{Duration, UNIX_EPOCH},
 };
 
 /// Setup for tests by creating a public key and returning a signature,
@@ -71,3 +72,48 @@ pub async fn invalid_signature_hex() {
     // Note: should be a from slice error as the signature
     assert!(error.is_ed_25519());
 }
+
+#[test]
+pub fn signature_timestamp_freshness() {
+    let cache = ReplayCache::new(Duration::from_secs(300));
+    let now = UNIX_EPOCH + Duration::from_secs(1692442800);
+
+    assert!(cache.timestamp_is_fresh("1692442800", now));
+    assert!(cache.timestamp_is_fresh("1692442500", now));
+    assert!(cache.timestamp_is_fresh("1692443100", now));
+
+    // Outside the clock skew in either direction
+    assert!(!cache.timestamp_is_fresh("1692442499", now));
+    assert!(!cache.timestamp_is_fresh("1692443101", now));
+
+    // Not a unix timestamp
+    assert!(!cache.timestamp_is_fresh("2023-08-19T11:00:00Z", now));
+    assert!(!cache.timestamp_is_fresh("", now));
+}
+
+#[tokio::test]
+pub async fn replayed_signature() {
+    let (_, signature, timestamp, _) = setup();
+    let cache = ReplayCache::new(Duration::from_secs(300));
+
+    assert!(cache.insert(&signature, &timestamp).await);
+    assert!(!cache.insert(&signature, &timestamp).await);
+    // Hex is case-insensitive
+    assert!(!cache.insert(&signature.to_uppercase(), &timestamp).await);
+
+    // The same signature with another timestamp can't be valid, but is a
+    // separate entry
+    assert!(cache.insert(&signature, "1692442801").await);
+}
+
+#[tokio::test]
+pub async fn forgotten_signature() {
+    let (_, signature, timestamp, _) = setup();
+    let cache = ReplayCache::new(Duration::from_secs(300));
+
+    assert!(cache.insert(&signature, &timestamp).await);
+    // The request failed, the retry is accepted
+    cache.remove(&signature.to_uppercase(), &timestamp).await;
+    assert!(cache.insert(&signature, &timestamp).await);
+    assert!(!cache.insert(&signature, &timestamp).await);
+}
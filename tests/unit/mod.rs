@@ -1,2 +1,8 @@
+mod client_settings;
+mod jwt_validation;
 mod messages;
 mod middleware;
+mod provider_cache;
+mod relay;
+mod retry;
+mod webpush;
@@ -0,0 +1,42 @@
+use echo_server::providers::{
+    cache::{ProviderCache, ProviderCacheKey},
+    fcm::FcmProvider,
+    Provider, ProviderKind,
+};
+
+const TENANT_ID: &str = "example-tenant";
+
+#[test]
+pub fn cache_key_depends_on_credentials() {
+    let key = ProviderCacheKey::new(TENANT_ID, ProviderKind::Fcm, &["api-key"]);
+
+    assert_eq!(
+        key,
+        ProviderCacheKey::new(TENANT_ID, ProviderKind::Fcm, &["api-key"])
+    );
+    assert_ne!(
+        key,
+        ProviderCacheKey::new(TENANT_ID, ProviderKind::Fcm, &["other-api-key"])
+    );
+    assert_ne!(
+        ProviderCacheKey::new(TENANT_ID, ProviderKind::Apns, &["ab", "c"]),
+        ProviderCacheKey::new(TENANT_ID, ProviderKind::Apns, &["a", "bc"])
+    );
+}
+
+#[tokio::test]
+pub async fn invalidate_tenant_evicts_only_that_tenant() {
+    let cache = ProviderCache::new();
+    let key = ProviderCacheKey::new(TENANT_ID, ProviderKind::Fcm, &["api-key"]);
+    let other_key = ProviderCacheKey::new("other-tenant", ProviderKind::Fcm, &["api-key"]);
+    let provider = Provider::Fcm(FcmProvider::new("api-key".to_string()));
+
+    cache.insert(key.clone(), provider.clone()).await;
+    cache.insert(other_key.clone(), provider).await;
+    assert!(cache.get(&key).await.is_some());
+
+    cache.invalidate_tenant(TENANT_ID);
+
+    assert!(cache.get(&key).await.is_none());
+    assert!(cache.get(&other_key).await.is_some());
+}
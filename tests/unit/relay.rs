@@ -0,0 +1,122 @@
+use {
+    axum::{routing::get, Router},
+    chrono::{Duration, TimeZone, Utc},
+    echo_server::relay::{parse_relay_keys, RelayClient, RelayKey, RelayKeySource},
+    ed25519_dalek::SigningKey,
+    rand::rngs::OsRng,
+    tokio::net::TcpListener,
+};
+
+fn generate_key() -> String {
+    hex::encode(SigningKey::generate(&mut OsRng).verifying_key().to_bytes())
+}
+
+#[test]
+pub fn parse_single_hex_key() {
+    let key = generate_key();
+
+    let keys = parse_relay_keys(&format!("{key}\n")).unwrap();
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(hex::encode(keys[0].public_key.to_bytes()), key);
+    assert_eq!(keys[0].not_before, None);
+    assert_eq!(keys[0].not_after, None);
+}
+
+#[test]
+pub fn parse_key_set() {
+    let (old, new) = (generate_key(), generate_key());
+    let document = format!(
+        r#"{{"keys": [
+            {{"public_key": "{old}", "not_after": "2024-01-01T00:00:00Z"}},
+            {{"public_key": "{new}", "not_before": "2023-12-01T00:00:00Z"}}
+        ]}}"#
+    );
+
+    let keys = parse_relay_keys(&document).unwrap();
+
+    assert_eq!(keys.len(), 2);
+    assert_eq!(
+        keys[0].not_after,
+        Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+    );
+    assert_eq!(
+        keys[1].not_before,
+        Some(Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap())
+    );
+}
+
+#[test]
+pub fn invalid_key_length_is_an_error() {
+    assert!(parse_relay_keys("ff469faa").is_err());
+    assert!(RelayClient::new("ff469faa".to_string()).is_err());
+    assert!(parse_relay_keys(r#"{"keys": [{"public_key": "not-hex"}]}"#).is_err());
+}
+
+#[test]
+pub fn verifying_keys_respect_validity_windows() {
+    let now = Utc::now();
+    let static_key = generate_key();
+    let client = RelayClient::new(format!("{static_key}, {}", generate_key())).unwrap();
+    assert_eq!(client.get_verifying_keys(now).len(), 2);
+
+    let key = |not_before, not_after| RelayKey {
+        public_key: SigningKey::generate(&mut OsRng).verifying_key(),
+        not_before,
+        not_after,
+    };
+    let current = key(Some(now - Duration::days(1)), Some(now + Duration::days(1)));
+    client.set_loaded_keys(vec![
+        current.clone(),
+        key(None, Some(now - Duration::days(1))),
+        key(Some(now + Duration::days(1)), None),
+    ]);
+
+    let keys = client.get_verifying_keys(now);
+    assert_eq!(keys.len(), 3);
+    assert!(keys.contains(&current.public_key));
+
+    // A reload replaces the loaded keys but keeps the configured ones
+    client.set_loaded_keys(vec![]);
+    let keys = client.get_verifying_keys(now);
+    assert_eq!(keys.len(), 2);
+    assert_eq!(hex::encode(keys[0].to_bytes()), static_key);
+}
+
+#[tokio::test]
+pub async fn fetch_keys_from_url() {
+    let key = generate_key();
+    let document = format!(r#"{{"keys": [{{"public_key": "{key}"}}]}}"#);
+    let app = Router::new().route("/public-keys", get(move || async move { document }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let source = RelayKeySource::parse(&format!("http://{addr}/public-keys"));
+    assert!(matches!(source, RelayKeySource::Url(_)));
+    let keys = source.fetch(&reqwest::Client::new()).await.unwrap();
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(hex::encode(keys[0].public_key.to_bytes()), key);
+
+    let missing = RelayKeySource::parse(&format!("http://{addr}/missing"));
+    assert!(missing.fetch(&reqwest::Client::new()).await.is_err());
+}
+
+#[tokio::test]
+pub async fn fetch_keys_from_file() {
+    let key = generate_key();
+    let path = std::env::temp_dir().join(format!("relay-keys-{key}"));
+    tokio::fs::write(&path, &key).await.unwrap();
+
+    let source = RelayKeySource::parse(&format!("file://{}", path.display()));
+    assert_eq!(
+        source,
+        RelayKeySource::File(path.to_string_lossy().to_string())
+    );
+    let keys = source.fetch(&reqwest::Client::new()).await.unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(hex::encode(keys[0].public_key.to_bytes()), key);
+}
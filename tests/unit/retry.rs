@@ -0,0 +1,64 @@
+use {
+    echo_server::{error::Error, providers::retry::RetryPolicy},
+    std::time::Duration,
+};
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(1_000),
+        max_elapsed: Duration::from_secs(5),
+    }
+}
+
+#[test]
+pub fn backoff_grows_exponentially_up_to_cap() {
+    let policy = policy();
+
+    assert_eq!(policy.max_delay(1), Duration::from_millis(100));
+    assert_eq!(policy.max_delay(2), Duration::from_millis(200));
+    assert_eq!(policy.max_delay(3), Duration::from_millis(400));
+    assert_eq!(policy.max_delay(5), Duration::from_millis(1_000));
+    assert_eq!(policy.max_delay(100), Duration::from_millis(1_000));
+}
+
+#[test]
+pub fn backoff_is_jittered_within_bounds() {
+    let policy = policy();
+
+    for attempt in 1..10 {
+        assert!(policy.backoff(attempt) <= policy.max_delay(attempt));
+    }
+}
+
+#[test]
+pub fn no_retries_makes_a_single_attempt() {
+    let policy = RetryPolicy::no_retries();
+
+    assert_eq!(policy.max_attempts, 1);
+    assert_eq!(policy.max_delay(1), Duration::ZERO);
+}
+
+#[test]
+pub fn transient_errors_are_retryable() {
+    assert!(Error::WebPushResponse(503, None).is_retryable());
+    assert!(Error::WebPushResponse(429, None).is_retryable());
+    assert!(Error::ApnsResponse(a2::ErrorReason::ServiceUnavailable).is_retryable());
+}
+
+#[test]
+pub fn permanent_errors_are_not_retryable() {
+    assert!(!Error::BadDeviceToken("expired".to_string()).is_retryable());
+    assert!(!Error::BadApnsCredentials.is_retryable());
+    assert!(!Error::WebPushResponse(400, None).is_retryable());
+    assert!(!Error::ApnsResponse(a2::ErrorReason::BadDeviceToken).is_retryable());
+}
+
+#[test]
+pub fn retry_after_is_honored() {
+    let error = Error::WebPushResponse(429, Some(Duration::from_secs(3)));
+
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(3)));
+    assert_eq!(Error::WebPushResponse(503, None).retry_after(), None);
+}
@@ -0,0 +1,144 @@
+use {
+    base64::Engine as _,
+    echo_server::providers::webpush::{
+        encrypt_with_keys, is_public_ip, VapidKey, WebPushSubscription,
+    },
+    p256::SecretKey,
+    std::net::IpAddr,
+};
+
+// Test vector from RFC 8291 Appendix A
+const PLAINTEXT: &str = "When I grow up, I want to be a watermelon";
+const AS_PRIVATE: &str = "yfWPiYE-n46HLnH0KqZOF1fJJU3MYrct3AELtAQ-oRw";
+const UA_PUBLIC: &str =
+    "BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4";
+const AUTH_SECRET: &str = "BTBZMqHH6r4Tts7J_aSIgg";
+const SALT: &str = "DGv6ra1nlYgDCS1FRnbzlw";
+const AS_PUBLIC: &str =
+    "BP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A8";
+const EXPECTED: &str = "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN";
+
+fn decode(value: &str) -> Vec<u8> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(value)
+        .unwrap()
+}
+
+fn subscription_token(endpoint: &str, p256dh: &str, auth: &str) -> String {
+    serde_json::json!({
+        "endpoint": endpoint,
+        "keys": {
+            "p256dh": p256dh,
+            "auth": auth,
+        }
+    })
+    .to_string()
+}
+
+#[test]
+pub fn encrypts_rfc8291_test_vector() {
+    let as_secret = SecretKey::from_slice(&decode(AS_PRIVATE)).unwrap();
+    let salt: [u8; 16] = decode(SALT).try_into().unwrap();
+
+    let encrypted = encrypt_with_keys(
+        PLAINTEXT.as_bytes(),
+        &decode(UA_PUBLIC),
+        &decode(AUTH_SECRET),
+        &as_secret,
+        &salt,
+    )
+    .unwrap();
+
+    assert_eq!(encrypted, decode(EXPECTED));
+}
+
+#[test]
+pub fn vapid_key_derives_public_key() {
+    let key = VapidKey::new(AS_PRIVATE, "mailto:test@example.com".to_string()).unwrap();
+
+    assert_eq!(key.public_key(), AS_PUBLIC);
+}
+
+#[test]
+pub fn vapid_key_requires_contact_subject() {
+    assert!(VapidKey::new(AS_PRIVATE, "test@example.com".to_string()).is_err());
+}
+
+#[test]
+pub fn parses_valid_subscription() {
+    let token = subscription_token("https://push.example.com/abc", UA_PUBLIC, AUTH_SECRET);
+
+    let subscription = WebPushSubscription::from_token(&token).unwrap();
+
+    assert_eq!(subscription.endpoint, "https://push.example.com/abc");
+    assert_eq!(subscription.p256dh().unwrap(), decode(UA_PUBLIC));
+}
+
+#[test]
+pub fn rejects_invalid_subscriptions() {
+    // Not JSON
+    assert!(WebPushSubscription::from_token("not-a-subscription").is_err());
+    // Insecure endpoint
+    assert!(WebPushSubscription::from_token(&subscription_token(
+        "http://push.example.com/abc",
+        UA_PUBLIC,
+        AUTH_SECRET
+    ))
+    .is_err());
+    // Invalid key
+    assert!(WebPushSubscription::from_token(&subscription_token(
+        "https://push.example.com/abc",
+        AUTH_SECRET,
+        AUTH_SECRET
+    ))
+    .is_err());
+    // Short auth secret
+    assert!(WebPushSubscription::from_token(&subscription_token(
+        "https://push.example.com/abc",
+        UA_PUBLIC,
+        "BTBZMqHH"
+    ))
+    .is_err());
+}
+
+#[test]
+pub fn rejects_internal_endpoints() {
+    for endpoint in [
+        "https://127.0.0.1/abc",
+        "https://0x7f.1/abc",
+        "https://169.254.169.254/latest/meta-data",
+        "https://10.0.0.1/abc",
+        "https://[::1]/abc",
+        "https://localhost/abc",
+        "https://push.localhost/abc",
+        "https://metadata/abc",
+    ] {
+        assert!(
+            WebPushSubscription::from_token(&subscription_token(endpoint, UA_PUBLIC, AUTH_SECRET))
+                .is_err(),
+            "{endpoint} was accepted"
+        );
+    }
+}
+
+#[test]
+pub fn only_public_addresses_are_resolved() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public_ip(ip.parse::<IpAddr>().unwrap()), "{ip}");
+    }
+
+    assert!(is_public_ip("142.250.74.110".parse().unwrap()));
+    assert!(is_public_ip("2607:f8b0:4004:c07::71".parse().unwrap()));
+}